@@ -0,0 +1,9 @@
+pub mod budget;
+pub mod condense;
+pub mod evaluate;
+pub mod finalize;
+pub mod history;
+pub mod inputs;
+pub mod prompt;
+pub mod schedule;
+pub mod trigger;
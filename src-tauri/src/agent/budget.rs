@@ -0,0 +1,137 @@
+use crate::agent::prompt::{AgentTemplates, TemplateInput, TranscriptionInput};
+use crate::config::agents::Agent;
+use crate::llm::router::{get_context_length, LlmRouterState};
+use crate::llm::tokens::ModelFamily;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+// Left free in the context for the previous answer and the answer itself
+const ANSWER_RESERVE_TOKENS: usize = 1024;
+
+// Share of the context the prompt template alone may take before it is warned about
+const NEAR_LIMIT_PERCENT: usize = 80;
+
+/// How the prompt of an agent fits the context of the configured model
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTokenEstimate {
+    pub model_name: Option<String>,
+    // The prompt without any transcription or answers
+    pub template_tokens: usize,
+    // None if no model is configured or Ollama didn't tell
+    pub context_length: Option<usize>,
+    pub warning: Option<String>,
+}
+
+/// The configured model, counted in its own tokens
+pub struct ContextBudget {
+    pub model_name: Option<String>,
+    pub family: ModelFamily,
+    pub context_length: Option<usize>,
+}
+
+impl ContextBudget {
+    pub async fn configured(router: &LlmRouterState) -> Self {
+        let model_name = router
+            .ollama
+            .read()
+            .await
+            .as_ref()
+            .map(|ollama| ollama.model_name.clone());
+        let Some(model_name) = model_name else {
+            return Self {
+                model_name: None,
+                family: ModelFamily::default(),
+                context_length: None,
+            };
+        };
+        // Prompts still go out without a budget if Ollama can't be asked
+        let context_length = get_context_length(router, &model_name)
+            .await
+            .inspect_err(|e| warn!("Failed to get context length: {}", e))
+            .ok();
+        Self {
+            family: ModelFamily::from_model_name(&model_name),
+            model_name: Some(model_name),
+            context_length,
+        }
+    }
+
+    /// Tokens the prompt takes up before the history is added to it
+    pub fn prompt_tokens(&self, templates: &AgentTemplates, latest: &str) -> Result<usize, String> {
+        let prompt = templates.render_prompt(&TemplateInput {
+            transcription: TranscriptionInput {
+                latest: latest.to_string(),
+                ..TranscriptionInput::default()
+            },
+            ..TemplateInput::default()
+        })?;
+        Ok(self.family.estimate_tokens(&prompt))
+    }
+
+    /// Tokens left for the history next to a prompt of `prompt_tokens`, None without a limit
+    pub fn history_tokens(&self, prompt_tokens: usize) -> Option<usize> {
+        self.context_length.map(|context_length| {
+            context_length.saturating_sub(prompt_tokens + ANSWER_RESERVE_TOKENS)
+        })
+    }
+
+    pub fn template_warning(&self, template_tokens: usize) -> Option<String> {
+        let context_length = self.context_length?;
+        if template_tokens * 100 < context_length * NEAR_LIMIT_PERCENT {
+            return None;
+        }
+        Some(format!(
+            "The prompt template alone takes up about {} of the {} tokens {} takes in, \
+            leaving little room for the transcription",
+            template_tokens,
+            context_length,
+            self.model_name.as_deref().unwrap_or("the model"),
+        ))
+    }
+}
+
+/// Tokens the prompt of an agent takes up before any transcription, for the agent editor
+#[tauri::command]
+pub async fn estimate_prompt_tokens(
+    agent: Agent,
+    router: State<'_, LlmRouterState>,
+) -> Result<PromptTokenEstimate, String> {
+    let budget = ContextBudget::configured(&router).await;
+    let template_tokens = budget.prompt_tokens(&AgentTemplates::new(&agent)?, "")?;
+    Ok(PromptTokenEstimate {
+        warning: budget.template_warning(template_tokens),
+        model_name: budget.model_name,
+        template_tokens,
+        context_length: budget.context_length,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(context_length: Option<usize>) -> ContextBudget {
+        ContextBudget {
+            model_name: Some("llama3.2".to_string()),
+            family: ModelFamily::Llama,
+            context_length,
+        }
+    }
+
+    #[test]
+    fn leaves_room_for_the_answer() {
+        assert_eq!(budget(Some(4096)).history_tokens(1000), Some(2072));
+        assert_eq!(budget(Some(2048)).history_tokens(1500), Some(0));
+        assert_eq!(budget(None).history_tokens(1000), None);
+    }
+
+    #[test]
+    fn warns_about_templates_close_to_the_limit() {
+        assert!(budget(Some(4096)).template_warning(3000).is_none());
+        let warning = budget(Some(4096)).template_warning(3300).unwrap();
+        assert!(warning.contains("3300 of the 4096 tokens llama3.2"));
+        assert!(budget(None).template_warning(100000).is_none());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::agent::budget::ContextBudget;
+use crate::agent::prompt::AgentTemplates;
+use crate::config::agents::{Agent, HistorySettings, HistoryStrategy};
+use crate::llm::model::{load_language_model, LanguageModel, LlmBackend};
+use crate::llm::router::LlmRouterState;
+use crate::llm::tokens::{chars_to_tokens, ModelFamily};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+const SUMMARIES_HEADING: &str = "--- Summary of earlier in the meeting ---";
+const TRANSCRIPT_HEADING: &str = "--- Transcript ---";
+
+/// History of an agent kept within its budget, passed back in to condense the next history
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CondensedHistory {
+    // Oldest first
+    pub summaries: Vec<HistorySummary>,
+    // Lines at the start of the history the summaries cover
+    pub summarized_lines: usize,
+    // The history as the agent is given it
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySummary {
+    // 0 for a summary of transcript, one more for each time summaries were merged into it
+    pub level: u32,
+    pub text: String,
+}
+
+/// How the history of an agent is kept within the prompt, None to give all of it
+pub fn history_settings(agent: &Agent) -> Option<HistorySettings> {
+    agent.history.clone().or_else(|| {
+        agent
+            .transcription_history_max_chars
+            .map(|max_chars| HistorySettings {
+                strategy: HistoryStrategy::Truncate,
+                token_budget: chars_to_tokens(max_chars as usize),
+            })
+    })
+}
+
+/// Condenses the history of the prompter of an agent window, which keeps the result for next time.
+/// The history also stays within what the context of the model leaves next to the prompt.
+#[tauri::command]
+pub async fn condense_transcription_history(
+    agent: Agent,
+    lines: Vec<String>,
+    latest: String,
+    previous: Option<CondensedHistory>,
+    router: State<'_, LlmRouterState>,
+) -> Result<CondensedHistory, String> {
+    let budget = ContextBudget::configured(&router).await;
+    let prompt_tokens = budget.prompt_tokens(&AgentTemplates::new(&agent)?, &latest)?;
+    let history_tokens = budget.history_tokens(prompt_tokens);
+    if history_tokens == Some(0) {
+        warn!(
+            "The prompt takes up all of the context of {}, the history is left out",
+            budget.model_name.as_deref().unwrap_or("the model")
+        );
+    }
+    let settings = match (history_settings(&agent), history_tokens) {
+        (Some(settings), Some(history_tokens)) => HistorySettings {
+            token_budget: settings.token_budget.min(history_tokens),
+            ..settings
+        },
+        (Some(settings), None) => settings,
+        (None, Some(history_tokens)) => HistorySettings {
+            strategy: HistoryStrategy::Truncate,
+            token_budget: history_tokens,
+        },
+        (None, None) => {
+            return Ok(CondensedHistory {
+                text: lines.join("\n"),
+                ..CondensedHistory::default()
+            })
+        }
+    };
+    // Only summaries need a model
+    let model = match settings.strategy {
+        HistoryStrategy::Truncate => None,
+        HistoryStrategy::RollingSummary | HistoryStrategy::HierarchicalSummary => {
+            Some(load_language_model(&LlmBackend::Configured, &router).await?)
+        }
+    };
+    condense_history(
+        &lines,
+        previous.unwrap_or_default(),
+        &settings,
+        budget.family,
+        model.as_deref(),
+    )
+    .await
+}
+
+/// Keeps the newest lines word for word and, unless truncating, summarizes older ones once
+/// they no longer fit. Summaries are only made for lines that weren't summarized before.
+pub async fn condense_history(
+    lines: &[String],
+    mut condensed: CondensedHistory,
+    settings: &HistorySettings,
+    family: ModelFamily,
+    model: Option<&dyn LanguageModel>,
+) -> Result<CondensedHistory, String> {
+    // The history was started over since
+    if condensed.summarized_lines > lines.len() {
+        condensed = CondensedHistory::default();
+    }
+    let budget = settings.token_budget;
+    let model = match (settings.strategy, model) {
+        // Nothing would fit next to summaries either
+        _ if budget == 0 => return Ok(CondensedHistory::default()),
+        (HistoryStrategy::Truncate, _) | (_, None) => {
+            let kept = newest_within(lines, budget, family);
+            return Ok(CondensedHistory {
+                text: lines[lines.len() - kept..].join("\n"),
+                ..CondensedHistory::default()
+            });
+        }
+        (_, Some(model)) => model,
+    };
+
+    let recent = &lines[condensed.summarized_lines..];
+    if family.estimate_tokens(&render(&condensed.summaries, recent)) > budget {
+        // Half of the budget stays for what was said most recently
+        let recent_budget = budget - budget / 2;
+        let summaries_budget = (budget / 2).saturating_sub(headings_tokens(family));
+        let older_count = recent.len() - newest_within(recent, recent_budget, family);
+        if older_count > 0 {
+            let older = recent[..older_count].join("\n");
+            match settings.strategy {
+                HistoryStrategy::RollingSummary => {
+                    let earlier = condensed
+                        .summaries
+                        .iter()
+                        .map(|summary| summary.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let prompt = if earlier.is_empty() {
+                        summarize_prompt(&older, summaries_budget)
+                    } else {
+                        update_summary_prompt(&earlier, &older, summaries_budget)
+                    };
+                    condensed.summaries = vec![HistorySummary {
+                        level: 0,
+                        text: model.talk(&prompt, None).await?.trim().to_string(),
+                    }];
+                }
+                HistoryStrategy::HierarchicalSummary => {
+                    let prompt = summarize_prompt(&older, summaries_budget / 2);
+                    condensed.summaries.push(HistorySummary {
+                        level: 0,
+                        text: model.talk(&prompt, None).await?.trim().to_string(),
+                    });
+                    // The oldest parts of the meeting end up in the coarsest summaries
+                    while condensed.summaries.len() > 1
+                        && summaries_tokens(&condensed.summaries, family) > summaries_budget
+                    {
+                        let first = condensed.summaries.remove(0);
+                        let second = condensed.summaries.remove(0);
+                        let prompt = summarize_prompt(
+                            &format!("{}\n{}", first.text, second.text),
+                            summaries_budget / 2,
+                        );
+                        condensed.summaries.insert(
+                            0,
+                            HistorySummary {
+                                level: first.level.max(second.level) + 1,
+                                text: model.talk(&prompt, None).await?.trim().to_string(),
+                            },
+                        );
+                    }
+                }
+                HistoryStrategy::Truncate => {}
+            }
+            condensed.summarized_lines += older_count;
+        }
+    }
+
+    let text = render(&condensed.summaries, &lines[condensed.summarized_lines..]);
+    // Summaries longer than asked for are cut rather than going over the budget
+    let text_lines: Vec<String> = text.lines().map(str::to_string).collect();
+    condensed.text =
+        text_lines[text_lines.len() - newest_within(&text_lines, budget, family)..].join("\n");
+    Ok(condensed)
+}
+
+/// How many of the newest lines fit in the budget together
+fn newest_within(lines: &[String], budget: usize, family: ModelFamily) -> usize {
+    let mut tokens = 0;
+    let mut count = 0;
+    for line in lines.iter().rev() {
+        tokens += family.estimate_tokens(line) + 1;
+        if tokens > budget {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+fn headings_tokens(family: ModelFamily) -> usize {
+    family.estimate_tokens(SUMMARIES_HEADING) + family.estimate_tokens(TRANSCRIPT_HEADING) + 2
+}
+
+fn summaries_tokens(summaries: &[HistorySummary], family: ModelFamily) -> usize {
+    summaries
+        .iter()
+        .map(|summary| family.estimate_tokens(&summary.text) + 1)
+        .sum()
+}
+
+fn render(summaries: &[HistorySummary], recent: &[String]) -> String {
+    if summaries.is_empty() {
+        return recent.join("\n");
+    }
+    let mut parts = vec![SUMMARIES_HEADING.to_string()];
+    parts.extend(summaries.iter().map(|summary| summary.text.clone()));
+    parts.push(TRANSCRIPT_HEADING.to_string());
+    parts.extend(recent.iter().cloned());
+    parts.join("\n")
+}
+
+fn summarize_prompt(text: &str, token_budget: usize) -> String {
+    format!(
+        "Summarize this part of a meeting transcript in at most {} words. \
+        Keep decisions, action items, open questions, names and numbers.\n\n{}",
+        words(token_budget),
+        text
+    )
+}
+
+fn update_summary_prompt(summary: &str, text: &str, token_budget: usize) -> String {
+    format!(
+        "Below is a summary of a meeting so far and what was said after it. \
+        Write an updated summary of the whole meeting in at most {} words. \
+        Keep decisions, action items, open questions, names and numbers.\n\n\
+        Summary so far:\n{}\n\nSaid after it:\n{}",
+        words(token_budget),
+        summary,
+        text
+    )
+}
+
+/// Words that about fit in the tokens, at least a few for tiny budgets
+fn words(tokens: usize) -> usize {
+    (tokens * 3 / 4).max(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::model::TalkFuture;
+    use std::sync::Mutex;
+
+    /// Answers with numbered summaries and keeps the prompts it was given
+    #[derive(Default)]
+    struct Summarizer {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl LanguageModel for Summarizer {
+        fn talk<'a>(
+            &'a self,
+            text: &'a str,
+            _structured_output_schema_string: Option<&'a str>,
+        ) -> TalkFuture<'a> {
+            let mut prompts = self.prompts.lock().unwrap();
+            prompts.push(text.to_string());
+            let answer = summary(prompts.len());
+            Box::pin(async move { Ok(answer) })
+        }
+    }
+
+    // Eight tokens each with the newline
+    fn summary(number: usize) -> String {
+        format!("Summary {} of what was said", number)
+    }
+
+    // Ten tokens each with the newline
+    fn lines(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| format!("Host: line {:02} of the meeting so far.", i))
+            .collect()
+    }
+
+    fn settings(strategy: HistoryStrategy) -> HistorySettings {
+        HistorySettings {
+            strategy,
+            token_budget: 80,
+        }
+    }
+
+    #[tokio::test]
+    async fn truncates_to_the_newest_lines() {
+        let lines = lines(10);
+        let condensed = condense_history(
+            &lines,
+            CondensedHistory::default(),
+            &settings(HistoryStrategy::Truncate),
+            ModelFamily::Other,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(condensed.text, lines[2..].join("\n"));
+        assert!(condensed.summaries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn leaves_histories_within_budget_alone() {
+        let model = Summarizer::default();
+        let lines = lines(6);
+        let condensed = condense_history(
+            &lines,
+            CondensedHistory::default(),
+            &settings(HistoryStrategy::RollingSummary),
+            ModelFamily::Other,
+            Some(&model),
+        )
+        .await
+        .unwrap();
+        assert_eq!(condensed.text, lines.join("\n"));
+        assert!(model.prompts.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rolls_older_lines_into_one_summary() {
+        let model = Summarizer::default();
+        let all = lines(20);
+        let condensed = condense_history(
+            &all[..12],
+            CondensedHistory::default(),
+            &settings(HistoryStrategy::RollingSummary),
+            ModelFamily::Other,
+            Some(&model),
+        )
+        .await
+        .unwrap();
+        assert_eq!(condensed.summarized_lines, 8);
+        assert_eq!(
+            condensed.text,
+            format!(
+                "{}\n{}\n{}\n{}",
+                SUMMARIES_HEADING,
+                summary(1),
+                TRANSCRIPT_HEADING,
+                all[8..12].join("\n")
+            )
+        );
+
+        let condensed = condense_history(
+            &all,
+            condensed,
+            &settings(HistoryStrategy::RollingSummary),
+            ModelFamily::Other,
+            Some(&model),
+        )
+        .await
+        .unwrap();
+        assert_eq!(condensed.summarized_lines, 16);
+        assert_eq!(
+            condensed.summaries,
+            [HistorySummary {
+                level: 0,
+                text: summary(2)
+            }]
+        );
+        // The update only sees what the earlier summary didn't cover
+        let prompts = model.prompts.lock().unwrap();
+        assert!(prompts[1].contains(&format!("Summary so far:\n{}", summary(1))));
+        assert!(!prompts[1].contains(&all[7]));
+        assert!(prompts[1].contains(&all[8]));
+    }
+
+    #[tokio::test]
+    async fn merges_the_oldest_summaries_one_level_up() {
+        let model = Summarizer::default();
+        let all = lines(48);
+        let mut condensed = CondensedHistory::default();
+        for end in [12, 24, 36, 48] {
+            condensed = condense_history(
+                &all[..end],
+                condensed,
+                &settings(HistoryStrategy::HierarchicalSummary),
+                ModelFamily::Other,
+                Some(&model),
+            )
+            .await
+            .unwrap();
+        }
+        let levels: Vec<u32> = condensed
+            .summaries
+            .iter()
+            .map(|summary| summary.level)
+            .collect();
+        assert_eq!(levels, [2, 0]);
+        assert!(ModelFamily::Other.estimate_tokens(&condensed.text) <= 80);
+        assert!(condensed.text.ends_with(&all[47]));
+    }
+
+    #[tokio::test]
+    async fn starts_over_for_a_new_history() {
+        let model = Summarizer::default();
+        let previous = CondensedHistory {
+            summaries: vec![HistorySummary {
+                level: 0,
+                text: "Old meeting".to_string(),
+            }],
+            summarized_lines: 50,
+            text: String::new(),
+        };
+        let lines = lines(2);
+        let condensed = condense_history(
+            &lines,
+            previous,
+            &settings(HistoryStrategy::RollingSummary),
+            ModelFamily::Other,
+            Some(&model),
+        )
+        .await
+        .unwrap();
+        assert_eq!(condensed.text, lines.join("\n"));
+    }
+}
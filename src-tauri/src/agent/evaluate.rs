@@ -0,0 +1,842 @@
+use crate::agent::condense::{condense_history, history_settings, CondensedHistory};
+use crate::agent::history::TranscriptHistory;
+use crate::agent::inputs::{listens_to_transcription, upstream_agents};
+use crate::agent::prompt::{
+    AgentOutputInput, AgentTemplates, AnswerInput, PreviousAnswer, TemplateInput,
+};
+use crate::agent::schedule::{interval_ms, Schedule};
+use crate::agent::trigger::{silence_ms, AgentTriggers};
+use crate::config::agents::{
+    read_agent_config, read_agent_test, AgentConfig, HistorySettings, Trigger,
+};
+use crate::import::ImportFormat;
+use crate::llm::model::{load_language_model, LanguageModel, LlmBackend};
+use crate::llm::router::LlmRouterState;
+use crate::llm::tokens::ModelFamily;
+use crate::transcription::sessions::load_session;
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+use crate::util::paths::get_app_sub_path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::State;
+use tokio::fs;
+
+/// Test of an agent, <agent>.test.yaml next to it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentTest {
+    pub fixture: Fixture,
+    #[serde(default = "default_llm")]
+    pub llm: LlmBackend,
+    // How long each answer takes, which decides how much transcription each request covers
+    #[serde(default = "default_llm_latency_ms")]
+    pub llm_latency_ms: u64,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+}
+
+fn default_llm() -> LlmBackend {
+    LlmBackend::Configured
+}
+
+fn default_llm_latency_ms() -> u64 {
+    1000
+}
+
+/// Transcript the agent is run over
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Fixture {
+    File {
+        // SRT, WebVTT or JSON as imported, relative to the agent folder
+        file: String,
+    },
+    Session {
+        // Id of a saved session
+        session: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Assertion {
+    Contains {
+        text: String,
+        #[serde(default)]
+        response: ResponseSelector,
+    },
+    JsonPathEquals {
+        // e.g. $.actionItems[0].owner
+        path: String,
+        value: Value,
+        #[serde(default)]
+        response: ResponseSelector,
+    },
+    SchemaValid {
+        // The structured output schema of the agent if not given
+        #[serde(default)]
+        schema: Option<Value>,
+        #[serde(default)]
+        response: ResponseSelector,
+    },
+}
+
+/// Which answers an assertion holds for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ResponseSelector {
+    #[default]
+    Last,
+    Any,
+    All,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationReport {
+    pub agent_name: String,
+    pub exchanges: Vec<Exchange>,
+    pub assertions: Vec<AssertionResult>,
+    // No request failed and every assertion held
+    pub passed: bool,
+}
+
+/// One request of the agent and what came back
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Exchange {
+    // Time into the fixture the agent was invoked at
+    pub at_ms: u64,
+    pub transcription_history: String,
+    pub transcription_latest: String,
+    pub prompt: String,
+    pub answer: Option<String>,
+    // Provided if using structured output
+    pub answer_json: Option<Value>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    // Expected and actual value of a failed assertion
+    pub message: Option<String>,
+}
+
+/// Runs an agent over the fixture of its test and checks the answers, prompt changes can be
+/// tried this way before a meeting. `llm` overrides the backend the test chose.
+#[tauri::command]
+pub async fn evaluate_agent(
+    agent_name: String,
+    llm: Option<LlmBackend>,
+    router: State<'_, LlmRouterState>,
+) -> Result<EvaluationReport, String> {
+    let agent_config = AgentConfig {
+        agent: read_agent_config(&agent_name)?,
+        name: agent_name.clone(),
+    };
+    let test: AgentTest = serde_yaml::from_str(&read_agent_test(&agent_name)?)
+        .map_err(|e| format!("Failed to parse agent test {}: {}", agent_name, e))?;
+    let session = load_fixture(&test.fixture).await?;
+    let model = load_language_model(llm.as_ref().unwrap_or(&test.llm), &router).await?;
+    evaluate(
+        &agent_config,
+        &session,
+        model.as_ref(),
+        test.llm_latency_ms,
+        &test.assertions,
+    )
+    .await
+}
+
+async fn load_fixture(fixture: &Fixture) -> Result<SessionTimeline, String> {
+    match fixture {
+        Fixture::Session { session } => load_session(session).await,
+        Fixture::File { file } => {
+            let path = get_app_sub_path("agent")?.join(file);
+            let content = fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("Failed to read fixture {}: {}", path.display(), e))?;
+            ImportFormat::detect(&content)
+                .importer()
+                .import(&content, 0)
+        }
+    }
+}
+
+/// Plays the session to the agent on a simulated clock, so the same fixture and answers
+/// always lead to the same requests no matter how long the model really takes
+pub async fn evaluate(
+    agent_config: &AgentConfig,
+    session: &SessionTimeline,
+    model: &dyn LanguageModel,
+    llm_latency_ms: u64,
+    assertions: &[Assertion],
+) -> Result<EvaluationReport, String> {
+    let mut agent = EvaluatedAgent {
+        templates: AgentTemplates::new(&agent_config.agent)?,
+        model,
+        llm_latency_ms,
+        history: TranscriptHistory::default(),
+        history_settings: history_settings(&agent_config.agent),
+        condensed_history: CondensedHistory::default(),
+        previous_answer: PreviousAnswer::default(),
+        inputs: upstream_agents(&agent_config.agent)
+            .iter()
+            .map(|name| (name.clone(), AgentOutputInput::default()))
+            .collect(),
+        inputs_changed: false,
+        exchanges: Vec::new(),
+    };
+    let listens_to_transcription = listens_to_transcription(&agent_config.agent);
+    let mut entries: Vec<&TimelineEntry> = session.entries.iter().collect();
+    entries.sort_by_key(|entry| entry.recorded_ms());
+    match &agent_config.agent.trigger {
+        Some(trigger) => {
+            agent
+                .play_triggered(
+                    &agent_config.name,
+                    trigger,
+                    &entries,
+                    listens_to_transcription,
+                )
+                .await?
+        }
+        None => {
+            agent
+                .play_scheduled(
+                    interval_ms(&agent_config.agent),
+                    &entries,
+                    listens_to_transcription,
+                )
+                .await
+        }
+    }
+
+    let schema = agent.templates.structured_output_schema();
+    let assertions: Vec<AssertionResult> = assertions
+        .iter()
+        .map(|assertion| {
+            let message = check_assertion(assertion, &agent.exchanges, schema).err();
+            AssertionResult {
+                assertion: assertion.clone(),
+                passed: message.is_none(),
+                message,
+            }
+        })
+        .collect();
+    let passed = agent
+        .exchanges
+        .iter()
+        .all(|exchange| exchange.error.is_none())
+        && assertions.iter().all(|result| result.passed);
+    Ok(EvaluationReport {
+        agent_name: agent_config.name.clone(),
+        exchanges: agent.exchanges,
+        assertions,
+        passed,
+    })
+}
+
+struct EvaluatedAgent<'a> {
+    templates: AgentTemplates,
+    model: &'a dyn LanguageModel,
+    // Simulated time each answer takes
+    llm_latency_ms: u64,
+    history: TranscriptHistory,
+    history_settings: Option<HistorySettings>,
+    condensed_history: CondensedHistory,
+    previous_answer: PreviousAnswer,
+    inputs: BTreeMap<String, AgentOutputInput>,
+    // An upstream agent answered since the agent was last invoked
+    inputs_changed: bool,
+    exchanges: Vec<Exchange>,
+}
+
+impl EvaluatedAgent<'_> {
+    /// Invokes the agent as transcription and answers of upstream agents come in, debounced
+    /// by its interval
+    async fn play_scheduled(
+        &mut self,
+        interval_ms: u64,
+        entries: &[&TimelineEntry],
+        listens_to_transcription: bool,
+    ) {
+        let mut schedule = Schedule::new(interval_ms);
+        for entry in entries {
+            let now_ms = entry.recorded_ms();
+            while let Some(due_ms) = schedule.next_due_ms().filter(|due_ms| *due_ms <= now_ms) {
+                if schedule.advance(due_ms) {
+                    let duration_ms = self.invoke(due_ms).await;
+                    schedule.invoked(due_ms, duration_ms);
+                }
+            }
+            // Answers the session recorded of upstream agents stand in for them running alongside
+            let invokes = match entry {
+                TimelineEntry::AgentOutput {
+                    agent_name,
+                    text,
+                    json,
+                    ..
+                } => self.answered(agent_name, text, json),
+                _ if !listens_to_transcription => false,
+                _ => {
+                    self.history.heard(entry);
+                    // Only transcription invokes the agent, bookmarks and notes wait for it
+                    matches!(entry, TimelineEntry::Transcript { .. })
+                }
+            };
+            if invokes && schedule.transcribed(now_ms) {
+                let duration_ms = self.invoke(now_ms).await;
+                schedule.invoked(now_ms, duration_ms);
+            }
+        }
+        while let Some(due_ms) = schedule.next_due_ms() {
+            if schedule.advance(due_ms) {
+                let duration_ms = self.invoke(due_ms).await;
+                schedule.invoked(due_ms, duration_ms);
+            }
+        }
+    }
+
+    /// Invokes the agent only when its trigger fires, like the prompter does for such agents.
+    /// The hotkey isn't pressed during a fixture, so it never fires the agent here.
+    async fn play_triggered(
+        &mut self,
+        agent_name: &str,
+        trigger: &Trigger,
+        entries: &[&TimelineEntry],
+        listens_to_transcription: bool,
+    ) -> Result<(), String> {
+        let mut triggers = AgentTriggers::default();
+        triggers.watch(agent_name.to_string(), trigger)?;
+        let silence_ms = silence_ms(trigger);
+        // When transcription last came in, silence is counted from it
+        let mut heard_at_ms: Option<u64> = None;
+        for entry in entries {
+            let now_ms = entry.recorded_ms();
+            if let (Some(heard_ms), Some(silence_ms)) = (heard_at_ms, silence_ms) {
+                let silent_at_ms = heard_ms.saturating_add(silence_ms);
+                if silent_at_ms <= now_ms {
+                    heard_at_ms = None;
+                    if triggers.silent(agent_name, heard_ms, silent_at_ms) {
+                        self.invoke(silent_at_ms).await;
+                    }
+                }
+            }
+            match entry {
+                // Inputs wait for the trigger like transcription does
+                TimelineEntry::AgentOutput {
+                    agent_name,
+                    text,
+                    json,
+                    ..
+                } => {
+                    self.answered(agent_name, text, json);
+                }
+                TimelineEntry::Transcript { speaker, text, .. } => {
+                    if listens_to_transcription {
+                        self.history.heard(entry);
+                    }
+                    heard_at_ms = Some(now_ms);
+                    if !triggers.heard(speaker, text, now_ms).is_empty() {
+                        self.invoke(now_ms).await;
+                    }
+                }
+                _ if listens_to_transcription => self.history.heard(entry),
+                _ => {}
+            }
+        }
+        if let (Some(heard_at_ms), Some(silence_ms)) = (heard_at_ms, silence_ms) {
+            let silent_at_ms = heard_at_ms.saturating_add(silence_ms);
+            if triggers.silent(agent_name, heard_at_ms, silent_at_ms) {
+                self.invoke(silent_at_ms).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the answer as input if it is from an upstream agent, true if it was
+    fn answered(&mut self, agent_name: &str, text: &str, json: &Option<Value>) -> bool {
+        let Some(input) = self.inputs.get_mut(agent_name) else {
+            return false;
+        };
+        *input = AgentOutputInput {
+            text: text.to_string(),
+            json: json.clone(),
+        };
+        self.inputs_changed = true;
+        true
+    }
+
+    /// Makes the request if there is anything new, returns how long the answer took
+    async fn invoke(&mut self, now_ms: u64) -> u64 {
+        // Nothing new to answer, the prompter skips the request
+        if !self.history.has_latest() && !self.inputs_changed {
+            return 0;
+        }
+        self.inputs_changed = false;
+
+        let mut transcription = self.history.take_latest();
+        // Kept within the budget like the prompter does, summarizing with the evaluated model
+        if let Some(settings) = &self.history_settings {
+            let condensed = condense_history(
+                self.history.lines(),
+                std::mem::take(&mut self.condensed_history),
+                settings,
+                self.model
+                    .model_name()
+                    .map_or(ModelFamily::default(), ModelFamily::from_model_name),
+                Some(self.model),
+            )
+            .await;
+            match condensed {
+                Ok(condensed) => {
+                    transcription.all = condensed.text.clone();
+                    self.condensed_history = condensed;
+                }
+                Err(e) => {
+                    self.exchanges.push(Exchange {
+                        at_ms: now_ms,
+                        transcription_history: transcription.all,
+                        transcription_latest: transcription.latest,
+                        prompt: String::new(),
+                        answer: None,
+                        answer_json: None,
+                        error: Some(format!("Failed to condense history: {}", e)),
+                    });
+                    return self.llm_latency_ms;
+                }
+            }
+        }
+
+        let input = TemplateInput {
+            transcription,
+            answer: AnswerInput {
+                previous: self.previous_answer.clone(),
+            },
+            inputs: self.inputs.clone(),
+            // Earlier meetings are not part of the fixture
+            ..TemplateInput::default()
+        };
+        let mut exchange = Exchange {
+            at_ms: now_ms,
+            transcription_history: input.transcription.all.clone(),
+            transcription_latest: input.transcription.latest.clone(),
+            prompt: String::new(),
+            answer: None,
+            answer_json: None,
+            error: None,
+        };
+        let answer = match self.templates.render_prompt(&input) {
+            Ok(prompt) => {
+                exchange.prompt = prompt;
+                self.model
+                    .talk(&exchange.prompt, self.templates.structured_output_schema())
+                    .await
+                    .and_then(|answer| self.templates.map_answer(answer))
+            }
+            Err(e) => Err(e),
+        };
+        match answer {
+            Ok((text, json)) => {
+                self.previous_answer = PreviousAnswer {
+                    text: text.clone(),
+                    json: json.clone(),
+                };
+                exchange.answer = Some(text);
+                exchange.answer_json = json;
+            }
+            Err(e) => exchange.error = Some(e),
+        }
+        self.exchanges.push(exchange);
+        self.llm_latency_ms
+    }
+}
+
+/// Err with what was expected and what the answers were if the assertion doesn't hold
+fn check_assertion(
+    assertion: &Assertion,
+    exchanges: &[Exchange],
+    structured_output_schema: Option<&str>,
+) -> Result<(), String> {
+    let answered: Vec<&Exchange> = exchanges
+        .iter()
+        .filter(|exchange| exchange.answer.is_some())
+        .collect();
+    let Some(last) = answered.last() else {
+        return Err("The agent gave no answers".to_string());
+    };
+    let selector = match assertion {
+        Assertion::Contains { response, .. }
+        | Assertion::JsonPathEquals { response, .. }
+        | Assertion::SchemaValid { response, .. } => *response,
+    };
+    let check = |exchange: &Exchange| check_answer(assertion, exchange, structured_output_schema);
+    match selector {
+        ResponseSelector::Last => check(last),
+        ResponseSelector::All => answered.iter().try_for_each(|exchange| {
+            check(exchange).map_err(|e| format!("Answer at {} ms: {}", exchange.at_ms, e))
+        }),
+        ResponseSelector::Any => {
+            if answered.iter().any(|exchange| check(exchange).is_ok()) {
+                return Ok(());
+            }
+            Err(format!(
+                "None of {} answers matched, the last: {}",
+                answered.len(),
+                check(last).err().unwrap_or_default()
+            ))
+        }
+    }
+}
+
+fn check_answer(
+    assertion: &Assertion,
+    exchange: &Exchange,
+    structured_output_schema: Option<&str>,
+) -> Result<(), String> {
+    let answer = exchange.answer.as_deref().unwrap_or_default();
+    match assertion {
+        Assertion::Contains { text, .. } => {
+            if answer.contains(text.as_str()) {
+                Ok(())
+            } else {
+                Err(format!("Expected \"{}\" in: {}", text, answer))
+            }
+        }
+        Assertion::JsonPathEquals { path, value, .. } => {
+            let json = answer_json(exchange)?;
+            match json_path(&json, path)? {
+                Some(actual) if actual == value => Ok(()),
+                Some(actual) => Err(format!("Expected {} at {}, got {}", value, path, actual)),
+                None => Err(format!("Expected {} at {}, got nothing", value, path)),
+            }
+        }
+        Assertion::SchemaValid { schema, .. } => {
+            let schema = match (schema, structured_output_schema) {
+                (Some(schema), _) => schema.clone(),
+                (None, Some(schema)) => serde_json::from_str(schema)
+                    .map_err(|e| format!("Error parsing schema JSON: {}", e))?,
+                (None, None) => {
+                    return Err("No schema given and the agent has no structured output".to_string())
+                }
+            };
+            let validator =
+                jsonschema::validator_for(&schema).map_err(|e| format!("Invalid schema: {}", e))?;
+            let json = answer_json(exchange)?;
+            let errors: Vec<String> = validator
+                .iter_errors(&json)
+                .map(|e| format!("{}: {}", e.instance_path, e))
+                .collect();
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Not valid: {}", errors.join(", ")))
+            }
+        }
+    }
+}
+
+/// The structured output, or the answer itself for agents asked to answer in JSON
+fn answer_json(exchange: &Exchange) -> Result<Value, String> {
+    if let Some(json) = &exchange.answer_json {
+        return Ok(json.clone());
+    }
+    let answer = exchange.answer.as_deref().unwrap_or_default();
+    serde_json::from_str(answer).map_err(|e| format!("Answer is not JSON ({}): {}", e, answer))
+}
+
+/// Value at a path such as $.items[0].title or $['key with spaces'], None if there is none
+fn json_path<'a>(json: &'a Value, path: &str) -> Result<Option<&'a Value>, String> {
+    let invalid = || format!("Invalid JSON path: {}", path);
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    let mut current = json;
+    while !rest.is_empty() {
+        let next = if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            rest = &after_dot[end..];
+            current.get(&after_dot[..end])
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket.find(']').ok_or_else(invalid)?;
+            let index = &after_bracket[..end];
+            rest = &after_bracket[end + 1..];
+            let quoted = index
+                .strip_prefix('\'')
+                .and_then(|key| key.strip_suffix('\''))
+                .or_else(|| {
+                    index
+                        .strip_prefix('"')
+                        .and_then(|key| key.strip_suffix('"'))
+                });
+            match quoted {
+                Some(key) => current.get(key),
+                None => current.get(index.trim().parse::<usize>().map_err(|_| invalid())?),
+            }
+        } else {
+            return Err(invalid());
+        };
+        match next {
+            Some(next) => current = next,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::agents::{Agent, AgentInputs, StructuredOutput};
+    use crate::llm::mock::MockLanguageModel;
+    use serde_json::json;
+
+    fn agent(prompt: &str, structured_output: Option<StructuredOutput>) -> AgentConfig {
+        AgentConfig {
+            name: "tester".to_string(),
+            agent: Agent {
+                interval_in_sec: Some(3.0),
+                transcription_history_max_chars: None,
+                history: None,
+                inputs: None,
+                trigger: None,
+                prompt: prompt.to_string(),
+                structured_output,
+                vocabulary: None,
+                finalizer: None,
+            },
+        }
+    }
+
+    fn fixture() -> SessionTimeline {
+        let said = |speaker: &str, text: &str, end_ms| TimelineEntry::Transcript {
+            device_id: 0,
+            device_uid: "fixture".to_string(),
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+            confidence: 1.0,
+            start_ms: end_ms - 500,
+            end_ms,
+        };
+        SessionTimeline::from_entries(
+            0,
+            vec![
+                said("Host", "Welcome everyone", 1000),
+                said("Guest", "Thanks for having me", 1500),
+                TimelineEntry::Bookmark {
+                    at_ms: 1800,
+                    label: Some("Pricing".to_string()),
+                },
+                said("Guest", "What does it cost?", 2000),
+                said("Host", "Ten dollars a month", 9000),
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn requests_follow_the_agent_schedule() {
+        let model =
+            MockLanguageModel::new(vec!["first".to_string(), "second".to_string()]).unwrap();
+        let report = evaluate(
+            &agent(
+                "Since bookmark:\n{{transcription.sinceBookmark}}\nBefore: {{answer.previous.text}}",
+                None,
+            ),
+            &fixture(),
+            &model,
+            1000,
+            &[],
+        )
+        .await
+        .unwrap();
+
+        // Invoked right away, then again once the rest settled after the first answer,
+        // and right away again for text coming in when idle
+        let times: Vec<u64> = report.exchanges.iter().map(|e| e.at_ms).collect();
+        assert_eq!(times, [1000, 5000, 9000]);
+        assert_eq!(
+            report.exchanges[0].transcription_latest,
+            "Host: Welcome everyone"
+        );
+        assert_eq!(
+            report.exchanges[1].transcription_latest,
+            "Guest: Thanks for having me\n--- Bookmark: Pricing ---\nGuest: What does it cost?"
+        );
+        assert_eq!(
+            report.exchanges[2].prompt,
+            "Since bookmark:\n--- Bookmark: Pricing ---\nGuest: What does it cost?\nHost: Ten dollars a month\nBefore: second"
+        );
+        assert_eq!(
+            report
+                .exchanges
+                .iter()
+                .map(|e| e.answer.clone().unwrap())
+                .collect::<Vec<_>>(),
+            ["first", "second", "second"]
+        );
+        assert!(report.passed);
+    }
+
+    #[tokio::test]
+    async fn triggered_agents_answer_only_when_the_trigger_fires() {
+        let mut triggered = agent("Latest: {{transcription.latest}}", None);
+        triggered.agent.trigger = Some(Trigger {
+            keywords: vec!["cost".to_string()],
+            silence_sec: Some(5.0),
+            ..Trigger::default()
+        });
+        let model = MockLanguageModel::new(vec!["Ten dollars".to_string()]).unwrap();
+        let report = evaluate(&triggered, &fixture(), &model, 1000, &[])
+            .await
+            .unwrap();
+
+        // The question fires the keyword, the silence after it falls in the cooldown,
+        // the silence after the last line doesn't
+        let times: Vec<u64> = report.exchanges.iter().map(|e| e.at_ms).collect();
+        assert_eq!(times, [2000, 14000]);
+        assert_eq!(
+            report.exchanges[0].transcription_latest,
+            "Host: Welcome everyone\nGuest: Thanks for having me\n--- Bookmark: Pricing ---\nGuest: What does it cost?"
+        );
+        assert_eq!(
+            report.exchanges[1].transcription_latest,
+            "Host: Ten dollars a month"
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_upstream_agents_instead_of_the_transcript() {
+        let mut chained = agent("Answer: {{inputs.questions.text}}", None);
+        chained.agent.inputs = Some(AgentInputs {
+            transcription: false,
+            agents: vec!["questions".to_string()],
+        });
+        let mut session = fixture();
+        for (agent_name, at_ms) in [("questions", 2500), ("other", 3000)] {
+            session.entries.push(TimelineEntry::AgentOutput {
+                agent_name: agent_name.to_string(),
+                at_ms,
+                text: "What does it cost?".to_string(),
+                json: None,
+            });
+        }
+        let model = MockLanguageModel::new(vec!["Ten dollars".to_string()]).unwrap();
+        let report = evaluate(&chained, &session, &model, 1000, &[])
+            .await
+            .unwrap();
+
+        assert_eq!(report.exchanges.len(), 1);
+        assert_eq!(report.exchanges[0].at_ms, 2500);
+        assert_eq!(report.exchanges[0].prompt, "Answer: What does it cost?");
+        assert_eq!(report.exchanges[0].transcription_history, "");
+    }
+
+    #[tokio::test]
+    async fn checks_assertions_against_structured_answers() {
+        let structured_output = StructuredOutput {
+            schema:
+                r#"{"type":"object","properties":{"price":{"type":"number"}},"required":["price"]}"#
+                    .to_string(),
+            mapper: "Costs {{price}}".to_string(),
+        };
+        let model = MockLanguageModel::new(vec![
+            r#"{"price":"unknown"}"#.to_string(),
+            r#"{"price":10}"#.to_string(),
+        ])
+        .unwrap();
+        let assertions = [
+            Assertion::Contains {
+                text: "Costs 10".to_string(),
+                response: ResponseSelector::Last,
+            },
+            Assertion::JsonPathEquals {
+                path: "$.price".to_string(),
+                value: json!(10),
+                response: ResponseSelector::Any,
+            },
+            Assertion::SchemaValid {
+                schema: None,
+                response: ResponseSelector::All,
+            },
+        ];
+        let report = evaluate(
+            &agent("{{transcription.latest}}", Some(structured_output)),
+            &fixture(),
+            &model,
+            1000,
+            &assertions,
+        )
+        .await
+        .unwrap();
+
+        let passed: Vec<bool> = report.assertions.iter().map(|a| a.passed).collect();
+        assert_eq!(passed, [true, true, false]);
+        assert!(report.assertions[2]
+            .message
+            .as_deref()
+            .unwrap()
+            .starts_with("Answer at 1000 ms: Not valid: /price:"));
+        assert!(!report.passed);
+    }
+
+    #[test]
+    fn reads_agent_tests() {
+        let test: AgentTest = serde_yaml::from_str(
+            r#"
+fixture:
+  file: fixtures/standup.srt
+llm:
+  type: mock
+  responses: ["{\"owner\": \"Ann\"}"]
+assertions:
+  - type: contains
+    text: Ann
+  - type: jsonPathEquals
+    path: $.owner
+    value: Ann
+    response: all
+  - type: schemaValid
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            test.fixture,
+            Fixture::File {
+                file: "fixtures/standup.srt".to_string()
+            }
+        );
+        assert_eq!(test.llm_latency_ms, 1000);
+        assert_eq!(test.assertions.len(), 3);
+        assert!(matches!(
+            test.assertions[1],
+            Assertion::JsonPathEquals {
+                response: ResponseSelector::All,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn resolves_json_paths() {
+        let json = json!({"items": [{"title": "Ship it"}], "odd key": true});
+        assert_eq!(
+            json_path(&json, "$.items[0].title").unwrap(),
+            Some(&json!("Ship it"))
+        );
+        assert_eq!(
+            json_path(&json, "$['odd key']").unwrap(),
+            Some(&json!(true))
+        );
+        assert_eq!(json_path(&json, "$").unwrap(), Some(&json));
+        assert_eq!(json_path(&json, "$.items[3]").unwrap(), None);
+        assert_eq!(json_path(&json, "$.missing.title").unwrap(), None);
+        assert!(json_path(&json, "items").is_err());
+        assert!(json_path(&json, "$.items[x]").is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use crate::agent::history::TranscriptHistory;
+use crate::agent::prompt::{AgentTemplates, ChunkInput, TemplateInput, TranscriptionInput};
+use crate::config::agents::{read_agent_configs, AgentConfig};
+use crate::llm::model::{load_language_model, LanguageModel, LlmBackend};
+use crate::llm::router::LlmRouterState;
+use crate::transcription::sessions::{load_session, save_session};
+use crate::transcription::timeline::SessionTimeline;
+use crate::util::error_handler::show_error;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+const MEETING_SUMMARY_READY_EVENT_TYPE: &str = "meeting-summary-ready";
+
+// About three thousand tokens, which fits the context of most local models with room to answer
+const DEFAULT_CHUNK_MAX_CHARS: usize = 12000;
+
+// Finalizer answers are asked for in this shape
+const MEETING_SUMMARY_SCHEMA: &str = r#"{
+  "type": "object",
+  "properties": {
+    "summary": {"type": "string"},
+    "decisions": {"type": "array", "items": {"type": "string"}},
+    "actionItems": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "description": {"type": "string"},
+          "owner": {"type": ["string", "null"]},
+          "dueDate": {"type": ["string", "null"]}
+        },
+        "required": ["description", "owner", "dueDate"]
+      }
+    }
+  },
+  "required": ["summary", "decisions", "actionItems"]
+}"#;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetingSummary {
+    pub summary: String,
+    #[serde(default)]
+    pub decisions: Vec<String>,
+    #[serde(default)]
+    pub action_items: Vec<ActionItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionItem {
+    pub description: String,
+    #[serde(default)]
+    pub owner: Option<String>,
+    // As said in the meeting, e.g. "Friday" or "2025-06-01"
+    #[serde(default)]
+    pub due_date: Option<String>,
+}
+
+/// What a finalizer agent made of a meeting
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizerOutput {
+    pub agent_name: String,
+    pub summary: Option<MeetingSummary>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetingSummaryReadyEvent {
+    pub r#type: String,
+    pub session_id: String,
+    pub summaries: Vec<FinalizerOutput>,
+}
+
+/// Runs the finalizer agents over a meeting that ended, in the background
+pub fn spawn_finalizers(app_handle: AppHandle, session_id: String) {
+    tokio::spawn(async move {
+        if let Err(e) = finalize_session(&app_handle, &session_id).await {
+            show_error(
+                format!("Failed to summarize meeting {}: {}", session_id, e),
+                app_handle,
+            );
+        }
+    });
+}
+
+async fn finalize_session(app_handle: &AppHandle, session_id: &str) -> Result<(), String> {
+    let finalizers: Vec<AgentConfig> = read_agent_configs()?
+        .into_iter()
+        .filter(|agent_config| agent_config.agent.finalizer.is_some())
+        .collect();
+    if finalizers.is_empty() {
+        return Ok(());
+    }
+    let mut session = load_session(session_id).await?;
+    let transcript = session_transcript(&session);
+    if transcript.is_empty() {
+        return Ok(());
+    }
+    let router = app_handle.state::<LlmRouterState>();
+    let model = load_language_model(&LlmBackend::Configured, &router).await?;
+
+    let mut summaries = Vec::new();
+    for agent_config in finalizers {
+        info!(
+            "Summarizing meeting {} with {}",
+            session_id, agent_config.name
+        );
+        let summary = run_finalizer(&agent_config, &transcript, model.as_ref()).await;
+        if let Err(e) = &summary {
+            warn!("Finalizer {} failed: {}", agent_config.name, e);
+        }
+        summaries.push(FinalizerOutput {
+            agent_name: agent_config.name,
+            error: summary.as_ref().err().cloned(),
+            summary: summary.ok(),
+        });
+    }
+
+    session.summaries = summaries.clone();
+    save_session(&session).await?;
+    app_handle
+        .emit(
+            MEETING_SUMMARY_READY_EVENT_TYPE,
+            MeetingSummaryReadyEvent {
+                r#type: MEETING_SUMMARY_READY_EVENT_TYPE.to_string(),
+                session_id: session.id,
+                summaries,
+            },
+        )
+        .map_err(|e| format!("Failed to emit event: {}", e))
+}
+
+/// Everything said in the meeting, as agents saw it during the meeting
+fn session_transcript(session: &SessionTimeline) -> String {
+    let mut entries: Vec<_> = session.entries.iter().collect();
+    entries.sort_by_key(|entry| entry.recorded_ms());
+    let mut history = TranscriptHistory::default();
+    for entry in entries {
+        history.heard(entry);
+    }
+    history.take_latest().all
+}
+
+/// Maps parts of a long transcript to notes until they fit one request, then reduces them
+/// to a meeting summary with the prompt of the agent
+pub async fn run_finalizer(
+    agent_config: &AgentConfig,
+    transcript: &str,
+    model: &dyn LanguageModel,
+) -> Result<MeetingSummary, String> {
+    let templates = AgentTemplates::new(&agent_config.agent)?;
+    let chunk_max_chars = agent_config
+        .agent
+        .finalizer
+        .as_ref()
+        .and_then(|finalizer| finalizer.chunk_max_chars)
+        .unwrap_or(DEFAULT_CHUNK_MAX_CHARS)
+        .max(1);
+
+    let mut text = transcript.to_string();
+    while text.chars().count() > chunk_max_chars {
+        let chunks = split_into_chunks(&text, chunk_max_chars);
+        let mut notes = Vec::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let prompt = templates.render_map_prompt(&TemplateInput {
+                transcription: whole(chunk),
+                chunk: Some(ChunkInput {
+                    index: index + 1,
+                    count: chunks.len(),
+                }),
+                ..TemplateInput::default()
+            })?;
+            notes.push(model.talk(&prompt, None).await?);
+        }
+        let condensed = notes.join("\n\n");
+        // Notes no shorter than what they were taken of won't get shorter in another round
+        let condensed_enough = condensed.chars().count() < text.chars().count();
+        text = condensed;
+        if !condensed_enough {
+            break;
+        }
+    }
+
+    let prompt = templates.render_prompt(&TemplateInput {
+        transcription: whole(&text),
+        ..TemplateInput::default()
+    })?;
+    let answer = model.talk(&prompt, Some(MEETING_SUMMARY_SCHEMA)).await?;
+    serde_json::from_str(&answer).map_err(|e| {
+        format!(
+            "Failed to parse meeting summary: {}. Response: {}",
+            e, answer
+        )
+    })
+}
+
+/// All of the meeting at once, as far as the prompt can tell
+fn whole(text: &str) -> TranscriptionInput {
+    TranscriptionInput {
+        all: text.to_string(),
+        latest: text.to_string(),
+        since_bookmark: text.to_string(),
+    }
+}
+
+/// Parts of at most `max_chars`, split between lines unless a line is longer than that
+fn split_into_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut chunk_chars = 0;
+    for line in text.lines() {
+        let line_chars = line.chars().count();
+        if chunk_chars > 0 && chunk_chars + 1 + line_chars > max_chars {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_chars = 0;
+        }
+        if line_chars > max_chars {
+            let chars: Vec<char> = line.chars().collect();
+            for part in chars.chunks(max_chars) {
+                chunks.push(part.iter().collect());
+            }
+            continue;
+        }
+        if chunk_chars > 0 {
+            chunk.push('\n');
+            chunk_chars += 1;
+        }
+        chunk.push_str(line);
+        chunk_chars += line_chars;
+    }
+    if chunk_chars > 0 {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::agents::{Agent, Finalizer};
+    use crate::llm::mock::MockLanguageModel;
+
+    fn finalizer(chunk_max_chars: usize) -> AgentConfig {
+        AgentConfig {
+            name: "minutes".to_string(),
+            agent: Agent {
+                interval_in_sec: None,
+                transcription_history_max_chars: None,
+                history: None,
+                inputs: None,
+                trigger: None,
+                prompt: "Summarize:\n{{{transcription.all}}}".to_string(),
+                structured_output: None,
+                vocabulary: None,
+                finalizer: Some(Finalizer {
+                    map_prompt: Some("Notes of {{chunk.index}}/{{chunk.count}}".to_string()),
+                    chunk_max_chars: Some(chunk_max_chars),
+                }),
+            },
+        }
+    }
+
+    const SUMMARY: &str = r#"{"summary":"Pricing was agreed","decisions":["Ten dollars a month"],
+        "actionItems":[{"description":"Update the website","owner":"Ann","dueDate":null}]}"#;
+
+    #[tokio::test]
+    async fn summarizes_short_meetings_at_once() {
+        let model = MockLanguageModel::new(vec![SUMMARY.to_string()]).unwrap();
+        let summary = run_finalizer(&finalizer(1000), "Host: Ten dollars?\nGuest: Yes", &model)
+            .await
+            .unwrap();
+        assert_eq!(summary.decisions, ["Ten dollars a month"]);
+        assert_eq!(
+            summary.action_items,
+            [ActionItem {
+                description: "Update the website".to_string(),
+                owner: Some("Ann".to_string()),
+                due_date: None,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn condenses_long_meetings_part_by_part() {
+        let model = MockLanguageModel::new(vec![
+            "A".to_string(),
+            "B".to_string(),
+            "C".to_string(),
+            SUMMARY.to_string(),
+        ])
+        .unwrap();
+        let transcript = "Host: one two\nGuest: three four\nHost: five six";
+        let summary = run_finalizer(&finalizer(20), transcript, &model)
+            .await
+            .unwrap();
+        assert_eq!(summary.summary, "Pricing was agreed");
+    }
+
+    #[tokio::test]
+    async fn fails_on_answers_that_are_no_summary() {
+        let model = MockLanguageModel::new(vec!["Pricing was agreed".to_string()]).unwrap();
+        let error = run_finalizer(&finalizer(1000), "Host: Hi", &model)
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("Failed to parse meeting summary"));
+    }
+
+    #[test]
+    fn splits_between_lines() {
+        assert_eq!(split_into_chunks("ab\ncd\nef", 5), ["ab\ncd", "ef"]);
+        assert_eq!(
+            split_into_chunks("ab\ncdefgh\ni", 3),
+            ["ab", "cde", "fgh", "i"]
+        );
+        assert!(split_into_chunks("", 3).is_empty());
+    }
+
+    #[test]
+    fn orders_the_transcript_as_it_was_said() {
+        let said = |text: &str, end_ms| crate::transcription::timeline::TimelineEntry::Transcript {
+            device_id: 0,
+            device_uid: "uid".to_string(),
+            speaker: "Host".to_string(),
+            text: text.to_string(),
+            confidence: 1.0,
+            start_ms: 0,
+            end_ms,
+        };
+        let session =
+            SessionTimeline::from_entries(0, vec![said("second", 2000), said("first", 1000)]);
+        assert_eq!(session_transcript(&session), "Host: first\nHost: second");
+    }
+}
@@ -0,0 +1,67 @@
+use crate::agent::prompt::TranscriptionInput;
+use crate::transcription::timeline::TimelineEntry;
+
+/// What an agent heard so far, kept the way the app's prompter keeps it
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptHistory {
+    history: Vec<String>,
+    // Lines since the agent was last invoked
+    latest: Vec<String>,
+    // Where in the history the last bookmark was added
+    bookmark_index: usize,
+}
+
+impl TranscriptHistory {
+    /// Adds what an entry of a session adds for agents, which don't see pauses or other answers
+    pub fn heard(&mut self, entry: &TimelineEntry) {
+        match entry {
+            TimelineEntry::Transcript { speaker, text, .. } => self.said(speaker, text),
+            TimelineEntry::Bookmark { label, .. } => self.bookmarked(label.as_deref()),
+            TimelineEntry::Note { text, .. } => self.noted(text),
+            TimelineEntry::DevicePaused { .. } | TimelineEntry::AgentOutput { .. } => {}
+        }
+    }
+
+    pub fn said(&mut self, speaker: &str, text: &str) {
+        if speaker.is_empty() {
+            self.push(text.to_string());
+        } else {
+            self.push(format!("{}: {}", speaker, text));
+        }
+    }
+
+    pub fn bookmarked(&mut self, label: Option<&str>) {
+        self.bookmark_index = self.history.len();
+        self.push(match label {
+            Some(label) => format!("--- Bookmark: {} ---", label),
+            None => "--- Bookmark ---".to_string(),
+        });
+    }
+
+    pub fn noted(&mut self, text: &str) {
+        self.push(format!("Note: {}", text));
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn has_latest(&self) -> bool {
+        !self.latest.is_empty()
+    }
+
+    /// Transcription for the next invocation, after which nothing is latest anymore
+    pub fn take_latest(&mut self) -> TranscriptionInput {
+        let latest = std::mem::take(&mut self.latest);
+        TranscriptionInput {
+            all: self.history.join("\n"),
+            latest: latest.join("\n"),
+            since_bookmark: self.history[self.bookmark_index..].join("\n"),
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        self.history.push(line.clone());
+        self.latest.push(line);
+    }
+}
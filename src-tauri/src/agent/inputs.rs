@@ -0,0 +1,176 @@
+use crate::config::agents::{Agent, AgentConfig};
+use std::collections::{HashMap, HashSet};
+
+/// Agents whose answers the agent takes as inputs
+pub fn upstream_agents(agent: &Agent) -> &[String] {
+    agent
+        .inputs
+        .as_ref()
+        .map_or(&[], |inputs| inputs.agents.as_slice())
+}
+
+/// Whether the agent is given the transcript, unless it only answers other agents
+pub fn listens_to_transcription(agent: &Agent) -> bool {
+    agent
+        .inputs
+        .as_ref()
+        .is_none_or(|inputs| inputs.transcription)
+}
+
+/// Fails if agents take each other's answers as inputs, which would have them answer each other forever
+pub fn check_input_cycles(agent_configs: &[AgentConfig]) -> Result<(), String> {
+    match find_input_cycle(agent_configs) {
+        Some(cycle) => Err(describe_input_cycle(&cycle)),
+        None => Ok(()),
+    }
+}
+
+/// Leaves out the agents of every cycle of inputs, returned along with the cycles
+pub fn remove_input_cycles(
+    mut agent_configs: Vec<AgentConfig>,
+) -> (Vec<AgentConfig>, Vec<Vec<String>>) {
+    let mut cycles = Vec::new();
+    while let Some(cycle) = find_input_cycle(&agent_configs) {
+        agent_configs.retain(|agent_config| !cycle.contains(&agent_config.name));
+        cycles.push(cycle);
+    }
+    (agent_configs, cycles)
+}
+
+/// Names of every agent along the cycles
+pub fn agents_in_cycles(cycles: &[Vec<String>]) -> HashSet<String> {
+    cycles.iter().flatten().cloned().collect()
+}
+
+pub fn describe_input_cycle(cycle: &[String]) -> String {
+    format!(
+        "Agents take each other's answers as inputs in a cycle: {}",
+        cycle.join(" -> ")
+    )
+}
+
+/// Names along the first cycle of inputs found, starting and ending with the same agent
+fn find_input_cycle(agent_configs: &[AgentConfig]) -> Option<Vec<String>> {
+    let inputs: HashMap<&str, &[String]> = agent_configs
+        .iter()
+        .map(|agent_config| {
+            (
+                agent_config.name.as_str(),
+                upstream_agents(&agent_config.agent),
+            )
+        })
+        .collect();
+    let mut names: Vec<&str> = inputs.keys().copied().collect();
+    names.sort();
+
+    // Agents whose inputs were all followed without coming back around
+    let mut done: Vec<&str> = Vec::new();
+    for name in names {
+        let mut path = Vec::new();
+        if let Some(cycle) = follow_inputs(name, &inputs, &mut path, &mut done) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+fn follow_inputs<'a>(
+    name: &'a str,
+    inputs: &HashMap<&'a str, &'a [String]>,
+    path: &mut Vec<&'a str>,
+    done: &mut Vec<&'a str>,
+) -> Option<Vec<String>> {
+    if done.contains(&name) {
+        return None;
+    }
+    if let Some(start) = path.iter().position(|on_path| *on_path == name) {
+        let mut cycle: Vec<String> = path[start..].iter().map(|name| name.to_string()).collect();
+        cycle.push(name.to_string());
+        return Some(cycle);
+    }
+    path.push(name);
+    // Inputs of agents that don't exist are never answered, so they end the path
+    for upstream in inputs.get(name).copied().unwrap_or_default() {
+        if let Some(cycle) = follow_inputs(upstream, inputs, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.push(name);
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::agents::AgentInputs;
+
+    fn chained(name: &str, upstream: &[&str]) -> AgentConfig {
+        AgentConfig {
+            name: name.to_string(),
+            agent: Agent {
+                interval_in_sec: None,
+                transcription_history_max_chars: None,
+                history: None,
+                inputs: Some(AgentInputs {
+                    transcription: true,
+                    agents: upstream.iter().map(|name| name.to_string()).collect(),
+                }),
+                trigger: None,
+                prompt: String::new(),
+                structured_output: None,
+                vocabulary: None,
+                finalizer: None,
+            },
+        }
+    }
+
+    #[test]
+    fn allows_chains_and_shared_inputs() {
+        let agent_configs = [
+            chained("questions", &[]),
+            chained("answers", &["questions"]),
+            chained("review", &["questions", "answers", "missing"]),
+        ];
+        assert_eq!(find_input_cycle(&agent_configs), None);
+    }
+
+    #[test]
+    fn finds_cycles() {
+        let agent_configs = [
+            chained("a", &["b"]),
+            chained("b", &["c"]),
+            chained("c", &["a"]),
+        ];
+        assert_eq!(
+            find_input_cycle(&agent_configs).unwrap(),
+            ["a", "b", "c", "a"]
+        );
+        assert_eq!(
+            check_input_cycles(&[chained("echo", &["echo"])]).unwrap_err(),
+            "Agents take each other's answers as inputs in a cycle: echo -> echo"
+        );
+    }
+
+    #[test]
+    fn removes_only_agents_in_cycles() {
+        let agent_configs = vec![
+            chained("echo", &["echo"]),
+            chained("questions", &[]),
+            chained("a", &["b"]),
+            chained("b", &["a"]),
+            // Left waiting for answers that never come, but not answering itself
+            chained("review", &["a", "questions"]),
+        ];
+        let (agent_configs, cycles) = remove_input_cycles(agent_configs);
+        let names: Vec<&str> = agent_configs
+            .iter()
+            .map(|agent_config| agent_config.name.as_str())
+            .collect();
+        assert_eq!(names, ["questions", "review"]);
+        assert_eq!(cycles, [vec!["a", "b", "a"], vec!["echo", "echo"]]);
+        let mut disabled: Vec<String> = agents_in_cycles(&cycles).into_iter().collect();
+        disabled.sort();
+        assert_eq!(disabled, ["a", "b", "echo"]);
+    }
+}
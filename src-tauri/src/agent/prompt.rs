@@ -0,0 +1,156 @@
+use crate::config::agents::Agent;
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const PROMPT_TEMPLATE: &str = "prompt";
+const MAPPER_TEMPLATE: &str = "mapper";
+const MAP_TEMPLATE: &str = "map";
+
+// Condenses a part of a meeting for finalizer agents that don't bring their own map prompt
+const DEFAULT_MAP_PROMPT: &str =
+    "Below is part {{chunk.index}} of {{chunk.count}} of a meeting transcript. \
+Write concise notes of what was discussed, every decision that was made and every action item \
+with its owner and due date if they were mentioned.\n\n{{{transcription.all}}}";
+
+handlebars_helper!(json_stringify: |value: Json| to_indented_json(value));
+
+/// Variables of agent prompts, the same the app's prompter fills in
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct TemplateInput {
+    pub transcription: TranscriptionInput,
+    pub meetings: MeetingsInput,
+    pub answer: AnswerInput,
+    // Latest answers of the agents taken as inputs, by agent name
+    pub inputs: BTreeMap<String, AgentOutputInput>,
+    // Only for map prompts of finalizer agents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk: Option<ChunkInput>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ChunkInput {
+    // From 1
+    pub index: usize,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionInput {
+    pub all: String,
+    pub latest: String,
+    // All of it if there is no bookmark
+    pub since_bookmark: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct MeetingsInput {
+    pub related: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct AnswerInput {
+    pub previous: PreviousAnswer,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct PreviousAnswer {
+    pub text: String,
+    // Provided if the agent uses structured output
+    pub json: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct AgentOutputInput {
+    pub text: String,
+    // Provided if the other agent uses structured output
+    pub json: Option<Value>,
+}
+
+/// Prompt and structured output mapper of an agent, compiled once
+pub struct AgentTemplates {
+    handlebars: Handlebars<'static>,
+    structured_output_schema: Option<String>,
+}
+
+impl AgentTemplates {
+    pub fn new(agent: &Agent) -> Result<Self, String> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("json_stringify", Box::new(json_stringify));
+        handlebars
+            .register_template_string(PROMPT_TEMPLATE, &agent.prompt)
+            .map_err(|e| format!("Failed to compile prompt: {}", e))?;
+        if let Some(structured_output) = &agent.structured_output {
+            handlebars
+                .register_template_string(MAPPER_TEMPLATE, &structured_output.mapper)
+                .map_err(|e| format!("Failed to compile structured output mapper: {}", e))?;
+        }
+        if let Some(finalizer) = &agent.finalizer {
+            handlebars
+                .register_template_string(
+                    MAP_TEMPLATE,
+                    finalizer
+                        .map_prompt
+                        .as_deref()
+                        .unwrap_or(DEFAULT_MAP_PROMPT),
+                )
+                .map_err(|e| format!("Failed to compile map prompt: {}", e))?;
+        }
+        Ok(Self {
+            handlebars,
+            structured_output_schema: agent
+                .structured_output
+                .as_ref()
+                .map(|structured_output| structured_output.schema.clone()),
+        })
+    }
+
+    pub fn structured_output_schema(&self) -> Option<&str> {
+        self.structured_output_schema.as_deref()
+    }
+
+    pub fn render_prompt(&self, input: &TemplateInput) -> Result<String, String> {
+        self.handlebars
+            .render(PROMPT_TEMPLATE, input)
+            .map_err(|e| format!("Failed to render prompt: {}", e))
+    }
+
+    /// Prompt condensing a part of a meeting, for finalizer agents
+    pub fn render_map_prompt(&self, input: &TemplateInput) -> Result<String, String> {
+        self.handlebars
+            .render(MAP_TEMPLATE, input)
+            .map_err(|e| format!("Failed to render map prompt: {}", e))
+    }
+
+    /// Text of an answer, mapped from the JSON it came as if the agent uses structured output
+    pub fn map_answer(&self, answer: String) -> Result<(String, Option<Value>), String> {
+        if self.structured_output_schema.is_none() {
+            return Ok((answer, None));
+        }
+        let json: Value = serde_json::from_str(&answer).map_err(|e| {
+            format!(
+                "Failed to parse LLM response as JSON: {}. Response: {}",
+                e, answer
+            )
+        })?;
+        let text = self
+            .handlebars
+            .render(MAPPER_TEMPLATE, &json)
+            .map_err(|e| format!("Failed to map structured output: {}", e))?;
+        Ok((text, Some(json)))
+    }
+}
+
+/// JSON indented by four spaces like JSON.stringify in the prompter
+fn to_indented_json(value: &Value) -> String {
+    let mut json = Vec::new();
+    let mut serializer =
+        serde_json::Serializer::with_formatter(&mut json, PrettyFormatter::with_indent(b"    "));
+    match value.serialize(&mut serializer) {
+        Ok(()) => String::from_utf8(json).unwrap_or_default(),
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::agents::Agent;
+
+/// Time between answers of an agent, 3 seconds unless it says otherwise
+pub fn interval_ms(agent: &Agent) -> u64 {
+    let interval_in_sec = agent
+        .interval_in_sec
+        .filter(|interval_in_sec| *interval_in_sec != 0.0)
+        .unwrap_or(3.0)
+        .max(1.0);
+    (interval_in_sec * 1000.0) as u64
+}
+
+/// When an agent is invoked as transcription comes in, the way the prompter debounces it:
+/// right away when idle, never while an answer is being made, and otherwise once nothing
+/// new came in for the interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    interval_ms: u64,
+    // When the answer being made is done
+    running_until_ms: Option<u64>,
+    // When the delayed invocation is due
+    timeout_at_ms: Option<u64>,
+    // Transcription came in that no invocation covers yet
+    pending: bool,
+}
+
+impl Schedule {
+    pub fn new(interval_ms: u64) -> Self {
+        Self {
+            interval_ms,
+            running_until_ms: None,
+            timeout_at_ms: None,
+            pending: false,
+        }
+    }
+
+    /// Transcription came in, true if the agent is to be invoked right away
+    pub fn transcribed(&mut self, now_ms: u64) -> bool {
+        if self.running_until_ms.is_some() {
+            self.pending = true;
+            return false;
+        }
+        if self.timeout_at_ms.is_none() {
+            return true;
+        }
+        self.timeout_at_ms = Some(now_ms + self.interval_ms);
+        self.pending = true;
+        false
+    }
+
+    /// The agent was invoked and took `duration_ms` to answer
+    pub fn invoked(&mut self, now_ms: u64, duration_ms: u64) {
+        self.running_until_ms = Some(now_ms + duration_ms);
+    }
+
+    /// When something changes next without new transcription
+    pub fn next_due_ms(&self) -> Option<u64> {
+        self.running_until_ms.or(self.timeout_at_ms)
+    }
+
+    /// Moves time forward to what is due, true if the agent is to be invoked
+    pub fn advance(&mut self, now_ms: u64) -> bool {
+        if self
+            .running_until_ms
+            .is_some_and(|until_ms| until_ms <= now_ms)
+        {
+            self.running_until_ms = None;
+            if self.pending {
+                self.timeout_at_ms = Some(now_ms + self.interval_ms);
+            }
+            return false;
+        }
+        if self.timeout_at_ms.is_some_and(|at_ms| at_ms <= now_ms) {
+            self.timeout_at_ms = None;
+            return std::mem::take(&mut self.pending);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Times the agent is invoked at for transcription coming in at `transcribed_ms`
+    fn invocations(transcribed_ms: &[u64], interval_ms: u64, duration_ms: u64) -> Vec<u64> {
+        let mut schedule = Schedule::new(interval_ms);
+        let mut invoked = Vec::new();
+        let mut invoke = |schedule: &mut Schedule, now_ms: u64| {
+            invoked.push(now_ms);
+            schedule.invoked(now_ms, duration_ms);
+        };
+        for &now_ms in transcribed_ms {
+            while let Some(due_ms) = schedule.next_due_ms().filter(|due_ms| *due_ms <= now_ms) {
+                if schedule.advance(due_ms) {
+                    invoke(&mut schedule, due_ms);
+                }
+            }
+            if schedule.transcribed(now_ms) {
+                invoke(&mut schedule, now_ms);
+            }
+        }
+        while let Some(due_ms) = schedule.next_due_ms() {
+            if schedule.advance(due_ms) {
+                invoke(&mut schedule, due_ms);
+            }
+        }
+        invoked
+    }
+
+    #[test]
+    fn invokes_right_away_when_idle() {
+        assert_eq!(invocations(&[0, 5000, 10000], 3000, 1000), [0, 5000, 10000]);
+    }
+
+    #[test]
+    fn batches_transcription_coming_in_while_answering() {
+        // 1500 and 2500 come in while the first answer is made, it is done at 2000
+        assert_eq!(invocations(&[0, 1500, 2500], 3000, 2000), [0, 5500]);
+    }
+
+    #[test]
+    fn waits_for_transcription_to_settle() {
+        assert_eq!(
+            invocations(&[0, 500, 2000, 4000, 6500], 3000, 1000),
+            [0, 9500]
+        );
+    }
+
+    #[test]
+    fn defaults_interval() {
+        let agent = |interval_in_sec| Agent {
+            interval_in_sec,
+            transcription_history_max_chars: None,
+            history: None,
+            inputs: None,
+            trigger: None,
+            prompt: String::new(),
+            structured_output: None,
+            vocabulary: None,
+            finalizer: None,
+        };
+        assert_eq!(interval_ms(&agent(None)), 3000);
+        assert_eq!(interval_ms(&agent(Some(0.0))), 3000);
+        assert_eq!(interval_ms(&agent(Some(0.2))), 1000);
+        assert_eq!(interval_ms(&agent(Some(10.0))), 10000);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::config::agents::Trigger;
+use crate::transcription::event::TranscriptionEvent;
+use log::{error, info};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Mutex;
+
+const AGENT_TRIGGERED_EVENT_TYPE: &str = "agent-triggered";
+
+// Unless the trigger says otherwise
+const DEFAULT_COOLDOWN_MS: u64 = 10000;
+
+// Who questions are asked to, the speaker of the microphone
+const HOST_SPEAKER: &str = "Host";
+
+// Transcription usually ends questions with a question mark, these catch those it doesn't
+const QUESTION_WORDS: [&str; 17] = [
+    "what", "why", "how", "when", "where", "who", "which", "can", "could", "would", "should", "do",
+    "does", "did", "is", "are", "will",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TriggerReason {
+    Keyword,
+    Pattern,
+    QuestionToHost,
+    Silence,
+    Hotkey,
+}
+
+/// Asks the window of an agent to answer what was said since it last answered
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentTriggeredEvent {
+    pub r#type: String,
+    pub agent_name: String,
+    pub reason: TriggerReason,
+}
+
+#[derive(Default)]
+pub struct TriggerState {
+    pub triggers: Arc<Mutex<AgentTriggers>>,
+}
+
+/// Triggers of the agents whose windows are open, by agent name
+#[derive(Default)]
+pub struct AgentTriggers {
+    agents: BTreeMap<String, WatchedAgent>,
+}
+
+struct WatchedAgent {
+    // Lowercase
+    keywords: Vec<String>,
+    pattern: Option<Regex>,
+    question_to_host: bool,
+    silence_ms: Option<u64>,
+    hotkey: bool,
+    cooldown_ms: u64,
+    fired_at_ms: Option<u64>,
+    // When transcription last came in, silence is counted from it
+    heard_at_ms: Option<u64>,
+}
+
+/// Has the trigger of an agent checked against transcription until the agent window closes
+#[tauri::command]
+pub async fn watch_agent_trigger(
+    agent_name: String,
+    trigger: Trigger,
+    state: State<'_, TriggerState>,
+) -> Result<(), String> {
+    info!("Watching trigger of agent {}", agent_name);
+    state.triggers.lock().await.watch(agent_name, &trigger)
+}
+
+#[tauri::command]
+pub async fn unwatch_agent_trigger(
+    agent_name: String,
+    state: State<'_, TriggerState>,
+) -> Result<(), String> {
+    info!("Stopped watching trigger of agent {}", agent_name);
+    state.triggers.lock().await.unwatch(&agent_name);
+    Ok(())
+}
+
+/// Fires the agents whose triggers match what was said, and waits for silence after it
+pub async fn observe_transcription(app_handle: &AppHandle, event: &TranscriptionEvent) {
+    let TranscriptionEvent::TranscriptionData { speaker, text, .. } = event else {
+        return;
+    };
+    let state = app_handle.state::<TriggerState>();
+    let heard_at_ms = now_ms();
+    let (fired, silences) = {
+        let mut triggers = state.triggers.lock().await;
+        let fired = triggers.heard(speaker, text, heard_at_ms);
+        (fired, triggers.silence_waits())
+    };
+    for (agent_name, reason) in fired {
+        emit_triggered(app_handle, agent_name, reason);
+    }
+    for (agent_name, silence_ms) in silences {
+        let app_handle = app_handle.clone();
+        let triggers = state.triggers.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(silence_ms)).await;
+            // Anything said in the meantime waits for silence of its own
+            let silent = triggers
+                .lock()
+                .await
+                .silent(&agent_name, heard_at_ms, now_ms());
+            if silent {
+                emit_triggered(&app_handle, agent_name, TriggerReason::Silence);
+            }
+        });
+    }
+}
+
+/// Fires the agents triggered by the trigger agents shortcut
+pub async fn trigger_hotkey_agents(app_handle: &AppHandle) {
+    let state = app_handle.state::<TriggerState>();
+    let agent_names = state.triggers.lock().await.hotkey(now_ms());
+    for agent_name in agent_names {
+        emit_triggered(app_handle, agent_name, TriggerReason::Hotkey);
+    }
+}
+
+fn emit_triggered(app_handle: &AppHandle, agent_name: String, reason: TriggerReason) {
+    info!("Agent {} triggered by {:?}", agent_name, reason);
+    if let Err(e) = app_handle.emit(
+        AGENT_TRIGGERED_EVENT_TYPE,
+        AgentTriggeredEvent {
+            r#type: AGENT_TRIGGERED_EVENT_TYPE.to_string(),
+            agent_name,
+            reason,
+        },
+    ) {
+        error!("Failed to emit event: {}", e);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+impl AgentTriggers {
+    /// Replaces the trigger the agent had before
+    pub fn watch(&mut self, agent_name: String, trigger: &Trigger) -> Result<(), String> {
+        let pattern = trigger
+            .pattern
+            .as_deref()
+            .map(|pattern| {
+                // Case is rarely transcribed the way it is typed
+                Regex::new(&format!("(?i){}", pattern))
+                    .map_err(|e| format!("Invalid trigger pattern {}: {}", pattern, e))
+            })
+            .transpose()?;
+        self.agents.insert(
+            agent_name,
+            WatchedAgent {
+                keywords: trigger
+                    .keywords
+                    .iter()
+                    .map(|keyword| keyword.trim().to_lowercase())
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect(),
+                pattern,
+                question_to_host: trigger.question_to_host,
+                silence_ms: silence_ms(trigger),
+                hotkey: trigger.hotkey,
+                cooldown_ms: trigger
+                    .cooldown_sec
+                    .map_or(DEFAULT_COOLDOWN_MS, |cooldown_sec| {
+                        (cooldown_sec.max(0.0) * 1000.0) as u64
+                    }),
+                fired_at_ms: None,
+                heard_at_ms: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, agent_name: &str) {
+        self.agents.remove(agent_name);
+    }
+
+    /// Agents to invoke for what was said, each at most once per cooldown
+    pub fn heard(
+        &mut self,
+        speaker: &str,
+        text: &str,
+        now_ms: u64,
+    ) -> Vec<(String, TriggerReason)> {
+        let mut fired = Vec::new();
+        for (agent_name, agent) in self.agents.iter_mut() {
+            agent.heard_at_ms = Some(now_ms);
+            let Some(reason) = agent.reason(speaker, text) else {
+                continue;
+            };
+            if agent.fire(now_ms) {
+                fired.push((agent_name.clone(), reason));
+            }
+        }
+        fired
+    }
+
+    /// Agents that fire on silence, with how long it has to last
+    pub fn silence_waits(&self) -> Vec<(String, u64)> {
+        self.agents
+            .iter()
+            .filter_map(|(agent_name, agent)| Some((agent_name.clone(), agent.silence_ms?)))
+            .collect()
+    }
+
+    /// Whether the agent fires for nothing being said since `heard_at_ms`
+    pub fn silent(&mut self, agent_name: &str, heard_at_ms: u64, now_ms: u64) -> bool {
+        let Some(agent) = self.agents.get_mut(agent_name) else {
+            return false;
+        };
+        let waited_enough = agent
+            .silence_ms
+            .is_some_and(|silence_ms| now_ms >= heard_at_ms.saturating_add(silence_ms));
+        agent.heard_at_ms == Some(heard_at_ms) && waited_enough && agent.fire(now_ms)
+    }
+
+    /// Agents to invoke for the shortcut, which is pressed on purpose and skips the cooldown
+    pub fn hotkey(&mut self, now_ms: u64) -> Vec<String> {
+        self.agents
+            .iter_mut()
+            .filter(|(_, agent)| agent.hotkey)
+            .map(|(agent_name, agent)| {
+                agent.fired_at_ms = Some(now_ms);
+                agent_name.clone()
+            })
+            .collect()
+    }
+}
+
+impl WatchedAgent {
+    fn reason(&self, speaker: &str, text: &str) -> Option<TriggerReason> {
+        let lowercase = text.to_lowercase();
+        if self
+            .keywords
+            .iter()
+            .any(|keyword| lowercase.contains(keyword.as_str()))
+        {
+            return Some(TriggerReason::Keyword);
+        }
+        if self
+            .pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(text))
+        {
+            return Some(TriggerReason::Pattern);
+        }
+        if self.question_to_host && speaker != HOST_SPEAKER && is_question(text) {
+            return Some(TriggerReason::QuestionToHost);
+        }
+        None
+    }
+
+    /// Marks the agent fired unless it still cools down from the last time
+    fn fire(&mut self, now_ms: u64) -> bool {
+        let cooled_down = self
+            .fired_at_ms
+            .is_none_or(|fired_at_ms| now_ms >= fired_at_ms.saturating_add(self.cooldown_ms));
+        if cooled_down {
+            self.fired_at_ms = Some(now_ms);
+        }
+        cooled_down
+    }
+}
+
+/// How long nothing has to be said for the agent to fire, None if silence doesn't trigger it
+pub fn silence_ms(trigger: &Trigger) -> Option<u64> {
+    trigger
+        .silence_sec
+        .filter(|silence_sec| *silence_sec > 0.0)
+        .map(|silence_sec| (silence_sec * 1000.0) as u64)
+}
+
+fn is_question(text: &str) -> bool {
+    let text = text.trim();
+    if text.ends_with('?') {
+        return true;
+    }
+    text.split_whitespace()
+        .next()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .is_some_and(|word| QUESTION_WORDS.contains(&word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watched(trigger: Trigger) -> AgentTriggers {
+        let mut triggers = AgentTriggers::default();
+        triggers.watch("helper".to_string(), &trigger).unwrap();
+        triggers
+    }
+
+    #[test]
+    fn fires_on_keywords_and_patterns_once_per_cooldown() {
+        let mut triggers = watched(Trigger {
+            keywords: vec!["Pricing".to_string()],
+            pattern: Some(r"\$\d+".to_string()),
+            cooldown_sec: Some(5.0),
+            ..Trigger::default()
+        });
+        assert!(triggers.heard("Host", "Hello", 0).is_empty());
+        assert_eq!(
+            triggers.heard("Guest", "What about pricing", 1000),
+            [("helper".to_string(), TriggerReason::Keyword)]
+        );
+        assert!(triggers.heard("Guest", "It is $10", 3000).is_empty());
+        assert_eq!(
+            triggers.heard("Guest", "Or $20", 6000),
+            [("helper".to_string(), TriggerReason::Pattern)]
+        );
+    }
+
+    #[test]
+    fn fires_on_questions_to_the_host() {
+        let mut triggers = watched(Trigger {
+            question_to_host: true,
+            cooldown_sec: Some(0.0),
+            ..Trigger::default()
+        });
+        assert!(triggers.heard("Host", "What do you think?", 0).is_empty());
+        assert!(triggers.heard("Guest", "I think so.", 0).is_empty());
+        assert_eq!(triggers.heard("Guest", "How does it work", 0).len(), 1);
+        assert_eq!(triggers.heard("Guest", "And the price?", 0).len(), 1);
+    }
+
+    #[test]
+    fn fires_once_nothing_was_said_for_a_while() {
+        let mut triggers = watched(Trigger {
+            silence_sec: Some(4.0),
+            ..Trigger::default()
+        });
+        triggers.heard("Guest", "Hmm", 1000);
+        assert_eq!(triggers.silence_waits(), [("helper".to_string(), 4000)]);
+        triggers.heard("Guest", "Let me think", 3000);
+        // Waits for the first line are outdated by the second
+        assert!(!triggers.silent("helper", 1000, 5000));
+        assert!(!triggers.silent("helper", 3000, 6000));
+        assert!(triggers.silent("helper", 3000, 7000));
+    }
+
+    #[test]
+    fn hotkey_skips_the_cooldown() {
+        let mut triggers = watched(Trigger {
+            keywords: vec!["help".to_string()],
+            hotkey: true,
+            ..Trigger::default()
+        });
+        assert_eq!(triggers.heard("Guest", "Help", 0).len(), 1);
+        assert_eq!(triggers.hotkey(1000), ["helper"]);
+        // The hotkey starts the cooldown over
+        assert!(triggers.heard("Guest", "Help", 10500).is_empty());
+        triggers.unwatch("helper");
+        assert!(triggers.hotkey(20000).is_empty());
+    }
+
+    #[test]
+    fn waits_forever_for_endless_silence_and_cooldown() {
+        let mut triggers = watched(Trigger {
+            keywords: vec!["help".to_string()],
+            silence_sec: Some(f64::INFINITY),
+            cooldown_sec: Some(1e20),
+            ..Trigger::default()
+        });
+        assert_eq!(triggers.heard("Guest", "Help", 1000).len(), 1);
+        assert!(triggers.heard("Guest", "Help", u64::MAX - 1).is_empty());
+        assert!(!triggers.silent("helper", u64::MAX - 1, u64::MAX - 1));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let error = AgentTriggers::default()
+            .watch(
+                "helper".to_string(),
+                &Trigger {
+                    pattern: Some("(".to_string()),
+                    ..Trigger::default()
+                },
+            )
+            .unwrap_err();
+        assert!(error.starts_with("Invalid trigger pattern ("));
+    }
+}
@@ -1,4 +1,9 @@
+pub mod device_watcher;
 pub mod devices;
 pub mod driver;
+pub mod input;
 #[cfg(target_os = "macos")]
 pub mod macos_core_audio;
+pub mod meter;
+pub mod noise;
+pub mod resample;
@@ -0,0 +1,261 @@
+use crate::audio::devices::{fetch_default_input_device, list_all_devices, DeviceOption};
+use crate::transcription::control::on_device_change;
+use crate::util::error_handler::show_error;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{async_runtime, AppHandle, Emitter, EventTarget};
+
+/// How often the device list is compared against the previous one
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceChangeEvent {
+    pub r#type: String,
+    // The device that appeared or went away, or the new default input if any
+    pub device: Option<DeviceOption>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DeviceChange {
+    Added(DeviceOption),
+    Removed(DeviceOption),
+    DefaultChanged(Option<DeviceOption>),
+}
+
+impl DeviceChange {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DeviceChange::Added(_) => "device-added",
+            DeviceChange::Removed(_) => "device-removed",
+            DeviceChange::DefaultChanged(_) => "default-device-changed",
+        }
+    }
+
+    fn to_event(&self) -> DeviceChangeEvent {
+        let device = match self {
+            DeviceChange::Added(device) | DeviceChange::Removed(device) => Some(device.clone()),
+            DeviceChange::DefaultChanged(device) => device.clone(),
+        };
+        DeviceChangeEvent {
+            r#type: self.event_type().to_string(),
+            device,
+        }
+    }
+}
+
+/// Devices keyed by UID, which unlike the numeric id survives a reconnect
+#[derive(Default)]
+struct DeviceSnapshot {
+    devices: HashMap<String, DeviceOption>,
+    default_device_uid: Option<String>,
+}
+
+impl DeviceSnapshot {
+    /// Enumerates the devices on a blocking thread, CoreAudio and cpal can take a while
+    async fn capture() -> Result<Self, String> {
+        tokio::task::spawn_blocking(Self::capture_blocking)
+            .await
+            .map_err(|e| format!("Device enumeration failed: {}", e))?
+    }
+
+    fn capture_blocking() -> Result<Self, String> {
+        let devices = list_all_devices()?;
+        let default_device = fetch_default_input_device()?;
+
+        Ok(Self {
+            devices: devices
+                .into_iter()
+                // Skip the virtual entry standing for the default device
+                .filter(|device| device.id >= 0)
+                .map(|device| (device.uid.clone(), device))
+                .collect(),
+            default_device_uid: default_device.map(|device| device.uid),
+        })
+    }
+
+    fn diff(&self, next: &DeviceSnapshot) -> Vec<DeviceChange> {
+        let mut changes = Vec::new();
+        for (uid, device) in &self.devices {
+            match next.devices.get(uid) {
+                None => changes.push(DeviceChange::Removed(device.clone())),
+                // Same device came back under a new id before we noticed it was gone
+                Some(next_device) if next_device.id != device.id => {
+                    changes.push(DeviceChange::Removed(device.clone()));
+                    changes.push(DeviceChange::Added(next_device.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        for (uid, device) in &next.devices {
+            if !self.devices.contains_key(uid) {
+                changes.push(DeviceChange::Added(device.clone()));
+            }
+        }
+        if self.default_device_uid != next.default_device_uid {
+            changes.push(DeviceChange::DefaultChanged(
+                next.default_device_uid
+                    .as_ref()
+                    .and_then(|uid| next.devices.get(uid))
+                    .cloned(),
+            ));
+        }
+        changes
+    }
+}
+
+/// Polls the audio devices for the lifetime of the app, emits change events
+/// and lets the running transcription session rebind its devices
+pub fn start_device_watcher(app_handle: AppHandle) {
+    async_runtime::spawn(async move {
+        let mut snapshot = match DeviceSnapshot::capture().await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                show_error(format!("Failed to start device watcher: {}", e), app_handle);
+                return;
+            }
+        };
+        info!("Watching for audio device changes");
+
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let next = match DeviceSnapshot::capture().await {
+                Ok(next) => next,
+                Err(e) => {
+                    error!("Failed to list audio devices: {}", e);
+                    continue;
+                }
+            };
+
+            for change in snapshot.diff(&next) {
+                info!("Audio device change: {:?}", change);
+                if let Err(e) =
+                    app_handle.emit_to(EventTarget::any(), change.event_type(), change.to_event())
+                {
+                    error!("Failed to emit event: {}", e);
+                }
+                if let Err(e) = on_device_change(app_handle.clone(), change).await {
+                    show_error(
+                        format!("Failed to recover transcription after device change: {}", e),
+                        app_handle.clone(),
+                    );
+                }
+            }
+
+            snapshot = next;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: i32, uid: &str) -> DeviceOption {
+        DeviceOption {
+            id,
+            name: uid.to_string(),
+            uid: uid.to_string(),
+            host: "CoreAudio".to_string(),
+            channels: 1,
+            default_sample_rate: 48000,
+            is_default: false,
+        }
+    }
+
+    fn snapshot(devices: &[DeviceOption], default_device_uid: Option<&str>) -> DeviceSnapshot {
+        DeviceSnapshot {
+            devices: devices
+                .iter()
+                .map(|device| (device.uid.clone(), device.clone()))
+                .collect(),
+            default_device_uid: default_device_uid.map(str::to_string),
+        }
+    }
+
+    // Event type, UID and id of each change, sorted as the snapshots are unordered
+    fn changes(
+        previous: &DeviceSnapshot,
+        next: &DeviceSnapshot,
+    ) -> Vec<(&'static str, String, i32)> {
+        let mut changes: Vec<_> = previous
+            .diff(next)
+            .iter()
+            .map(|change| {
+                let device = change.to_event().device;
+                (
+                    change.event_type(),
+                    device
+                        .as_ref()
+                        .map_or(String::new(), |device| device.uid.clone()),
+                    device.map_or(-1, |device| device.id),
+                )
+            })
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    #[test]
+    fn reports_nothing_for_unchanged_devices() {
+        let devices = [device(1, "mic"), device(2, "headset")];
+        assert!(changes(
+            &snapshot(&devices, Some("mic")),
+            &snapshot(&devices, Some("mic"))
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_added_and_removed_devices() {
+        let previous = snapshot(&[device(1, "mic"), device(2, "headset")], None);
+        let next = snapshot(&[device(1, "mic"), device(3, "webcam")], None);
+        assert_eq!(
+            changes(&previous, &next),
+            vec![
+                ("device-added", "webcam".to_string(), 3),
+                ("device-removed", "headset".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_a_reconnect_under_a_new_id_as_removed_and_added() {
+        let previous = snapshot(&[device(1, "mic"), device(2, "headset")], None);
+        let next = snapshot(&[device(1, "mic"), device(7, "headset")], None);
+        assert_eq!(
+            changes(&previous, &next),
+            vec![
+                ("device-added", "headset".to_string(), 7),
+                ("device-removed", "headset".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_new_default_device() {
+        let devices = [device(1, "mic"), device(2, "headset")];
+        assert_eq!(
+            changes(
+                &snapshot(&devices, Some("mic")),
+                &snapshot(&devices, Some("headset"))
+            ),
+            vec![("default-device-changed", "headset".to_string(), 2)]
+        );
+        // A default that went away together with its device is reported as no default
+        assert_eq!(
+            changes(
+                &snapshot(&devices, Some("headset")),
+                &snapshot(&devices[..1], None)
+            ),
+            vec![
+                ("default-device-changed", String::new(), -1),
+                ("device-removed", "headset".to_string(), 2),
+            ]
+        );
+    }
+}
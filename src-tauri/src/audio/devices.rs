@@ -18,9 +18,61 @@ pub async fn get_hidden_device() -> Result<Option<DeviceOption>, String> {
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct DeviceOption {
+    // Transient id assigned by the OS, changes when a device reconnects
     pub id: i32,
     pub name: String,
+    // Stable identifier, the device UID or host and name if the backend has no UID
+    pub uid: String,
+    pub host: String,
+    pub channels: u16,
+    pub default_sample_rate: u32,
+    pub is_default: bool,
+}
+
+/// Selects a device either by its stable UID or, for compatibility, by its numeric id
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum DeviceSelector {
+    Id(i32),
+    Uid(String),
+}
+
+/// Device id used by clients to select whatever the system default microphone is
+pub const DEFAULT_DEVICE_ID: i32 = -1;
+/// Device UID used by clients to select whatever the system default microphone is
+pub const DEFAULT_DEVICE_UID: &str = "default";
+
+/// Identifier for backends that expose no UID of their own
+pub fn fallback_device_uid(host: &str, name: &str) -> String {
+    format!("{}:{}", host, name)
+}
+
+/// All devices that can be listened to, including the default and the hidden output device
+pub fn list_all_devices() -> Result<Vec<DeviceOption>, String> {
+    let mut devices = Vec::new();
+    list_available_audio_input_devices(&mut devices)?;
+    devices.extend(fetch_hidden_output_device()?);
+    Ok(devices)
+}
+
+/// Resolve a selector to the device it currently points to
+pub fn resolve_device(selector: &DeviceSelector) -> Result<DeviceOption, String> {
+    let devices = list_all_devices()?;
+    let device = match selector {
+        DeviceSelector::Id(id) if *id < DEFAULT_DEVICE_ID => {
+            return Err(format!(
+                "Invalid device ID: {}. Device IDs must be -1 (default) or positive integers.",
+                id
+            ))
+        }
+        DeviceSelector::Id(id) => devices.into_iter().find(|device| device.id == *id),
+        DeviceSelector::Uid(uid) => devices.into_iter().find(|device| {
+            device.uid == *uid || fallback_device_uid(&device.host, &device.name) == *uid
+        }),
+    };
+    device.ok_or_else(|| format!("Device {:?} not found", selector))
 }
 
 #[cfg(not(target_os = "macos"))]
@@ -29,18 +81,29 @@ pub fn list_available_audio_input_devices(devices: &mut Vec<DeviceOption>) -> Re
 }
 
 #[cfg(not(target_os = "macos"))]
-fn fetch_hidden_output_device() -> Result<Option<DeviceOption>, String> {
+pub fn fetch_hidden_output_device() -> Result<Option<DeviceOption>, String> {
     Err("Platform not supported")
 }
 
+#[cfg(not(target_os = "macos"))]
+pub fn fetch_default_input_device() -> Result<Option<DeviceOption>, String> {
+    Err("Platform not supported".to_string())
+}
+
 #[cfg(target_os = "macos")]
-fn list_available_audio_input_devices(devices: &mut Vec<DeviceOption>) -> Result<(), String> {
+pub fn list_available_audio_input_devices(devices: &mut Vec<DeviceOption>) -> Result<(), String> {
     use super::macos_core_audio::list_available_audio_input_devices_macos;
     list_available_audio_input_devices_macos(devices)
 }
 
 #[cfg(target_os = "macos")]
-fn fetch_hidden_output_device() -> Result<Option<DeviceOption>, String> {
+pub fn fetch_hidden_output_device() -> Result<Option<DeviceOption>, String> {
     use super::macos_core_audio::fetch_hidden_output_device_macos;
     fetch_hidden_output_device_macos()
 }
+
+#[cfg(target_os = "macos")]
+pub fn fetch_default_input_device() -> Result<Option<DeviceOption>, String> {
+    use super::macos_core_audio::fetch_default_input_device_macos;
+    fetch_default_input_device_macos()
+}
@@ -0,0 +1,235 @@
+use crate::audio::devices::{DeviceOption, DEFAULT_DEVICE_ID};
+use futures_core::ready;
+use kalosm::sound::AsyncSource;
+use log::{error, info};
+use rodio::cpal;
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::cpal::{FromSample, SampleFormat, SizedSample, StreamError};
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+/// Interleaved audio captured from an input device, one chunk per cpal callback.
+///
+/// The cpal stream is not `Send` on every platform so it lives on its own thread,
+/// which is stopped once this stream is dropped or the device goes away.
+pub struct AudioInputStream {
+    receiver: UnboundedReceiver<Vec<f32>>,
+    channels: u16,
+    sample_rate: u32,
+    stop_sender: mpsc::Sender<()>,
+}
+
+impl AudioInputStream {
+    /// Opens the device through the host API, or the system default input
+    pub async fn open(device: &DeviceOption) -> Result<Self, String> {
+        let (sample_sender, sample_receiver) = unbounded_channel::<Vec<f32>>();
+        // Opening can take a while, wait for it without blocking the runtime
+        let (ready_sender, ready_receiver) = oneshot::channel::<Result<(u16, u32), String>>();
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+
+        let device = device.clone();
+        let error_stop_sender = stop_sender.clone();
+        std::thread::Builder::new()
+            .name(format!("audio-input-{}", device.name))
+            .spawn(move || {
+                let stream = match build_stream(&device, sample_sender, error_stop_sender) {
+                    Ok((stream, channels, sample_rate)) => {
+                        let _ = ready_sender.send(Ok((channels, sample_rate)));
+                        stream
+                    }
+                    Err(e) => {
+                        let _ = ready_sender.send(Err(e));
+                        return;
+                    }
+                };
+
+                // Keep the stream alive until the reader goes away or the device fails
+                let _ = stop_receiver.recv();
+                drop(stream);
+                info!("Closed audio input {}", device.name);
+            })
+            .map_err(|e| format!("Failed to spawn audio input thread: {}", e))?;
+
+        let (channels, sample_rate) = ready_receiver
+            .await
+            .map_err(|_| "Audio input thread exited before the stream started".to_string())??;
+
+        Ok(Self {
+            receiver: sample_receiver,
+            channels,
+            sample_rate,
+            stop_sender,
+        })
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl Drop for AudioInputStream {
+    fn drop(&mut self) {
+        let _ = self.stop_sender.send(());
+    }
+}
+
+impl futures_core::Stream for AudioInputStream {
+    type Item = Vec<f32>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+/// Flattens a stream of mono chunks into the sample stream the voice activity detector reads,
+/// see `ResampledStream` to get there from the raw device stream
+pub struct SampleStream<S> {
+    source: S,
+    sample_rate: u32,
+    buffer: std::vec::IntoIter<f32>,
+}
+
+impl<S> SampleStream<S> {
+    pub fn new(source: S, sample_rate: u32) -> Self {
+        Self {
+            source,
+            sample_rate,
+            buffer: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<S: futures_core::Stream<Item = Vec<f32>> + Unpin> futures_core::Stream for SampleStream<S> {
+    type Item = f32;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(sample) = this.buffer.next() {
+                return Poll::Ready(Some(sample));
+            }
+            match ready!(Pin::new(&mut this.source).poll_next(cx)) {
+                Some(chunk) => this.buffer = chunk.into_iter(),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<S: futures_core::Stream<Item = Vec<f32>> + Unpin> AsyncSource for SampleStream<S> {
+    fn as_stream(&mut self) -> impl futures_core::Stream<Item = f32> + '_ {
+        self
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+/// Finds the cpal device matching the device option, by UID or id where the platform allows
+fn find_cpal_device(host: &cpal::Host, device: &DeviceOption) -> Result<cpal::Device, String> {
+    if device.id == DEFAULT_DEVICE_ID {
+        return host
+            .default_input_device()
+            .ok_or_else(|| "No default input device available".to_string());
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use crate::audio::macos_core_audio::find_cpal_device_macos;
+        if let Some(cpal_device) = find_cpal_device_macos(host, device)? {
+            return Ok(cpal_device);
+        }
+    }
+
+    // Two devices of the same name can't be told apart here, only used where there is no better way
+    host.input_devices()
+        .map_err(|e| format!("Failed to list input devices: {}", e))?
+        .find(|cpal_device| cpal_device.name().map_or(false, |name| name == device.name))
+        .ok_or_else(|| {
+            format!(
+                "Device {} not found by the {} audio host",
+                device.name,
+                host.id().name()
+            )
+        })
+}
+
+/// Builds and starts the input stream, returns it with its channel count and sample rate
+fn build_stream(
+    device: &DeviceOption,
+    sender: UnboundedSender<Vec<f32>>,
+    stop_sender: mpsc::Sender<()>,
+) -> Result<(cpal::Stream, u16, u32), String> {
+    let host = cpal::default_host();
+    let cpal_device = find_cpal_device(&host, device)?;
+    let supported_config = cpal_device.default_input_config().map_err(|e| {
+        format!(
+            "Failed to get default input config for device {}: {}",
+            device.name, e
+        )
+    })?;
+    let sample_rate = supported_config.sample_rate().0;
+    let config = supported_config.config();
+
+    let stream = match supported_config.sample_format() {
+        SampleFormat::F32 => build_typed_stream::<f32>(&cpal_device, &config, sender, stop_sender),
+        SampleFormat::I16 => build_typed_stream::<i16>(&cpal_device, &config, sender, stop_sender),
+        SampleFormat::U16 => build_typed_stream::<u16>(&cpal_device, &config, sender, stop_sender),
+        SampleFormat::I32 => build_typed_stream::<i32>(&cpal_device, &config, sender, stop_sender),
+        SampleFormat::I8 => build_typed_stream::<i8>(&cpal_device, &config, sender, stop_sender),
+        SampleFormat::U8 => build_typed_stream::<u8>(&cpal_device, &config, sender, stop_sender),
+        SampleFormat::F64 => build_typed_stream::<f64>(&cpal_device, &config, sender, stop_sender),
+        sample_format => Err(format!(
+            "Unsupported sample format {} for device {}",
+            sample_format, device.name
+        )),
+    }?;
+
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start device {}: {}", device.name, e))?;
+    info!(
+        "Opened audio input {} at {}Hz with {} channels",
+        device.name, sample_rate, config.channels
+    );
+
+    Ok((stream, config.channels, sample_rate))
+}
+
+fn build_typed_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    sender: UnboundedSender<Vec<f32>>,
+    stop_sender: mpsc::Sender<()>,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device
+        .build_input_stream::<T, _, _>(
+            config,
+            move |data: &[T], _| {
+                // Downmixing and resampling happen later, off the audio thread
+                let samples = data.iter().map(|sample| f32::from_sample(*sample)).collect();
+                let _ = sender.send(samples);
+            },
+            move |e| {
+                error!("Audio input stream error: {}", e);
+                if let StreamError::DeviceNotAvailable = e {
+                    // Close the stream so readers see it end
+                    let _ = stop_sender.send(());
+                }
+            },
+            None,
+        )
+        .map_err(|e| format!("Failed to build input stream: {}", e))
+}
@@ -1,10 +1,11 @@
 use crate::audio;
-use audio::devices::DeviceOption;
+use audio::devices::{fallback_device_uid, DeviceOption, DEFAULT_DEVICE_ID, DEFAULT_DEVICE_UID};
 use core_foundation::base::TCFType;
 use core_foundation::string::{CFString, CFStringRef};
 use coreaudio_sys::{
-    kAudioDevicePropertyDeviceNameCFString, kAudioDevicePropertyScopeInput,
-    kAudioDevicePropertyScopeOutput, kAudioDevicePropertyStreamConfiguration,
+    kAudioDevicePropertyDeviceNameCFString, kAudioDevicePropertyDeviceUID,
+    kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyScopeInput,
+    kAudioDevicePropertyScopeOutput, kAudioDevicePropertyStreamConfiguration, AudioBufferList,
     kAudioHardwareNoError, kAudioHardwarePropertyDefaultInputDevice, kAudioHardwarePropertyDevices,
     kAudioHardwarePropertyTranslateUIDToDevice, kAudioObjectPropertyElementMaster,
     kAudioObjectPropertyScopeGlobal, kAudioObjectSystemObject, kCFAllocatorDefault,
@@ -13,6 +14,8 @@ use coreaudio_sys::{
 };
 use lazy_static::lazy_static;
 use log::info;
+use rodio::cpal;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
 use std::ffi::CString;
 use std::mem;
 use std::os::raw::c_void;
@@ -25,6 +28,7 @@ lazy_static! {
 
 const LOCAL_ECHO_INTERNAL_UID: &str = "Ollisten_INTERNAL";
 const LOCAL_ECHO_INTERNAL_DISPLAY_NAME: &str = "Ollisten";
+const CORE_AUDIO_HOST: &str = "CoreAudio";
 
 pub fn fetch_hidden_output_device_macos() -> Result<Option<DeviceOption>, String> {
     // Acquire lock before audio operations
@@ -44,10 +48,12 @@ pub fn fetch_hidden_output_device_macos() -> Result<Option<DeviceOption>, String
 
     let name = get_device_name(device_id).unwrap_or_else(|_| "Unknown Device".to_string());
     info!("Internal device {LOCAL_ECHO_INTERNAL_UID} found with id {device_id} name {name}");
-    Ok(Some(DeviceOption {
-        id: device_id as i32,
-        name: LOCAL_ECHO_INTERNAL_DISPLAY_NAME.to_string(),
-    }))
+    let default_device_id = get_default_input_device().ok();
+    Ok(Some(describe_device(
+        device_id,
+        LOCAL_ECHO_INTERNAL_DISPLAY_NAME.to_string(),
+        default_device_id,
+    )))
 }
 
 pub fn list_available_audio_input_devices_macos(
@@ -56,18 +62,58 @@ pub fn list_available_audio_input_devices_macos(
     // Acquire lock before audio operations
     let _guard = AudioDeviceMutex.lock().map_err(|e| e.to_string())?;
 
-    // Default microphone
+    // Default microphone, described by whichever device is the default right now
+    let default_device_id = get_default_input_device().ok().filter(|id| *id != 0);
     devices.push(DeviceOption {
-        id: -1,
+        id: DEFAULT_DEVICE_ID,
         name: "Default".to_string(),
+        uid: DEFAULT_DEVICE_UID.to_string(),
+        host: CORE_AUDIO_HOST.to_string(),
+        channels: default_device_id.map_or(0, get_input_channel_count),
+        default_sample_rate: default_device_id
+            .and_then(|id| get_nominal_sample_rate(id).ok())
+            .unwrap_or(0),
+        is_default: true,
     });
 
     // All available other microphones
-    list_audio_input_devices(devices)?;
+    list_audio_input_devices(devices, default_device_id)?;
 
     Ok(())
 }
 
+pub fn fetch_default_input_device_macos() -> Result<Option<DeviceOption>, String> {
+    // Acquire lock before audio operations
+    let _guard = AudioDeviceMutex.lock().map_err(|e| e.to_string())?;
+
+    // kAudioObjectUnknown, there is no default input at the moment
+    let device_id = get_default_input_device()?;
+    if device_id == 0 {
+        return Ok(None);
+    }
+
+    let name = get_device_name(device_id)?;
+    Ok(Some(describe_device(device_id, name, Some(device_id))))
+}
+
+/// Describes a device by both its transient id and its persistent UID
+fn describe_device(
+    device_id: AudioDeviceID,
+    name: String,
+    default_device_id: Option<AudioDeviceID>,
+) -> DeviceOption {
+    DeviceOption {
+        id: device_id as i32,
+        uid: get_device_uid(device_id)
+            .unwrap_or_else(|_| fallback_device_uid(CORE_AUDIO_HOST, &name)),
+        host: CORE_AUDIO_HOST.to_string(),
+        channels: get_input_channel_count(device_id),
+        default_sample_rate: get_nominal_sample_rate(device_id).unwrap_or(0),
+        is_default: default_device_id == Some(device_id),
+        name,
+    }
+}
+
 /// Fetches all audio device IDs from the system
 fn fetch_all_device_ids() -> Result<Vec<AudioDeviceID>, String> {
     unsafe {
@@ -254,8 +300,118 @@ fn get_device_name(device_id: AudioDeviceID) -> Result<String, String> {
     }
 }
 
+/// Gets the persistent UID of an audio device, unlike the device ID it survives reconnects and reboots
+fn get_device_uid(device_id: AudioDeviceID) -> Result<String, String> {
+    unsafe {
+        let uid_address = AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyDeviceUID,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMaster,
+        };
+        let mut property_size = mem::size_of::<CFStringRef>() as u32;
+
+        let mut device_uid_ref: CFStringRef = ptr::null_mut();
+        let status = AudioObjectGetPropertyData(
+            device_id,
+            &uid_address,
+            0,
+            ptr::null(),
+            &mut property_size,
+            &mut device_uid_ref as *mut _ as *mut _,
+        );
+
+        if status as i32 == kAudioHardwareNoError as i32 && !device_uid_ref.is_null() {
+            let cf_string: CFString = TCFType::wrap_under_create_rule(device_uid_ref);
+            Ok(cf_string.to_string())
+        } else {
+            Err(format!("Error getting device UID: {}", status))
+        }
+    }
+}
+
+/// Counts the input channels across all input streams of a device
+fn get_input_channel_count(device_id: AudioDeviceID) -> u16 {
+    unsafe {
+        let input_stream_config_address = AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyStreamConfiguration,
+            mScope: kAudioDevicePropertyScopeInput,
+            mElement: kAudioObjectPropertyElementMaster,
+        };
+
+        let mut property_size: u32 = 0;
+        let status = AudioObjectGetPropertyDataSize(
+            device_id,
+            &input_stream_config_address,
+            0,
+            ptr::null(),
+            &mut property_size,
+        );
+        if status as i32 != kAudioHardwareNoError as i32
+            || (property_size as usize) < mem::size_of::<AudioBufferList>()
+        {
+            return 0;
+        }
+
+        // The buffer list has a variable number of trailing buffers, back it with u64 for alignment
+        let mut buffer = vec![0u64; (property_size as usize).div_ceil(mem::size_of::<u64>())];
+        let status = AudioObjectGetPropertyData(
+            device_id,
+            &input_stream_config_address,
+            0,
+            ptr::null(),
+            &mut property_size,
+            buffer.as_mut_ptr() as *mut _,
+        );
+        if status as i32 != kAudioHardwareNoError as i32 {
+            return 0;
+        }
+
+        let buffer_list = &*(buffer.as_ptr() as *const AudioBufferList);
+        let buffers = std::slice::from_raw_parts(
+            buffer_list.mBuffers.as_ptr(),
+            buffer_list.mNumberBuffers as usize,
+        );
+        buffers
+            .iter()
+            .map(|buffer| buffer.mNumberChannels)
+            .sum::<u32>()
+            .min(u16::MAX as u32) as u16
+    }
+}
+
+/// Gets the sample rate the device is currently running at
+fn get_nominal_sample_rate(device_id: AudioDeviceID) -> Result<u32, String> {
+    unsafe {
+        let sample_rate_address = AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyNominalSampleRate,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMaster,
+        };
+
+        let mut sample_rate: f64 = 0.0;
+        let mut property_size = mem::size_of::<f64>() as u32;
+        let status = AudioObjectGetPropertyData(
+            device_id,
+            &sample_rate_address,
+            0,
+            ptr::null(),
+            &mut property_size,
+            &mut sample_rate as *mut f64 as *mut _,
+        );
+
+        if status as i32 != kAudioHardwareNoError as i32 {
+            return Err(format!("Error getting device sample rate: {}", status));
+        }
+
+        Ok(sample_rate.round() as u32)
+    }
+}
+
 /// Lists audio input devices with improved filtering
-fn list_audio_input_devices(devices: &mut Vec<DeviceOption>) -> Result<(), String> {
+fn list_audio_input_devices(
+    devices: &mut Vec<DeviceOption>,
+    default_device_id: Option<AudioDeviceID>,
+) -> Result<(), String> {
     // Get all device IDs
     let all_device_ids = fetch_all_device_ids()?;
 
@@ -265,10 +421,7 @@ fn list_audio_input_devices(devices: &mut Vec<DeviceOption>) -> Result<(), Strin
             match get_device_name(device_id) {
                 Ok(name) => {
                     info!("Found device {name} found with id {device_id}");
-                    devices.push(DeviceOption {
-                        id: device_id as i32,
-                        name,
-                    });
+                    devices.push(describe_device(device_id, name, default_device_id));
                 }
                 Err(_) => {
                     info!("Found device with no name with id {device_id}");
@@ -282,6 +435,45 @@ fn list_audio_input_devices(devices: &mut Vec<DeviceOption>) -> Result<(), Strin
     Ok(())
 }
 
+/// Finds the device by its UID or else the id it was listed under, then opens it through the
+/// host under the name CoreAudio currently gives it. Hidden devices such as the internal loopback
+/// aren't enumerated by cpal and can't be opened until it can build a device from its id
+pub fn find_cpal_device_macos(
+    host: &cpal::Host,
+    device: &DeviceOption,
+) -> Result<Option<cpal::Device>, String> {
+    let name = {
+        // Acquire lock before audio operations
+        let _guard = AudioDeviceMutex.lock().map_err(|e| e.to_string())?;
+
+        // UIDs made up from the name when CoreAudio had none are not found, the id still is
+        let device_id = match get_device_by_uid(&device.uid) {
+            Ok(Some(id)) => id,
+            Ok(None) | Err(_) if device.id > 0 => device.id as AudioDeviceID,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        match get_device_name(device_id) {
+            Ok(name) => name,
+            // Gone since it was listed
+            Err(_) => return Ok(None),
+        }
+    };
+
+    let cpal_device = host
+        .input_devices()
+        .map_err(|e| format!("Failed to list input devices: {}", e))?
+        .find(|cpal_device| cpal_device.name().is_ok_and(|cpal_name| cpal_name == name));
+    match cpal_device {
+        Some(cpal_device) => Ok(Some(cpal_device)),
+        None => Err(format!(
+            "Device {} is hidden from the {} audio host and can't be opened",
+            device.name,
+            host.id().name()
+        )),
+    }
+}
+
 /// Get the device ID for a given UID, only way to get a hidden device
 fn get_device_by_uid(uid: &str) -> Result<Option<AudioDeviceID>, String> {
     unsafe {
@@ -0,0 +1,299 @@
+use crate::audio::devices::{resolve_device, DeviceSelector};
+use crate::audio::input::{AudioInputStream, SampleStream};
+use crate::audio::resample::ResampledStream;
+use crate::transcription::speech_to_text::SPEECH_SAMPLE_RATE;
+use crate::util::error_handler::show_error;
+use futures_core::ready;
+use kalosm::sound::*;
+use log::{error, info};
+use rodio::buffer::SamplesBuffer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::{mpsc, Mutex};
+
+const AUDIO_LEVEL_EVENT_TYPE: &str = "audio-level";
+
+/// How often at most a level event is emitted per device
+const METER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Peak amplitude below which a metering window is considered silent (about -60 dBFS)
+const SILENCE_PEAK_THRESHOLD: f32 = 0.001;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioLevelEvent {
+    pub r#type: String,
+    pub device_id: i32,
+    // Root mean square of the samples in the window, from 0 to 1
+    pub rms: f32,
+    // Highest absolute sample in the window, from 0 to 1
+    pub peak: f32,
+    // Highest voice activity probability in the window, from 0 to 1
+    pub voice_probability: f32,
+    // True if the whole window stayed below the silence threshold
+    pub silent: bool,
+}
+
+pub struct LevelMeterState {
+    pub meters: Arc<Mutex<HashMap<i32, Box<dyn FnMut() + Send + Sync>>>>,
+}
+
+/// Accumulates voice activity outputs and produces a throttled level reading
+pub struct LevelMeter {
+    device_id: i32,
+    sum_squares: f64,
+    sample_count: u64,
+    peak: f32,
+    voice_probability: f32,
+    last_emitted: Instant,
+}
+
+impl LevelMeter {
+    pub fn new(device_id: i32) -> Self {
+        Self {
+            device_id,
+            sum_squares: 0.0,
+            sample_count: 0,
+            peak: 0.0,
+            voice_probability: 0.0,
+            last_emitted: Instant::now(),
+        }
+    }
+
+    pub fn observe(&mut self, output: &VoiceActivityDetectorOutput) -> Option<AudioLevelEvent> {
+        self.observe_samples(&output.samples, output.probability)
+    }
+
+    fn observe_samples(
+        &mut self,
+        samples: &SamplesBuffer<f32>,
+        probability: f32,
+    ) -> Option<AudioLevelEvent> {
+        for sample in samples.clone() {
+            self.sum_squares += (sample as f64) * (sample as f64);
+            self.peak = self.peak.max(sample.abs());
+            self.sample_count += 1;
+        }
+        self.voice_probability = self.voice_probability.max(probability);
+
+        if self.last_emitted.elapsed() < METER_INTERVAL || self.sample_count == 0 {
+            return None;
+        }
+
+        let event = AudioLevelEvent {
+            r#type: AUDIO_LEVEL_EVENT_TYPE.to_string(),
+            device_id: self.device_id,
+            rms: (self.sum_squares / self.sample_count as f64).sqrt() as f32,
+            peak: self.peak,
+            voice_probability: self.voice_probability,
+            silent: self.peak < SILENCE_PEAK_THRESHOLD,
+        };
+        self.sum_squares = 0.0;
+        self.sample_count = 0;
+        self.peak = 0.0;
+        self.voice_probability = 0.0;
+        self.last_emitted = Instant::now();
+        Some(event)
+    }
+}
+
+/// Passes voice activity outputs through unchanged while feeding a level meter
+pub struct MeteredStream<S> {
+    source: S,
+    meter: LevelMeter,
+    sender: mpsc::Sender<AudioLevelEvent>,
+}
+
+impl<S> MeteredStream<S> {
+    pub fn new(source: S, meter: LevelMeter, sender: mpsc::Sender<AudioLevelEvent>) -> Self {
+        Self {
+            source,
+            meter,
+            sender,
+        }
+    }
+}
+
+impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin> futures_core::Stream
+    for MeteredStream<S>
+{
+    type Item = VoiceActivityDetectorOutput;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let next = ready!(Pin::new(&mut this.source).poll_next(cx));
+        if let Some(ref output) = next {
+            if let Some(event) = this.meter.observe(output) {
+                // Never block the audio path, dropping a reading is fine
+                let _ = this.sender.try_send(event);
+            }
+        }
+        Poll::Ready(next)
+    }
+}
+
+/// Spawns a task that emits level events for a device until the returned sender is dropped
+pub fn spawn_level_emitter(app_handle: AppHandle) -> mpsc::Sender<AudioLevelEvent> {
+    let (tx, mut rx) = mpsc::channel::<AudioLevelEvent>(8);
+    tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            if let Err(e) = app_handle.emit(AUDIO_LEVEL_EVENT_TYPE, event) {
+                error!("Failed to emit level event: {}", e);
+            }
+        }
+    });
+    tx
+}
+
+#[tauri::command]
+pub async fn start_level_meter(
+    app_handle: AppHandle,
+    device_ids: Vec<DeviceSelector>,
+    state: State<'_, LevelMeterState>,
+) -> Result<(), String> {
+    info!("Command: start_level_meter {:?}", device_ids);
+
+    let mut meters = state.meters.lock().await;
+    abort_all_meters(&mut meters);
+
+    for selector in device_ids {
+        let device = resolve_device(&selector)?;
+        let device_id = device.id;
+        let input = AudioInputStream::open(&device).await?;
+        // Downmix and resample to the 16kHz mono the voice activity detector and Whisper expect
+        let channels = input.channels();
+        let sample_rate = input.sample_rate();
+        let input = ResampledStream::new(input, channels, sample_rate);
+        let stream = MeteredStream::new(
+            SampleStream::new(input, SPEECH_SAMPLE_RATE).voice_activity_stream(),
+            LevelMeter::new(device_id),
+            spawn_level_emitter(app_handle.clone()),
+        );
+
+        let (abort_sender, abort_receiver) = tokio::sync::oneshot::channel();
+        let task_handle = app_handle.clone();
+        let handle = tokio::spawn(async move {
+            let mut stream = stream;
+            tokio::select! {
+                _ = abort_receiver => {},
+                _ = async {
+                    // Drain the stream, metering happens as a side effect
+                    while stream.next().await.is_some() {}
+                    show_error(
+                        format!("Level meter for device {} stopped unexpectedly", device_id),
+                        task_handle,
+                    );
+                } => {}
+            }
+        });
+
+        meters.insert(
+            device_id,
+            Box::new({
+                let mut abort_sender = Some(abort_sender);
+                move || {
+                    if let Some(sender) = abort_sender.take() {
+                        let _ = sender.send(());
+                    }
+                    handle.abort();
+                }
+            }),
+        );
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_level_meter(state: State<'_, LevelMeterState>) -> Result<(), String> {
+    info!("Command: stop_level_meter");
+
+    let mut meters = state.meters.lock().await;
+    abort_all_meters(&mut meters);
+
+    Ok(())
+}
+
+fn abort_all_meters(meters: &mut HashMap<i32, Box<dyn FnMut() + Send + Sync>>) {
+    for (id, mut abort) in meters.drain() {
+        info!("Stopping level meter for device {}", id);
+        abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observe(
+        meter: &mut LevelMeter,
+        samples: &[f32],
+        probability: f32,
+    ) -> Option<AudioLevelEvent> {
+        meter.observe_samples(&SamplesBuffer::new(1, 16000, samples.to_vec()), probability)
+    }
+
+    // Pretends the last event went out a full interval ago
+    fn make_due(meter: &mut LevelMeter) {
+        meter.last_emitted = Instant::now() - METER_INTERVAL;
+    }
+
+    #[test]
+    fn reports_rms_peak_and_voice_probability() {
+        let mut meter = LevelMeter::new(3);
+        make_due(&mut meter);
+
+        let event = observe(&mut meter, &[0.5, -0.5, 0.5, -0.8, 0.0], 0.7).unwrap();
+        assert_eq!(event.r#type, AUDIO_LEVEL_EVENT_TYPE);
+        assert_eq!(event.device_id, 3);
+        assert!((event.rms - (1.39f32 / 5.0).sqrt()).abs() < 1e-6);
+        assert_eq!(event.peak, 0.8);
+        assert_eq!(event.voice_probability, 0.7);
+        assert!(!event.silent);
+    }
+
+    #[test]
+    fn reports_silence_below_the_threshold() {
+        let mut meter = LevelMeter::new(0);
+        make_due(&mut meter);
+
+        let event = observe(&mut meter, &[0.0005, -0.0009, 0.0], 0.0).unwrap();
+        assert!(event.silent);
+        make_due(&mut meter);
+        let event = observe(&mut meter, &[0.0005, -SILENCE_PEAK_THRESHOLD], 0.0).unwrap();
+        assert!(!event.silent);
+    }
+
+    #[test]
+    fn accumulates_until_the_interval_has_passed() {
+        let mut meter = LevelMeter::new(0);
+        assert!(observe(&mut meter, &[1.0, -1.0], 0.9).is_none());
+        assert!(observe(&mut meter, &[], 0.2).is_none());
+
+        make_due(&mut meter);
+        let event = observe(&mut meter, &[0.0, 0.0], 0.1).unwrap();
+        assert!((event.rms - 0.5f32.sqrt()).abs() < 1e-6);
+        assert_eq!(event.peak, 1.0);
+        assert_eq!(event.voice_probability, 0.9);
+
+        // The window starts over and the next event waits for another interval
+        assert!(observe(&mut meter, &[0.1], 0.0).is_none());
+        make_due(&mut meter);
+        let event = observe(&mut meter, &[], 0.0).unwrap();
+        assert!((event.rms - 0.1).abs() < 1e-6);
+        assert_eq!(event.peak, 0.1);
+        assert_eq!(event.voice_probability, 0.0);
+    }
+
+    #[test]
+    fn skips_windows_without_samples() {
+        let mut meter = LevelMeter::new(0);
+        make_due(&mut meter);
+        assert!(observe(&mut meter, &[], 1.0).is_none());
+    }
+}
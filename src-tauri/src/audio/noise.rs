@@ -0,0 +1,324 @@
+use futures_core::ready;
+use log::error;
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+const NOISE_SUPPRESSION_LEVEL_EVENT_TYPE: &str = "noise-suppression-level";
+
+/// How often at most the before and after levels are emitted per device
+const LEVEL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 32ms frames at 16kHz, half overlapping
+const FRAME_LEN: usize = 512;
+const HOP_LEN: usize = FRAME_LEN / 2;
+
+/// How much more than the noise floor a bin needs to pass through untouched
+const OVER_SUBTRACTION: f32 = 3.0;
+
+/// Lowest gain at full strength, about -20dB, silencing bins completely sounds watery
+const MIN_GAIN: f32 = 0.1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NoiseSuppressionLevelEvent {
+    pub r#type: String,
+    pub device_id: i32,
+    // From 0 (off) to 1
+    pub strength: f32,
+    // Root mean square of the audio going in and coming out over the last interval
+    pub rms_before: f32,
+    pub rms_after: f32,
+}
+
+/// Spectral gating noise suppression for 16kHz mono audio.
+///
+/// Tracks the noise floor of each frequency bin and attenuates bins that don't rise
+/// clearly above it, so steady background noise and distant chatter get quieter while
+/// speech close to the microphone passes through.
+pub struct NoiseSuppressor {
+    strength: f32,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    // Square root of a Hann window, applied before and after so overlapping frames add up to one
+    window: Vec<f32>,
+    frame: Vec<f32>,
+    pending: Vec<f32>,
+    overlap: Vec<f32>,
+    noise_floor: Option<Vec<f32>>,
+    gains: Vec<f32>,
+}
+
+impl NoiseSuppressor {
+    pub fn new(strength: f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let bins = FRAME_LEN / 2 + 1;
+        Self {
+            strength: strength.clamp(0.0, 1.0),
+            forward: planner.plan_fft_forward(FRAME_LEN),
+            inverse: planner.plan_fft_inverse(FRAME_LEN),
+            window: (0..FRAME_LEN)
+                .map(|i| (0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_LEN as f32).cos()).sqrt())
+                .collect(),
+            frame: vec![0.0; FRAME_LEN],
+            pending: Vec::new(),
+            overlap: vec![0.0; FRAME_LEN],
+            noise_floor: None,
+            gains: vec![1.0; bins],
+        }
+    }
+
+    /// Returns as many samples as were given overall, delayed by half a frame
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.pending.extend_from_slice(samples);
+        let mut output = Vec::with_capacity(self.pending.len());
+        while self.pending.len() >= HOP_LEN {
+            self.frame.copy_within(HOP_LEN.., 0);
+            self.frame[FRAME_LEN - HOP_LEN..].copy_from_slice(&self.pending[..HOP_LEN]);
+            self.pending.drain(..HOP_LEN);
+
+            let processed = self.process_frame();
+            for (sum, sample) in self.overlap.iter_mut().zip(processed) {
+                *sum += sample;
+            }
+            output.extend_from_slice(&self.overlap[..HOP_LEN]);
+            self.overlap.copy_within(HOP_LEN.., 0);
+            self.overlap[FRAME_LEN - HOP_LEN..].fill(0.0);
+        }
+        output
+    }
+
+    fn process_frame(&mut self) -> Vec<f32> {
+        let mut input: Vec<f32> = self
+            .frame
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| sample * window)
+            .collect();
+        let mut spectrum = self.forward.make_output_vec();
+        if let Err(e) = self.forward.process(&mut input, &mut spectrum) {
+            error!("Noise suppression failed: {}", e);
+            return input;
+        }
+
+        let noise_floor = self
+            .noise_floor
+            .get_or_insert_with(|| spectrum.iter().map(|bin| bin.norm()).collect());
+        let min_gain = 1.0 - self.strength * (1.0 - MIN_GAIN);
+        for ((bin, noise), gain) in spectrum
+            .iter_mut()
+            .zip(noise_floor.iter_mut())
+            .zip(self.gains.iter_mut())
+        {
+            let magnitude = bin.norm();
+
+            // Follow the floor down quickly and up slowly, within half a minute for new
+            // background noise, while speech rarely sits on one frequency long enough to count
+            *noise = match magnitude < *noise {
+                true => *noise * 0.9 + magnitude * 0.1,
+                false => *noise * 0.9995 + magnitude * 0.0005,
+            };
+
+            let target = match magnitude > 0.0 {
+                true => (1.0 - self.strength * OVER_SUBTRACTION * *noise / magnitude)
+                    .clamp(min_gain, 1.0),
+                false => min_gain,
+            };
+            // Open instantly, close gradually to avoid bins flickering on and off
+            *gain = match target > *gain {
+                true => target,
+                false => *gain * 0.7 + target * 0.3,
+            };
+            *bin *= *gain;
+        }
+        // The inverse transform expects purely real DC and Nyquist bins
+        spectrum[0].im = 0.0;
+        spectrum[FRAME_LEN / 2] = Complex::new(spectrum[FRAME_LEN / 2].re, 0.0);
+
+        let mut output = self.inverse.make_output_vec();
+        if let Err(e) = self.inverse.process(&mut spectrum, &mut output) {
+            error!("Noise suppression failed: {}", e);
+            return vec![0.0; FRAME_LEN];
+        }
+        output
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| sample * window / FRAME_LEN as f32)
+            .collect()
+    }
+}
+
+/// Suppresses noise in a stream of 16kHz mono chunks, passes audio through if strength is 0
+pub struct NoiseSuppressedStream<S> {
+    source: S,
+    suppressor: Option<NoiseSuppressor>,
+    device_id: i32,
+    sum_squares_before: f64,
+    sum_squares_after: f64,
+    sample_count: u64,
+    last_emitted: Instant,
+    sender: mpsc::Sender<NoiseSuppressionLevelEvent>,
+}
+
+impl<S> NoiseSuppressedStream<S> {
+    pub fn new(
+        source: S,
+        strength: f32,
+        device_id: i32,
+        sender: mpsc::Sender<NoiseSuppressionLevelEvent>,
+    ) -> Self {
+        Self {
+            source,
+            suppressor: (strength > 0.0).then(|| NoiseSuppressor::new(strength)),
+            device_id,
+            sum_squares_before: 0.0,
+            sum_squares_after: 0.0,
+            sample_count: 0,
+            last_emitted: Instant::now(),
+            sender,
+        }
+    }
+
+    fn observe(&mut self, before: &[f32], after: &[f32]) {
+        let Some(ref suppressor) = self.suppressor else {
+            return;
+        };
+        self.sum_squares_before += before
+            .iter()
+            .map(|s| (*s as f64) * (*s as f64))
+            .sum::<f64>();
+        self.sum_squares_after += after.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>();
+        self.sample_count += before.len() as u64;
+
+        if self.last_emitted.elapsed() < LEVEL_INTERVAL || self.sample_count == 0 {
+            return;
+        }
+        // Never block the audio path, dropping a reading is fine
+        let _ = self.sender.try_send(NoiseSuppressionLevelEvent {
+            r#type: NOISE_SUPPRESSION_LEVEL_EVENT_TYPE.to_string(),
+            device_id: self.device_id,
+            strength: suppressor.strength,
+            rms_before: (self.sum_squares_before / self.sample_count as f64).sqrt() as f32,
+            rms_after: (self.sum_squares_after / self.sample_count as f64).sqrt() as f32,
+        });
+        self.sum_squares_before = 0.0;
+        self.sum_squares_after = 0.0;
+        self.sample_count = 0;
+        self.last_emitted = Instant::now();
+    }
+}
+
+impl<S: futures_core::Stream<Item = Vec<f32>> + Unpin> futures_core::Stream
+    for NoiseSuppressedStream<S>
+{
+    type Item = Vec<f32>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let Some(chunk) = ready!(Pin::new(&mut this.source).poll_next(cx)) else {
+            return Poll::Ready(None);
+        };
+        let Some(ref mut suppressor) = this.suppressor else {
+            return Poll::Ready(Some(chunk));
+        };
+        let processed = suppressor.process(&chunk);
+        this.observe(&chunk, &processed);
+        Poll::Ready(Some(processed))
+    }
+}
+
+/// Spawns a task that emits noise suppression levels until the returned sender is dropped
+pub fn spawn_noise_level_emitter(
+    app_handle: AppHandle,
+) -> mpsc::Sender<NoiseSuppressionLevelEvent> {
+    let (tx, mut rx) = mpsc::channel::<NoiseSuppressionLevelEvent>(4);
+    tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            if let Err(e) = app_handle.emit(NOISE_SUPPRESSION_LEVEL_EVENT_TYPE, event) {
+                error!("Failed to emit noise suppression level event: {}", e);
+            }
+        }
+    });
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 16000.0;
+
+    fn tone(frequency: f32, amplitude: f32, range: std::ops::Range<usize>) -> Vec<f32> {
+        range
+            .map(|i| amplitude * (2.0 * PI * frequency * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn quiets_steady_tones_and_keeps_speech_band_bursts() {
+        let mut suppressor = NoiseSuppressor::new(1.0);
+        // Four seconds of hum for the noise floor to settle on
+        let hum_len = 4 * SAMPLE_RATE as usize;
+        let hum = tone(100.0, 0.05, 0..hum_len);
+        let hum_out = suppressor.process(&hum);
+        let last_second = hum_len - SAMPLE_RATE as usize..hum_len;
+        assert!(rms(&hum_out[last_second.clone()]) < rms(&hum[last_second]) * 0.3);
+
+        // Half a second of louder speech band tones on top of the hum
+        let burst_len = SAMPLE_RATE as usize / 2;
+        let burst: Vec<f32> = tone(100.0, 0.05, hum_len..hum_len + burst_len)
+            .iter()
+            .zip(tone(500.0, 0.2, 0..burst_len))
+            .zip(tone(1500.0, 0.2, 0..burst_len))
+            .map(|((hum, low), high)| hum + low + high)
+            .collect();
+        let speech = tone(500.0, 0.2, 0..burst_len)
+            .iter()
+            .zip(tone(1500.0, 0.2, 0..burst_len))
+            .map(|(low, high)| low + high)
+            .collect::<Vec<_>>();
+        let burst_out = suppressor.process(&burst);
+        // Skip the first frame, which still overlaps the hum
+        assert!(rms(&burst_out[FRAME_LEN..]) > rms(&speech[FRAME_LEN..]) * 0.9);
+    }
+
+    #[test]
+    fn blocks_of_any_size_give_the_same_output() {
+        let input: Vec<f32> = tone(440.0, 0.3, 0..8 * HOP_LEN)
+            .iter()
+            .zip(tone(97.0, 0.1, 0..8 * HOP_LEN))
+            .map(|(a, b)| a + b)
+            .collect();
+
+        let whole = NoiseSuppressor::new(0.8).process(&input);
+        let mut suppressor = NoiseSuppressor::new(0.8);
+        let chunked: Vec<f32> = input
+            .chunks(37)
+            .flat_map(|chunk| suppressor.process(chunk))
+            .collect();
+        assert_eq!(whole.len(), input.len());
+        assert_eq!(chunked, whole);
+    }
+
+    #[test]
+    fn passes_audio_through_without_clicks_at_zero_strength() {
+        let input = tone(440.0, 0.5, 0..8 * HOP_LEN);
+        let output = NoiseSuppressor::new(0.0).process(&input);
+        assert_eq!(output.len(), input.len());
+        // Delayed by half a frame, overlapping frames add back up to the input
+        for (out, expected) in output[HOP_LEN..].iter().zip(&input) {
+            assert!((out - expected).abs() < 1e-4, "{} != {}", out, expected);
+        }
+    }
+}
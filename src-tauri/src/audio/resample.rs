@@ -0,0 +1,222 @@
+use crate::transcription::speech_to_text::SPEECH_SAMPLE_RATE;
+use futures_core::ready;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Downmixes interleaved audio to mono and converts it to 16kHz, keeping state between
+/// chunks so the output is the same no matter how the input was split
+pub struct Resampler {
+    channels: usize,
+    // Input samples per output sample
+    step: f64,
+    // Where the next output sample falls, in input samples from `previous`
+    position: f64,
+    // Last mono sample of the previous chunk, interpolation needs one sample of history
+    previous: Option<f32>,
+    // Samples of a frame that was split across chunks
+    partial_frame: Vec<f32>,
+    // Moving average over one output period, keeps high frequencies from folding into speech
+    filter: VecDeque<f32>,
+    filter_len: usize,
+}
+
+impl Resampler {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let step = sample_rate as f64 / SPEECH_SAMPLE_RATE as f64;
+        Self {
+            channels: channels.max(1) as usize,
+            step,
+            position: 0.0,
+            previous: None,
+            partial_frame: Vec::new(),
+            filter: VecDeque::new(),
+            filter_len: (step.round() as usize).max(1),
+        }
+    }
+
+    pub fn process(&mut self, interleaved: &[f32]) -> Vec<f32> {
+        let mono = self.downmix(interleaved);
+        if self.step == 1.0 {
+            return mono;
+        }
+
+        let mut samples = Vec::with_capacity(mono.len() + 1);
+        samples.extend(self.previous);
+        samples.extend(mono.into_iter().map(|sample| self.low_pass(sample)));
+        if samples.len() < 2 {
+            self.previous = samples.last().copied();
+            return Vec::new();
+        }
+
+        // Linear interpolation between the two input samples around each output sample
+        let last_index = (samples.len() - 1) as f64;
+        let mut output = Vec::with_capacity((last_index / self.step) as usize + 1);
+        while self.position < last_index {
+            let index = self.position.floor() as usize;
+            let fraction = (self.position - index as f64) as f32;
+            output.push(samples[index] * (1.0 - fraction) + samples[index + 1] * fraction);
+            self.position += self.step;
+        }
+
+        // The last sample becomes the history of the next chunk
+        self.position -= last_index;
+        self.previous = samples.last().copied();
+        output
+    }
+
+    fn downmix(&mut self, interleaved: &[f32]) -> Vec<f32> {
+        if self.channels == 1 {
+            return interleaved.to_vec();
+        }
+
+        let mut frames = std::mem::take(&mut self.partial_frame);
+        frames.extend_from_slice(interleaved);
+        let complete = frames.len() - frames.len() % self.channels;
+        self.partial_frame = frames.split_off(complete);
+
+        frames
+            .chunks_exact(self.channels)
+            .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+            .collect()
+    }
+
+    fn low_pass(&mut self, sample: f32) -> f32 {
+        if self.filter_len == 1 {
+            return sample;
+        }
+        self.filter.push_back(sample);
+        if self.filter.len() > self.filter_len {
+            self.filter.pop_front();
+        }
+        // Only a handful of taps, summing them each time avoids drift over a long session
+        self.filter.iter().sum::<f32>() / self.filter.len() as f32
+    }
+}
+
+/// Turns a stream of interleaved chunks from any device into 16kHz mono chunks
+pub struct ResampledStream<S> {
+    source: S,
+    resampler: Resampler,
+}
+
+impl<S> ResampledStream<S> {
+    pub fn new(source: S, channels: u16, sample_rate: u32) -> Self {
+        Self {
+            source,
+            resampler: Resampler::new(channels, sample_rate),
+        }
+    }
+}
+
+impl<S: futures_core::Stream<Item = Vec<f32>> + Unpin> futures_core::Stream for ResampledStream<S> {
+    type Item = Vec<f32>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let next = ready!(Pin::new(&mut this.source).poll_next(cx));
+        Poll::Ready(next.map(|chunk| this.resampler.process(&chunk)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Interleaves one generated signal per channel
+    fn interleave(
+        channels: usize,
+        frames: usize,
+        signal: impl Fn(usize, usize) -> f32,
+    ) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|frame| (0..channels).map(move |channel| (frame, channel)))
+            .map(|(frame, channel)| signal(frame, channel))
+            .collect()
+    }
+
+    fn sine(frequency: f32, sample_rate: u32, frame: usize) -> f32 {
+        (2.0 * PI * frequency * frame as f32 / sample_rate as f32).sin()
+    }
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn passes_16khz_mono_through_unchanged() {
+        let input = interleave(1, 1600, |frame, _| sine(440.0, 16000, frame));
+        assert_eq!(Resampler::new(1, 16000).process(&input), input);
+    }
+
+    #[test]
+    fn downmixes_stereo_by_averaging_channels() {
+        // Opposite channels cancel out, matching channels keep their level
+        let cancelling = interleave(2, 4800, |_, channel| [0.5, -0.5][channel]);
+        let matching = interleave(2, 4800, |_, _| 0.25);
+
+        let cancelled = Resampler::new(2, 48000).process(&cancelling);
+        let kept = Resampler::new(2, 48000).process(&matching);
+
+        assert!(cancelled.iter().all(|sample| sample.abs() < 1e-6));
+        assert!(kept.iter().all(|sample| (sample - 0.25).abs() < 1e-6));
+    }
+
+    #[test]
+    fn converts_sample_rate_and_keeps_pitch() {
+        for (channels, sample_rate) in [(2, 48000), (6, 44100), (4, 96000), (1, 8000)] {
+            let frames = sample_rate as usize; // one second
+            let input = interleave(channels, frames, |frame, _| sine(440.0, sample_rate, frame));
+
+            let output = Resampler::new(channels as u16, sample_rate).process(&input);
+
+            // The last input sample is held back until the next chunk arrives
+            let expected_len = SPEECH_SAMPLE_RATE as usize;
+            assert!(
+                output.len().abs_diff(expected_len) <= 2,
+                "{} channels at {}Hz gave {} samples",
+                channels,
+                sample_rate,
+                output.len()
+            );
+            // A 440Hz tone crosses zero 880 times a second
+            assert!(
+                zero_crossings(&output).abs_diff(880) <= 2,
+                "{} channels at {}Hz changed pitch",
+                channels,
+                sample_rate
+            );
+        }
+    }
+
+    #[test]
+    fn chunking_does_not_change_output() {
+        let input = interleave(3, 44100, |frame, channel| {
+            sine(300.0 + 100.0 * channel as f32, 44100, frame)
+        });
+        let whole = Resampler::new(3, 44100).process(&input);
+
+        // Chunk sizes that split frames in the middle, as some drivers do
+        let mut resampler = Resampler::new(3, 44100);
+        let mut chunked = Vec::new();
+        for chunk in input.chunks(1000) {
+            chunked.extend(resampler.process(chunk));
+        }
+
+        assert_eq!(chunked.len(), whole.len());
+        for (a, b) in chunked.iter().zip(&whole) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn holds_back_incomplete_frames() {
+        let mut resampler = Resampler::new(2, 16000);
+        assert_eq!(resampler.process(&[0.5]), Vec::<f32>::new());
+        assert_eq!(resampler.process(&[0.1, 0.2, 0.3]), vec![0.3, 0.25]);
+    }
+}
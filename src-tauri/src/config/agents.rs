@@ -1,5 +1,9 @@
+use crate::agent::inputs::{check_input_cycles, describe_input_cycle, remove_input_cycles};
 use crate::config::watcher::{start_config_watcher, WatcherState};
+use crate::transcription::vocabulary::Vocabulary;
+use crate::util::error_handler::show_error;
 use crate::util::paths::get_app_sub_path;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -16,9 +20,85 @@ pub struct StructuredOutput {
 #[serde(rename_all = "camelCase")]
 pub struct Agent {
     pub interval_in_sec: Option<f64>,
+    // Cuts off the start of the history, the history setting takes precedence
     pub transcription_history_max_chars: Option<u64>,
+    // How the history is kept within the prompt for long meetings
+    pub history: Option<HistorySettings>,
+    // Answers of other agents the agent is given, and invoked by
+    pub inputs: Option<AgentInputs>,
+    // Invokes the agent on what is said instead of on its interval
+    pub trigger: Option<Trigger>,
     pub prompt: String,
     pub structured_output: Option<StructuredOutput>,
+    // Terms and replacements added to the transcription vocabulary while this agent runs
+    pub vocabulary: Option<Vocabulary>,
+    // Runs once the meeting ended instead of during it
+    pub finalizer: Option<Finalizer>,
+}
+
+/// The prompt of a finalizer agent summarizes the whole meeting as a meeting summary JSON.
+/// Meetings too long for one request are first condensed part by part with the map prompt.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Finalizer {
+    pub map_prompt: Option<String>,
+    // Most transcript sent in one request
+    pub chunk_max_chars: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryStrategy {
+    // Drops the oldest transcript
+    Truncate,
+    // Keeps one summary of everything older, updated as the meeting goes on
+    RollingSummary,
+    // Keeps summaries of older parts, merging the oldest into coarser summaries as they pile up
+    HierarchicalSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySettings {
+    pub strategy: HistoryStrategy,
+    // Most tokens the history takes up in the prompt, half of it is kept for recent transcript
+    pub token_budget: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentInputs {
+    // False to only answer other agents, without the transcript
+    #[serde(default = "default_true")]
+    pub transcription: bool,
+    // Names of the agents, their latest answers are inputs.<name>.text and inputs.<name>.json
+    #[serde(default)]
+    pub agents: Vec<String>,
+}
+
+/// Any of these fire the agent, checked against transcription as it comes in
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Trigger {
+    // Fires when any of them is said, ignoring case
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    // Regex, ignoring case
+    pub pattern: Option<String>,
+    // Fires when anyone but the Host asks a question
+    #[serde(default)]
+    pub question_to_host: bool,
+    // Fires once nothing was said for this long
+    pub silence_sec: Option<f64>,
+    // Fires on the trigger agents shortcut
+    #[serde(default)]
+    pub hotkey: bool,
+    // Least time between two invocations, 10 seconds by default
+    pub cooldown_sec: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,17 +132,37 @@ pub async fn get_all_agent_configs(
     app_handle: tauri::AppHandle,
     state: State<'_, WatcherState>,
 ) -> Result<Vec<AgentConfig>, String> {
-    let agent_configs =
-        read_agent_configs().map_err(|e| format!("Failed to read agent configs: {}", e))?;
-
-    start_config_watcher(app_handle, state)
+    // Started first, so fixing a broken agent file is still picked up
+    start_config_watcher(app_handle.clone(), state)
         .await
         .map_err(|e| format!("Failed to start config watcher: {}", e))?;
 
+    let agent_configs =
+        read_agent_files().map_err(|e| format!("Failed to read agent configs: {}", e))?;
+    let (agent_configs, cycles) = remove_input_cycles(agent_configs);
+    for cycle in cycles {
+        show_error(
+            format!(
+                "{}, these agents are disabled until it is broken up",
+                describe_input_cycle(&cycle)
+            ),
+            app_handle.clone(),
+        );
+    }
+
+    Ok(agent_configs)
+}
+
+/// All agents but those taking each other's answers as inputs in a cycle
+pub fn read_agent_configs() -> Result<Vec<AgentConfig>, String> {
+    let (agent_configs, cycles) = remove_input_cycles(read_agent_files()?);
+    for cycle in cycles {
+        warn!("{}, skipping these agents", describe_input_cycle(&cycle));
+    }
     Ok(agent_configs)
 }
 
-fn read_agent_configs() -> Result<Vec<AgentConfig>, String> {
+pub fn read_agent_files() -> Result<Vec<AgentConfig>, String> {
     let agents_dir = get_app_sub_path("agent")?;
 
     // Read all yaml files in the directory
@@ -75,12 +175,8 @@ fn read_agent_configs() -> Result<Vec<AgentConfig>, String> {
             let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
             let path = entry.path();
 
-            // Only process yaml files
-            if path.is_file()
-                && path
-                    .extension()
-                    .map_or(false, |ext| ext == "yaml" || ext == "yml")
-            {
+            // Only process agents, their tests are yaml files too
+            if path.is_file() && is_agent_file(&path) {
                 // Check file size limit (1MB max for config files)
                 let metadata = fs::metadata(&path)
                     .map_err(|e| format!("Failed to read file metadata {}: {}", path.display(), e))?;
@@ -111,6 +207,36 @@ fn read_agent_configs() -> Result<Vec<AgentConfig>, String> {
     Ok(agent_configs)
 }
 
+/// Read a single agent by name
+pub fn read_agent_config(name: &str) -> Result<Agent, String> {
+    let agents_dir = get_app_sub_path("agent")?;
+
+    // Validate agent name to prevent path traversal
+    validate_agent_name(name)?;
+
+    let file_path = agents_dir.join(format!("{}.yaml", name));
+    validate_path_within_directory(&file_path, &agents_dir)?;
+
+    let content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read config file {}: {}", file_path.display(), e))?;
+    parse_agent(&content)
+        .map_err(|e| format!("Failed to parse config file {}: {}", file_path.display(), e))
+}
+
+/// Read the test of an agent, <agent>.test.yaml next to it
+pub fn read_agent_test(name: &str) -> Result<String, String> {
+    let agents_dir = get_app_sub_path("agent")?;
+
+    // Validate agent name to prevent path traversal
+    validate_agent_name(name)?;
+
+    let file_path = agents_dir.join(format!("{}.test.yaml", name));
+    validate_path_within_directory(&file_path, &agents_dir)?;
+
+    fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read agent test {}: {}", file_path.display(), e))
+}
+
 #[tauri::command]
 pub fn save_agent_config(initial_name: String, agent_config: AgentConfig) -> Result<(), String> {
     let agents_dir =
@@ -128,6 +254,14 @@ pub fn save_agent_config(initial_name: String, agent_config: AgentConfig) -> Res
     // Ensure the resolved path is still within the agents directory
     validate_path_within_directory(&agent_file_path, &agents_dir)?;
 
+    // Checked against the others as they will be once saved
+    let mut agent_configs: Vec<AgentConfig> = read_agent_files()?
+        .into_iter()
+        .filter(|other| other.name != initial_name && other.name != agent_config.name)
+        .collect();
+    agent_configs.push(agent_config.clone());
+    check_input_cycles(&agent_configs)?;
+
     let agent_file_content = serde_yaml::to_string(&agent_config.agent)
         .map_err(|e| format!("Failed to serialize agent: {}", e))?;
 
@@ -165,6 +299,18 @@ pub fn delete_agent_config(name: String) -> Result<(), String> {
     Ok(())
 }
 
+/// Agents are the yaml files of the agent folder, except tests next to them named <agent>.test.yaml
+pub fn is_agent_file(path: &Path) -> bool {
+    let is_yaml = path
+        .extension()
+        .map_or(false, |ext| ext == "yaml" || ext == "yml");
+    let is_test = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or(false, |stem| stem.ends_with(".test"));
+    is_yaml && !is_test
+}
+
 pub fn parse_agent(content: &str) -> Result<Agent, String> {
     let agent: Agent =
         serde_yaml::from_str(content).map_err(|e| format!("Failed to parse agent: {}", e))?;
@@ -1,6 +1,111 @@
+use crate::transcription::speech_to_text::SpeechToTextBackend;
+use crate::transcription::vocabulary::Vocabulary;
 use crate::util::paths::get_app_path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tokio::fs;
 
+/// The parts of ollisten.yaml the backend reads, the client owns the rest of the file
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AppConfig {
+    pub modes: Option<HashMap<String, ModeConfig>>,
+    pub transcription: Option<TranscriptionSettings>,
+    pub shortcuts: Option<ShortcutSettings>,
+    pub search: Option<SearchSettings>,
+    pub llm: Option<LlmSettings>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModeConfig {
+    pub label: String,
+    pub agents: Vec<String>,
+    pub vocabulary: Option<Vocabulary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TranscriptionSettings {
+    // Engine turning speech into text, the kalosm Whisper model unless set
+    pub backend: SpeechToTextBackend,
+    // Pass the end of the previous chunk's text to the next decode of the same device, only
+    // backends that take an initial prompt use it
+    pub carry_over_context: bool,
+    // Audio repeated at the start of the next chunk when speech is cut at the maximum chunk length
+    pub chunk_overlap_ms: u64,
+    // Noise suppression strength from 0 (off) to 1 by device UID, devices not listed are left as is
+    pub noise_suppression: HashMap<String, f32>,
+}
+
+impl Default for TranscriptionSettings {
+    fn default() -> Self {
+        Self {
+            backend: SpeechToTextBackend::Whisper,
+            carry_over_context: true,
+            chunk_overlap_ms: 0,
+            noise_suppression: HashMap::new(),
+        }
+    }
+}
+
+/// System-wide shortcuts registered at startup, a shortcut set to null is not registered
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShortcutSettings {
+    pub toggle_transcription: Option<String>,
+    pub pause_agents: Option<String>,
+    // Makes running agents answer right away instead of waiting for their interval
+    pub trigger_agents: Option<String>,
+    pub bookmark: Option<String>,
+    pub toggle_host_mute: Option<String>,
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            toggle_transcription: Some("CmdOrCtrl+Alt+T".to_string()),
+            pause_agents: Some("CmdOrCtrl+Alt+P".to_string()),
+            trigger_agents: Some("CmdOrCtrl+Alt+Enter".to_string()),
+            bookmark: Some("CmdOrCtrl+Alt+B".to_string()),
+            toggle_host_mute: Some("CmdOrCtrl+Alt+M".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchSettings {
+    // Ollama model used to embed transcripts for semantic search, changing it embeds everything again
+    pub embedding_model: String,
+    // Snippets from earlier meetings given to agents that ask for them
+    pub related_top_k: usize,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            embedding_model: "nomic-embed-text".to_string(),
+            related_top_k: 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LlmSettings {
+    // Tokens asked of Ollama for models whose Modelfile sets no num_ctx, more takes more memory
+    pub context_length: usize,
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        Self {
+            context_length: 4096,
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn set_app_config(app_config: String) -> Result<(), String> {
     let app_config_path = get_app_path()?.join("ollisten.yaml");
@@ -25,3 +130,13 @@ pub async fn read_app_config() -> Result<String, String> {
 
     Ok(file_contents)
 }
+
+/// Reads and parses ollisten.yaml, an empty config if there is none yet
+pub async fn load_app_config() -> Result<AppConfig, String> {
+    let file_contents = read_app_config().await?;
+    if file_contents.trim().is_empty() {
+        return Ok(AppConfig::default());
+    }
+
+    serde_yaml::from_str(&file_contents).map_err(|e| format!("Failed to parse app config: {}", e))
+}
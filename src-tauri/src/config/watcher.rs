@@ -1,9 +1,12 @@
-use crate::config::agents::{parse_agent, parse_name_from_file_path, FileChangeEvent};
+use crate::agent::inputs::{agents_in_cycles, describe_input_cycle, remove_input_cycles};
+use crate::config::agents::{
+    is_agent_file, parse_agent, parse_name_from_file_path, read_agent_files, FileChangeEvent,
+};
 use crate::util::error_handler::show_error;
 use crate::util::paths::get_app_sub_path;
 use log::{error, info};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError};
 use tauri::{Emitter, EventTarget, State};
 use tokio::sync::Mutex;
 
@@ -29,6 +32,13 @@ pub async fn start_config_watcher(
     // Clone app handle for the watcher callback
     let app = app_handle.clone();
 
+    // Agents disabled for taking each other's answers as inputs, to enable again once fixed
+    let disabled_agents = std::sync::Mutex::new(
+        read_agent_files()
+            .map(|agent_configs| agents_in_cycles(&remove_input_cycles(agent_configs).1))
+            .unwrap_or_default(),
+    );
+
     // Create a watcher instance with proper configuration
     let mut watcher = RecommendedWatcher::new(
         move |res: Result<notify::Event, notify::Error>| {
@@ -38,11 +48,8 @@ pub async fn start_config_watcher(
                     if !event.paths.is_empty() {
                         let path = &event.paths[0];
 
-                        // Only process yaml files based on extension
-                        let is_yaml = path
-                            .extension()
-                            .map_or(false, |ext| ext == "yaml" || ext == "yml");
-                        if !is_yaml {
+                        // Only process agents, not other yaml files such as their tests
+                        if !is_agent_file(path) {
                             return;
                         }
 
@@ -103,6 +110,46 @@ pub async fn start_config_watcher(
                                 return;
                             }
                         };
+
+                        let (agent_configs, cycles) = match read_agent_files() {
+                            Ok(agent_configs) => remove_input_cycles(agent_configs),
+                            Err(e) => {
+                                show_error(e, app.clone());
+                                return;
+                            }
+                        };
+                        let previously_disabled = std::mem::replace(
+                            &mut *disabled_agents
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner),
+                            agents_in_cycles(&cycles),
+                        );
+
+                        // Agents edited into a cycle of inputs keep running as they were
+                        if let Some(cycle) = cycles.iter().find(|cycle| cycle.contains(&agent_name))
+                        {
+                            show_error(describe_input_cycle(cycle), app.clone());
+                            return;
+                        }
+
+                        // The rest of a cycle this edit broke up runs again
+                        for agent_config in agent_configs.iter().filter(|agent_config| {
+                            agent_config.name != agent_name
+                                && previously_disabled.contains(&agent_config.name)
+                        }) {
+                            let file_event = FileChangeEvent {
+                                r#type: "file-agent-created".to_string(),
+                                name: agent_config.name.clone(),
+                                agent: Some(agent_config.agent.clone()),
+                            };
+                            info!("Emitting re-enabled agent event: {:?}", file_event);
+                            if let Err(e) =
+                                app.emit_to(EventTarget::any(), "file-agent-created", file_event)
+                            {
+                                error!("Failed to emit event: {}", e);
+                            }
+                        }
+
                         let file_event = FileChangeEvent {
                             r#type: event_type.to_string(),
                             name: agent_name,
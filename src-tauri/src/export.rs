@@ -0,0 +1,150 @@
+pub mod json;
+pub mod markdown;
+pub mod subtitles;
+pub mod text;
+
+use crate::export::json::JsonExporter;
+use crate::export::markdown::MarkdownExporter;
+use crate::export::subtitles::{SrtExporter, WebVttExporter};
+use crate::export::text::TextExporter;
+use crate::transcription::sessions::load_session;
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Srt,
+    WebVtt,
+    Markdown,
+    Text,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn exporter(&self) -> Box<dyn SessionExporter> {
+        match self {
+            ExportFormat::Srt => Box::new(SrtExporter),
+            ExportFormat::WebVtt => Box::new(WebVttExporter),
+            ExportFormat::Markdown => Box::new(MarkdownExporter),
+            ExportFormat::Text => Box::new(TextExporter),
+            ExportFormat::Json => Box::new(JsonExporter),
+        }
+    }
+}
+
+/// Writes a session out in one format, add a variant to ExportFormat to offer a new one
+pub trait SessionExporter: Send + Sync {
+    /// File extension without the dot
+    fn extension(&self) -> &'static str;
+    fn export(&self, session: &SessionTimeline) -> Result<String, String>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedSession {
+    // Suggested name to save the content under
+    pub file_name: String,
+    pub content: String,
+}
+
+#[tauri::command]
+pub async fn export_session(
+    session_id: String,
+    format: ExportFormat,
+) -> Result<ExportedSession, String> {
+    let session = load_session(&session_id).await?;
+    let exporter = format.exporter();
+    Ok(ExportedSession {
+        file_name: format!("meeting-{}.{}", session.id, exporter.extension()),
+        content: exporter.export(&session)?,
+    })
+}
+
+/// Entries in the order things happened, text is recorded only once it was spoken
+pub fn chronological(session: &SessionTimeline) -> Vec<&TimelineEntry> {
+    let mut entries: Vec<&TimelineEntry> = session.entries.iter().collect();
+    entries.sort_by_key(|entry| entry.time_ms());
+    entries
+}
+
+/// Time since the session started as 01:02:03
+pub fn format_offset(ms: u64) -> String {
+    let seconds = ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn testdata_path(file_name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/export/testdata")
+            .join(file_name)
+    }
+
+    fn session() -> SessionTimeline {
+        let content = std::fs::read_to_string(testdata_path("session.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    /// Compares with the expected file, run with UPDATE_GOLDEN=1 to accept changed output
+    fn assert_golden(format: ExportFormat) {
+        let exporter = format.exporter();
+        let actual = exporter.export(&session()).unwrap();
+        let path = testdata_path(&format!("expected.{}", exporter.extension()));
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&path, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(actual, expected, "{} differs", path.display());
+    }
+
+    #[test]
+    fn exports_srt() {
+        assert_golden(ExportFormat::Srt);
+    }
+
+    #[test]
+    fn exports_web_vtt() {
+        assert_golden(ExportFormat::WebVtt);
+    }
+
+    #[test]
+    fn exports_markdown() {
+        assert_golden(ExportFormat::Markdown);
+    }
+
+    #[test]
+    fn exports_text() {
+        assert_golden(ExportFormat::Text);
+    }
+
+    #[test]
+    fn exports_json() {
+        assert_golden(ExportFormat::Json);
+    }
+
+    #[test]
+    fn json_export_reads_back_the_same() {
+        let exporter = ExportFormat::Json.exporter();
+        let exported = exporter.export(&session()).unwrap();
+        let document: json::SessionDocument = serde_json::from_str(&exported).unwrap();
+        assert_eq!(exporter.export(&document.session).unwrap(), exported);
+        assert_eq!(document.session.entries, session().entries);
+    }
+
+    #[test]
+    fn formats_offsets() {
+        assert_eq!(format_offset(0), "00:00:00");
+        assert_eq!(format_offset(61_999), "00:01:01");
+        assert_eq!(format_offset(3_723_000), "01:02:03");
+    }
+}
@@ -0,0 +1,35 @@
+use crate::export::SessionExporter;
+use crate::transcription::timeline::SessionTimeline;
+use serde::{Deserialize, Serialize};
+
+pub const FORMAT_NAME: &str = "ollisten-session";
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The session as it is saved, marked so it can be told apart from JSON of other tools
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDocument {
+    pub format: String,
+    pub version: u32,
+    pub session: SessionTimeline,
+}
+
+/// Everything in the session, importing it gives back the same session
+pub struct JsonExporter;
+
+impl SessionExporter for JsonExporter {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn export(&self, session: &SessionTimeline) -> Result<String, String> {
+        let document = SessionDocument {
+            format: FORMAT_NAME.to_string(),
+            version: FORMAT_VERSION,
+            session: session.clone(),
+        };
+        serde_json::to_string_pretty(&document)
+            .map(|json| json + "\n")
+            .map_err(|e| format!("Failed to serialize session: {}", e))
+    }
+}
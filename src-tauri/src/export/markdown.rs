@@ -0,0 +1,109 @@
+use crate::export::{chronological, format_offset, SessionExporter};
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+
+/// Speakers as headings with what agents answered in between, as it happened
+pub struct MarkdownExporter;
+
+impl SessionExporter for MarkdownExporter {
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn export(&self, session: &SessionTimeline) -> Result<String, String> {
+        let mut markdown = format!("# Meeting on {}\n", format_unix_time(session.started_at));
+        // Consecutive chunks of the same speaker go under one heading
+        let mut current_speaker: Option<&str> = None;
+        for entry in chronological(session) {
+            match entry {
+                TimelineEntry::Transcript {
+                    speaker,
+                    text,
+                    start_ms,
+                    ..
+                } => {
+                    if current_speaker != Some(speaker.as_str()) {
+                        markdown += &format!("\n## {} ({})\n\n", speaker, format_offset(*start_ms));
+                        current_speaker = Some(speaker);
+                    }
+                    markdown += &format!("{}\n", escape_html(text));
+                }
+                TimelineEntry::AgentOutput {
+                    agent_name,
+                    at_ms,
+                    text,
+                    ..
+                } => {
+                    // Quoted so headings and lists of the answer stay apart from the transcript
+                    markdown += &format!("\n> **{}** ({})\n>\n", agent_name, format_offset(*at_ms));
+                    for line in text.trim().lines() {
+                        markdown += &match line.is_empty() {
+                            true => ">\n".to_string(),
+                            false => format!("> {}\n", line),
+                        };
+                    }
+                    current_speaker = None;
+                }
+                TimelineEntry::Bookmark { at_ms, label } => {
+                    markdown += &match label {
+                        Some(label) => {
+                            format!(
+                                "\n---\n\n**Bookmark** ({}): {}\n",
+                                format_offset(*at_ms),
+                                escape_html(label)
+                            )
+                        }
+                        None => format!("\n---\n\n**Bookmark** ({})\n", format_offset(*at_ms)),
+                    };
+                    current_speaker = None;
+                }
+                TimelineEntry::Note { at_ms, text } => {
+                    markdown += &format!(
+                        "\n**Note** ({}): {}\n",
+                        format_offset(*at_ms),
+                        escape_html(text)
+                    );
+                    current_speaker = None;
+                }
+                TimelineEntry::DevicePaused { .. } => {}
+            }
+        }
+        Ok(markdown)
+    }
+}
+
+/// Said or typed text such as "<today>" would otherwise be read as HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+/// 2025-10-12 14:03 UTC, the time zone of whoever reads the file isn't known here
+fn format_unix_time(ms: u64) -> String {
+    let seconds = ms / 1000;
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Year, month and day of days since 1970-01-01, after Howard Hinnant's date algorithms
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = match month_from_march < 10 {
+        true => month_from_march + 3,
+        false => month_from_march - 9,
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,104 @@
+use crate::export::{chronological, SessionExporter};
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+
+pub struct SrtExporter;
+
+pub struct WebVttExporter;
+
+/// Transcribed chunk shown while it was being spoken
+struct Cue<'a> {
+    start_ms: u64,
+    end_ms: u64,
+    speaker: &'a str,
+    text: String,
+}
+
+impl SessionExporter for SrtExporter {
+    fn extension(&self) -> &'static str {
+        "srt"
+    }
+
+    fn export(&self, session: &SessionTimeline) -> Result<String, String> {
+        Ok(cues(session)
+            .iter()
+            .enumerate()
+            .map(|(index, cue)| {
+                format!(
+                    "{}\n{} --> {}\n{}: {}\n",
+                    index + 1,
+                    format_time(cue.start_ms, ','),
+                    format_time(cue.end_ms, ','),
+                    cue.speaker,
+                    cue.text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+impl SessionExporter for WebVttExporter {
+    fn extension(&self) -> &'static str {
+        "vtt"
+    }
+
+    fn export(&self, session: &SessionTimeline) -> Result<String, String> {
+        let cues = cues(session)
+            .iter()
+            .map(|cue| {
+                // Voice spans let players show or style speakers
+                format!(
+                    "{} --> {}\n<v {}>{}\n",
+                    format_time(cue.start_ms, '.'),
+                    format_time(cue.end_ms, '.'),
+                    escape_vtt(cue.speaker),
+                    escape_vtt(&cue.text)
+                )
+            })
+            .collect::<Vec<_>>();
+        Ok(format!("WEBVTT\n\n{}", cues.join("\n")))
+    }
+}
+
+fn cues(session: &SessionTimeline) -> Vec<Cue<'_>> {
+    chronological(session)
+        .into_iter()
+        .filter_map(|entry| match entry {
+            TimelineEntry::Transcript {
+                speaker,
+                text,
+                start_ms,
+                end_ms,
+                ..
+            } => Some(Cue {
+                start_ms: *start_ms,
+                // Players skip cues that end before they start
+                end_ms: (*end_ms).max(*start_ms + 1),
+                speaker,
+                // A blank line would end the cue early
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            }),
+            _ => None,
+        })
+        .filter(|cue| !cue.text.is_empty())
+        .collect()
+}
+
+/// 00:01:02,500 in SRT, 00:01:02.500 in WebVTT
+fn format_time(ms: u64, separator: char) -> String {
+    let seconds = ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
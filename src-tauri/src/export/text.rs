@@ -0,0 +1,40 @@
+use crate::export::{chronological, format_offset, SessionExporter};
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+
+/// What was said, one line per chunk, without what agents answered
+pub struct TextExporter;
+
+impl SessionExporter for TextExporter {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn export(&self, session: &SessionTimeline) -> Result<String, String> {
+        let lines = chronological(session)
+            .into_iter()
+            .filter_map(|entry| match entry {
+                TimelineEntry::Transcript {
+                    speaker,
+                    text,
+                    start_ms,
+                    ..
+                } => Some(format!(
+                    "[{}] {}: {}",
+                    format_offset(*start_ms),
+                    speaker,
+                    text
+                )),
+                TimelineEntry::Note { at_ms, text } => {
+                    Some(format!("[{}] Note: {}", format_offset(*at_ms), text))
+                }
+                TimelineEntry::Bookmark { at_ms, label } => Some(match label {
+                    Some(label) => format!("[{}] Bookmark: {}", format_offset(*at_ms), label),
+                    None => format!("[{}] Bookmark", format_offset(*at_ms)),
+                }),
+                TimelineEntry::DevicePaused { .. } | TimelineEntry::AgentOutput { .. } => None,
+            })
+            .map(|line| line + "\n")
+            .collect();
+        Ok(lines)
+    }
+}
@@ -0,0 +1,227 @@
+pub mod json;
+pub mod subtitles;
+
+use crate::import::json::JsonImporter;
+use crate::import::subtitles::SubtitleImporter;
+use crate::search::semantic::spawn_embedding;
+use crate::transcription::sessions::{save_session, unused_session_id};
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tokio::fs;
+
+// Speaker of text that doesn't say who said it
+const UNKNOWN_SPEAKER: &str = "Speaker";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportFormat {
+    Srt,
+    WebVtt,
+    Json,
+}
+
+impl ImportFormat {
+    /// Guesses the format from the content, files of other tools don't always have the expected extension
+    pub fn detect(content: &str) -> ImportFormat {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        if content.starts_with("WEBVTT") {
+            ImportFormat::WebVtt
+        } else if content.starts_with('{') || content.starts_with('[') {
+            ImportFormat::Json
+        } else {
+            ImportFormat::Srt
+        }
+    }
+
+    pub fn importer(&self) -> Box<dyn SessionImporter> {
+        match self {
+            ImportFormat::Srt | ImportFormat::WebVtt => Box::new(SubtitleImporter),
+            ImportFormat::Json => Box::new(JsonImporter),
+        }
+    }
+}
+
+/// Reads a transcript made elsewhere into a session, add a variant to ImportFormat to offer a new one
+pub trait SessionImporter: Send + Sync {
+    /// `started_at` is used for files that don't say when the meeting happened
+    fn import(&self, content: &str, started_at: u64) -> Result<SessionTimeline, String>;
+}
+
+/// Text with times relative to the start of the meeting
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedCue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+/// Saves a transcript file as a new session, which can then be searched, exported and replayed
+#[tauri::command]
+pub async fn import_session(
+    app_handle: AppHandle,
+    path: String,
+    format: Option<ImportFormat>,
+) -> Result<SessionTimeline, String> {
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    // Roughly when the meeting happened, for files that don't say
+    let started_at = fs::metadata(&path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or_else(|_| SystemTime::now())
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+
+    let format = format.unwrap_or_else(|| ImportFormat::detect(&content));
+    let mut session = format.importer().import(&content, started_at)?;
+    if session.entries.is_empty() {
+        return Err(format!("No transcript found in {}", path));
+    }
+    session.id = unused_session_id(&session.id).await?;
+    save_session(&session).await?;
+    spawn_embedding(app_handle);
+    Ok(session)
+}
+
+/// Session of what was said, devices stand for speakers as imported files have no devices
+pub fn session_from_cues(started_at: u64, mut cues: Vec<ImportedCue>) -> SessionTimeline {
+    // Live sessions record text once it was said
+    cues.sort_by_key(|cue| (cue.end_ms, cue.start_ms));
+    let mut speakers: Vec<String> = Vec::new();
+    let entries = cues
+        .into_iter()
+        .map(|cue| {
+            let speaker = cue.speaker.unwrap_or_else(|| UNKNOWN_SPEAKER.to_string());
+            let device_id = match speakers.iter().position(|known| *known == speaker) {
+                Some(index) => index,
+                None => {
+                    speakers.push(speaker.clone());
+                    speakers.len() - 1
+                }
+            } as i32;
+            TimelineEntry::Transcript {
+                device_id,
+                device_uid: format!("imported-{}", device_id),
+                speaker,
+                text: cue.text,
+                // Not known, the text is taken as is
+                confidence: 1.0,
+                start_ms: cue.start_ms,
+                end_ms: cue.end_ms,
+            }
+        })
+        .collect();
+    SessionTimeline::from_entries(started_at, entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFormat;
+    use std::path::PathBuf;
+
+    fn read_testdata(file_name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/export/testdata")
+            .join(file_name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn exported_session() -> SessionTimeline {
+        serde_json::from_str(&read_testdata("session.json")).unwrap()
+    }
+
+    /// Speaker, text, start and end of transcripts, what subtitles keep of a session
+    fn transcripts(session: &SessionTimeline) -> Vec<(String, String, u64, u64)> {
+        let mut transcripts: Vec<(String, String, u64, u64)> = session
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                TimelineEntry::Transcript {
+                    speaker,
+                    text,
+                    start_ms,
+                    end_ms,
+                    ..
+                } => Some((speaker.clone(), text.clone(), *start_ms, *end_ms)),
+                _ => None,
+            })
+            .collect();
+        transcripts.sort_by_key(|(_, _, start_ms, _)| *start_ms);
+        transcripts
+    }
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(
+            ImportFormat::detect(&read_testdata("expected.srt")),
+            ImportFormat::Srt
+        );
+        assert_eq!(
+            ImportFormat::detect(&read_testdata("expected.vtt")),
+            ImportFormat::WebVtt
+        );
+        assert_eq!(
+            ImportFormat::detect(&read_testdata("expected.json")),
+            ImportFormat::Json
+        );
+    }
+
+    #[test]
+    fn imports_exported_subtitles() {
+        for format in [ExportFormat::Srt, ExportFormat::WebVtt] {
+            let exported = format.exporter().export(&exported_session()).unwrap();
+            let imported = ImportFormat::detect(&exported)
+                .importer()
+                .import(&exported, 0)
+                .unwrap();
+            assert_eq!(
+                transcripts(&imported),
+                transcripts(&exported_session()),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn imports_exported_json_as_is() {
+        let imported = JsonImporter
+            .import(&read_testdata("expected.json"), 0)
+            .unwrap();
+        assert_eq!(imported.id, exported_session().id);
+        assert_eq!(imported.started_at, exported_session().started_at);
+        assert_eq!(imported.entries, exported_session().entries);
+    }
+
+    #[test]
+    fn imports_whisper_segments() {
+        let content = r#"{
+            "text": "Hello there. Hi!",
+            "segments": [
+                {"start": 0.0, "end": 1.5, "text": " Hello there.", "speaker": "SPEAKER_00"},
+                {"start": 1.5, "end": 2.25, "text": " Hi!", "speaker": "SPEAKER_01"},
+                {"start": 3.0, "end": 4.0, "text": " Anyone?"}
+            ]
+        }"#;
+        let imported = JsonImporter.import(content, 1000).unwrap();
+        assert_eq!(imported.started_at, 1000);
+        assert_eq!(
+            transcripts(&imported),
+            vec![
+                (
+                    "SPEAKER_00".to_string(),
+                    "Hello there.".to_string(),
+                    0,
+                    1500
+                ),
+                ("SPEAKER_01".to_string(), "Hi!".to_string(), 1500, 2250),
+                ("Speaker".to_string(), "Anyone?".to_string(), 3000, 4000),
+            ]
+        );
+    }
+}
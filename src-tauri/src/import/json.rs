@@ -0,0 +1,67 @@
+use crate::export::json::{SessionDocument, FORMAT_NAME, FORMAT_VERSION};
+use crate::import::{session_from_cues, ImportedCue, SessionImporter};
+use crate::transcription::timeline::SessionTimeline;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Sessions exported from ollisten as they were, or segments of Whisper and
+/// tools built on it, such as WhisperX which adds speakers
+pub struct JsonImporter;
+
+#[derive(Debug, Deserialize)]
+struct Segment {
+    // Seconds since the start
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    speaker: Option<String>,
+}
+
+impl SessionImporter for JsonImporter {
+    fn import(&self, content: &str, started_at: u64) -> Result<SessionTimeline, String> {
+        let value: Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+            .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+        if value.get("format").and_then(Value::as_str) == Some(FORMAT_NAME) {
+            let document: SessionDocument = serde_json::from_value(value)
+                .map_err(|e| format!("Failed to read exported session: {}", e))?;
+            if document.version > FORMAT_VERSION {
+                return Err(format!(
+                    "Session was exported by a newer version of ollisten (format version {})",
+                    document.version
+                ));
+            }
+            return Ok(document.session);
+        }
+
+        let segments = match value {
+            Value::Array(_) => value,
+            Value::Object(mut object) => object.remove("segments").ok_or_else(|| {
+                "Unknown JSON transcript, expected an ollisten session or segments with start, end and text"
+                    .to_string()
+            })?,
+            _ => return Err("Unknown JSON transcript".to_string()),
+        };
+        let segments: Vec<Segment> = serde_json::from_value(segments)
+            .map_err(|e| format!("Failed to read segments: {}", e))?;
+        let cues = segments
+            .into_iter()
+            .map(|segment| ImportedCue {
+                start_ms: (segment.start.max(0.0) * 1000.0).round() as u64,
+                end_ms: (segment.end.max(0.0) * 1000.0).round() as u64,
+                speaker: segment
+                    .speaker
+                    .map(|speaker| speaker.trim().to_string())
+                    .filter(|speaker| !speaker.is_empty()),
+                text: segment
+                    .text
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+            .filter(|cue| !cue.text.is_empty())
+            .collect();
+        Ok(session_from_cues(started_at, cues))
+    }
+}
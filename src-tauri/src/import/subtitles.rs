@@ -0,0 +1,125 @@
+use crate::import::{session_from_cues, ImportedCue, SessionImporter};
+use crate::transcription::timeline::SessionTimeline;
+
+/// SRT and WebVTT, their cues are close enough to read both the same way.
+/// Speakers are taken from WebVTT voice spans or a leading "Name:" as Zoom writes them.
+pub struct SubtitleImporter;
+
+// Tags of SRT and WebVTT cue text, anything else in angle brackets is kept as text
+const MARKUP_TAGS: &[&str] = &["b", "c", "font", "i", "lang", "ruby", "rt", "u", "v"];
+
+impl SessionImporter for SubtitleImporter {
+    fn import(&self, content: &str, started_at: u64) -> Result<SessionTimeline, String> {
+        let content = content
+            .trim_start_matches('\u{feff}')
+            .replace("\r\n", "\n")
+            .replace('\r', "\n");
+        let mut cues = Vec::new();
+        for block in content.split("\n\n") {
+            let lines: Vec<&str> = block.lines().collect();
+            // The WebVTT header and NOTE, STYLE and REGION blocks have no timing
+            let Some(timing_index) = lines.iter().position(|line| line.contains("-->")) else {
+                continue;
+            };
+            let (start_ms, end_ms) = parse_timing(lines[timing_index])
+                .ok_or_else(|| format!("Invalid cue timing: {}", lines[timing_index]))?;
+            let (speaker, text) = parse_text(&lines[timing_index + 1..].join("\n"));
+            if text.is_empty() {
+                continue;
+            }
+            cues.push(ImportedCue {
+                start_ms,
+                end_ms,
+                speaker,
+                text,
+            });
+        }
+        Ok(session_from_cues(started_at, cues))
+    }
+}
+
+/// 00:00:01,200 --> 00:00:04,800 with any cue settings after it
+fn parse_timing(line: &str) -> Option<(u64, u64)> {
+    let (start, end) = line.split_once("-->")?;
+    let end = end.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// 01:02:03,500 in SRT, 01:02:03.500 or 02:03.500 in WebVTT
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let timestamp = timestamp.replace(',', ".");
+    let (clock, fraction) = timestamp
+        .split_once('.')
+        .unwrap_or((timestamp.as_str(), "0"));
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    // Milliseconds from however many digits there are
+    let fraction = format!("{:0<3}", fraction);
+    let millis = fraction.get(..3)?.parse::<u64>().ok()?;
+    Some(seconds * 1000 + millis)
+}
+
+/// Speaker and plain text of a cue
+fn parse_text(text: &str) -> (Option<String>, String) {
+    let mut speaker = None;
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        plain.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find('>') else {
+            plain.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + length];
+        if is_markup(tag) {
+            // <v Name> or <v.loud Name>
+            if let Some(voice) = tag.strip_prefix('v') {
+                if let Some((_, name)) = voice.split_once(' ') {
+                    speaker = speaker.or_else(|| Some(unescape(name.trim())));
+                }
+            }
+        } else {
+            plain.push_str(&rest[start..=start + length]);
+        }
+        rest = &rest[start + length + 1..];
+    }
+    plain.push_str(rest);
+    let plain = unescape(&plain.split_whitespace().collect::<Vec<_>>().join(" "));
+
+    if speaker.is_some() {
+        return (speaker, plain);
+    }
+    match plain.split_once(": ") {
+        Some((name, said)) if is_speaker_name(name) => {
+            (Some(name.to_string()), said.trim().to_string())
+        }
+        _ => (None, plain),
+    }
+}
+
+fn is_markup(tag: &str) -> bool {
+    let name = tag.trim_start_matches('/');
+    // Timestamps of karaoke style WebVTT cues
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return true;
+    }
+    let name = name.split(['.', ' ', '=']).next().unwrap_or_default();
+    MARKUP_TAGS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Short enough to be a name rather than the start of a sentence
+fn is_speaker_name(name: &str) -> bool {
+    name.split_whitespace().count() <= 3
+        && name.chars().next().is_some_and(char::is_alphabetic)
+        && !name.contains(['.', ',', '?', '!', '"'])
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
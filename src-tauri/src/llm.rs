@@ -1,5 +1,8 @@
 pub mod llama_cpp;
+pub mod mock;
+pub mod model;
 pub mod ollama;
 pub mod open_ai;
 pub mod router;
+pub mod tokens;
 pub mod types;
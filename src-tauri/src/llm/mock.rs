@@ -0,0 +1,32 @@
+use crate::llm::model::{LanguageModel, TalkFuture};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Answers with scripted responses in order, so agents can be tried without a model
+pub struct MockLanguageModel {
+    responses: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl MockLanguageModel {
+    pub fn new(responses: Vec<String>) -> Result<Self, String> {
+        if responses.is_empty() {
+            return Err("Mock LLM needs at least one response".to_string());
+        }
+        Ok(Self {
+            responses,
+            next: AtomicUsize::new(0),
+        })
+    }
+}
+
+impl LanguageModel for MockLanguageModel {
+    fn talk<'a>(
+        &'a self,
+        _text: &'a str,
+        _structured_output_schema_string: Option<&'a str>,
+    ) -> TalkFuture<'a> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        let response = self.responses[index.min(self.responses.len() - 1)].clone();
+        Box::pin(async move { Ok(response) })
+    }
+}
@@ -0,0 +1,72 @@
+use crate::llm::mock::MockLanguageModel;
+use crate::llm::ollama::{OllamaConfig, OllamaLanguageModel};
+use crate::llm::router::{get_context_length, LlmRouterState};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type TalkFuture<'a> = Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>>;
+
+/// A language model agents send their prompts to
+pub trait LanguageModel: Send + Sync {
+    // Decides how prompts are counted in tokens, None for models that aren't served
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn talk<'a>(
+        &'a self,
+        text: &'a str,
+        structured_output_schema_string: Option<&'a str>,
+    ) -> TalkFuture<'a>;
+}
+
+/// Which model answers, the one the app is set up with if none is given
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LlmBackend {
+    Configured,
+    #[serde(rename_all = "camelCase")]
+    Ollama {
+        model_name: String,
+    },
+    Mock {
+        // Answers in order, the last one is repeated once they run out
+        responses: Vec<String>,
+    },
+}
+
+pub async fn load_language_model(
+    backend: &LlmBackend,
+    router: &LlmRouterState,
+) -> Result<Arc<dyn LanguageModel>, String> {
+    Ok(match backend {
+        LlmBackend::Configured => {
+            let ollama = router
+                .ollama
+                .read()
+                .await
+                .clone()
+                .ok_or_else(|| "No LLM endpoint is configured".to_string())?;
+            let num_ctx = ollama_num_ctx(router, &ollama.model_name).await;
+            Arc::new(OllamaLanguageModel::new(ollama, num_ctx))
+        }
+        LlmBackend::Ollama { model_name } => Arc::new(OllamaLanguageModel::new(
+            OllamaConfig {
+                model_name: model_name.clone(),
+            },
+            ollama_num_ctx(router, model_name).await,
+        )),
+        LlmBackend::Mock { responses } => Arc::new(MockLanguageModel::new(responses.clone())?),
+    })
+}
+
+// The context length prompts are budgeted against, None leaves it to Ollama
+async fn ollama_num_ctx(router: &LlmRouterState, model_name: &str) -> Option<usize> {
+    get_context_length(router, model_name)
+        .await
+        .inspect_err(|e| warn!("Failed to get context length: {}", e))
+        .ok()
+}
@@ -1,17 +1,24 @@
+use crate::llm::model::{LanguageModel, TalkFuture};
 use crate::llm::router::LlmRouterState;
 use crate::llm::types::LlmModel;
 use log::info;
 use ollama_rs::generation::completion::request::GenerationRequest;
+use ollama_rs::generation::embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest};
 use ollama_rs::generation::parameters::{FormatType, JsonStructure};
+use ollama_rs::models::ModelOptions;
 use ollama_rs::Ollama;
 use serde::{Deserialize, Serialize};
-use serde_json::{self};
+use serde_json::{self, json, Value};
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Duration;
 use tauri::State;
 use tokio::process::Command;
 use tokio::time::sleep;
 
+// Where Ollama::default() connects to
+const OLLAMA_URL: &str = "http://127.0.0.1:11434";
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OllamaConfig {
     pub model_name: String,
@@ -157,12 +164,17 @@ pub async fn setup_ollama(state: State<'_, LlmRouterState>, llm_model: &str) ->
     Ok(())
 }
 
+/// Prompts are budgeted against num_ctx, Ollama would otherwise cut them to its own default
 pub async fn llm_talk_ollama(
     ollama_config: &OllamaConfig,
+    num_ctx: Option<usize>,
     text: &str,
     structured_output_schema_string: Option<&str>,
 ) -> Result<String, String> {
     let mut request = GenerationRequest::new(ollama_config.model_name.to_string(), text);
+    if let Some(num_ctx) = num_ctx {
+        request = request.options(ModelOptions::default().num_ctx(num_ctx as u64));
+    }
 
     if let Some(schema_string) = structured_output_schema_string {
         let schema_json = serde_json::from_str(schema_string)
@@ -180,6 +192,116 @@ pub async fn llm_talk_ollama(
     Ok(response.response)
 }
 
+// Part of the /api/show response
+#[derive(Debug, Deserialize, Default)]
+struct OllamaModelShow {
+    // Modelfile parameters, one "name value" per line
+    #[serde(default)]
+    parameters: String,
+    // E.g. "llama.context_length": 131072
+    #[serde(default)]
+    model_info: HashMap<String, Value>,
+}
+
+/// What a model tells about its context, from its Modelfile and what it was trained on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OllamaModelContext {
+    pub num_ctx: Option<usize>,
+    pub trained: Option<usize>,
+}
+
+impl OllamaModelContext {
+    /// Tokens requested as num_ctx and budgeted against, the num_ctx of the Modelfile if it
+    /// sets one and the configured length otherwise, never more than the model was trained on
+    pub fn context_length(&self, configured: usize) -> usize {
+        let requested = self.num_ctx.unwrap_or(configured);
+        self.trained
+            .map_or(requested, |trained| requested.min(trained))
+    }
+}
+
+pub async fn get_model_context_ollama(model_name: &str) -> Result<OllamaModelContext, String> {
+    let show: OllamaModelShow = reqwest::Client::new()
+        .post(format!("{}/api/show", OLLAMA_URL))
+        .json(&json!({ "model": model_name }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Error showing Ollama model {}: {}", model_name, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Error parsing Ollama model {}: {}", model_name, e))?;
+    Ok(model_context(&show))
+}
+
+fn model_context(show: &OllamaModelShow) -> OllamaModelContext {
+    OllamaModelContext {
+        num_ctx: show.parameters.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            if parts.next() != Some("num_ctx") {
+                return None;
+            }
+            parts.next()?.parse().ok()
+        }),
+        trained: show
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|value| value as usize),
+    }
+}
+
+pub struct OllamaLanguageModel {
+    config: OllamaConfig,
+    // Context length prompts are budgeted against, Ollama's default if unknown
+    num_ctx: Option<usize>,
+}
+
+impl OllamaLanguageModel {
+    pub fn new(config: OllamaConfig, num_ctx: Option<usize>) -> Self {
+        Self { config, num_ctx }
+    }
+}
+
+impl LanguageModel for OllamaLanguageModel {
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.config.model_name)
+    }
+
+    fn talk<'a>(
+        &'a self,
+        text: &'a str,
+        structured_output_schema_string: Option<&'a str>,
+    ) -> TalkFuture<'a> {
+        Box::pin(llm_talk_ollama(
+            &self.config,
+            self.num_ctx,
+            text,
+            structured_output_schema_string,
+        ))
+    }
+}
+
+/// One embedding per text, in the same order
+pub async fn embed_ollama(model_name: &str, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+    if texts.is_empty() {
+        return Ok(vec![]);
+    }
+    let request =
+        GenerateEmbeddingsRequest::new(model_name.to_string(), EmbeddingsInput::Multiple(texts));
+    let response = Ollama::default()
+        .generate_embeddings(request)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error embedding with Ollama, make sure {} is pulled: {}",
+                model_name, e
+            )
+        })?;
+    Ok(response.embeddings)
+}
+
 fn to_friendly_size(bytes: u64) -> String {
     // Convert bytes to human readable size
     let sizes = ["B", "KB", "MB", "GB", "TB"];
@@ -191,3 +313,26 @@ fn to_friendly_size(bytes: u64) -> String {
     }
     format!("{:.2}{}", size, sizes[i])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_num_ctx_over_the_configured_context_length() {
+        let mut show = OllamaModelShow {
+            parameters: "stop \"<|eot_id|>\"\nnum_ctx 8192".to_string(),
+            model_info: HashMap::from([("llama.context_length".to_string(), json!(131072))]),
+        };
+        assert_eq!(model_context(&show).context_length(4096), 8192);
+        show.parameters.clear();
+        assert_eq!(model_context(&show).context_length(4096), 4096);
+        // Never more than the model can take
+        show.model_info = HashMap::from([("phi.context_length".to_string(), json!(2048))]);
+        assert_eq!(model_context(&show).context_length(4096), 2048);
+        assert_eq!(
+            model_context(&OllamaModelShow::default()).context_length(4096),
+            4096
+        );
+    }
+}
@@ -1,11 +1,37 @@
-use crate::llm::ollama::{llm_talk_ollama, OllamaConfig};
+use crate::config::app_config::load_app_config;
+use crate::llm::ollama::{
+    get_model_context_ollama, llm_talk_ollama, OllamaConfig, OllamaModelContext,
+};
 use log::info;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::State;
 use tokio::sync::RwLock;
 
 pub struct LlmRouterState {
     pub ollama: Arc<RwLock<Option<OllamaConfig>>>,
+    // Context of each model by name, looked up once
+    pub context_lengths: Arc<RwLock<HashMap<String, OllamaModelContext>>>,
+}
+
+/// Tokens the Ollama model is asked to take in at once, which prompts are budgeted against
+pub async fn get_context_length(state: &LlmRouterState, model_name: &str) -> Result<usize, String> {
+    let cached = state.context_lengths.read().await.get(model_name).copied();
+    let model_context = match cached {
+        Some(model_context) => model_context,
+        None => {
+            let model_context = get_model_context_ollama(model_name).await?;
+            info!("LLM {} context: {:?}", model_name, model_context);
+            state
+                .context_lengths
+                .write()
+                .await
+                .insert(model_name.to_string(), model_context);
+            model_context
+        }
+    };
+    let settings = load_app_config().await?.llm.unwrap_or_default();
+    Ok(model_context.context_length(settings.context_length))
 }
 
 #[tauri::command]
@@ -24,8 +50,11 @@ pub async fn llm_talk(
             .replace('\n', " ")
     );
 
-    if let Some(ollama) = state.ollama.read().await.as_ref() {
-        let response = llm_talk_ollama(ollama, text, structured_output_schema_string).await?;
+    let ollama = state.ollama.read().await.clone();
+    if let Some(ollama) = ollama {
+        let num_ctx = get_context_length(&state, &ollama.model_name).await.ok();
+        let response =
+            llm_talk_ollama(&ollama, num_ctx, text, structured_output_schema_string).await?;
         info!(
             "LLM ollama response: {}",
             &response
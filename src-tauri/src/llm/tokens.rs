@@ -0,0 +1,99 @@
+/// Model families whose tokenizers fit different amounts of text into a token
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModelFamily {
+    Llama,
+    Qwen,
+    Gemma,
+    Mistral,
+    Phi,
+    #[default]
+    Other,
+}
+
+impl ModelFamily {
+    /// Family of an Ollama model such as "llama3.2:3b" or "hf.co/unsloth/Qwen3-8B-GGUF"
+    pub fn from_model_name(model_name: &str) -> Self {
+        let name = model_name
+            .rsplit('/')
+            .next()
+            .unwrap_or(model_name)
+            .to_lowercase();
+        [
+            ("llama", ModelFamily::Llama),
+            ("qwen", ModelFamily::Qwen),
+            ("qwq", ModelFamily::Qwen),
+            ("gemma", ModelFamily::Gemma),
+            ("mistral", ModelFamily::Mistral),
+            ("mixtral", ModelFamily::Mistral),
+            ("phi", ModelFamily::Phi),
+        ]
+        .into_iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map_or(ModelFamily::Other, |(_, family)| family)
+    }
+
+    // Rough average of English text per ten tokens, larger vocabularies fit more
+    fn chars_per_ten_tokens(self) -> usize {
+        match self {
+            ModelFamily::Llama => 42,
+            ModelFamily::Qwen => 40,
+            ModelFamily::Gemma => 44,
+            ModelFamily::Mistral => 36,
+            ModelFamily::Phi => 38,
+            ModelFamily::Other => 40,
+        }
+    }
+
+    /// Tokens a text takes up in a prompt, estimated from its length
+    pub fn estimate_tokens(self, text: &str) -> usize {
+        // Letters outside of ASCII, such as CJK or accented ones, are split into a token or more each
+        let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+            if c.is_ascii() {
+                (ascii + 1, other)
+            } else {
+                (ascii, other + 1)
+            }
+        });
+        (ascii * 10).div_ceil(self.chars_per_ten_tokens()) + other
+    }
+}
+
+/// Tokens about as many characters take up, for limits that were given in characters
+pub fn chars_to_tokens(chars: usize) -> usize {
+    chars * 10 / ModelFamily::Other.chars_per_ten_tokens()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_families_from_model_names() {
+        assert_eq!(
+            ModelFamily::from_model_name("llama3.2:3b"),
+            ModelFamily::Llama
+        );
+        assert_eq!(
+            ModelFamily::from_model_name("hf.co/unsloth/Qwen3-8B-GGUF:Q4_K_M"),
+            ModelFamily::Qwen
+        );
+        assert_eq!(
+            ModelFamily::from_model_name("gemma3:12b"),
+            ModelFamily::Gemma
+        );
+        assert_eq!(
+            ModelFamily::from_model_name("deepseek-r1"),
+            ModelFamily::Other
+        );
+    }
+
+    #[test]
+    fn estimates_by_family() {
+        let text = "a".repeat(44);
+        assert_eq!(ModelFamily::Gemma.estimate_tokens(&text), 10);
+        assert_eq!(ModelFamily::Mistral.estimate_tokens(&text), 13);
+        assert_eq!(ModelFamily::Other.estimate_tokens(&text), 11);
+        assert_eq!(ModelFamily::Other.estimate_tokens("日本語"), 3);
+        assert_eq!(ModelFamily::Other.estimate_tokens(""), 0);
+    }
+}
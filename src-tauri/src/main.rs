@@ -1,25 +1,31 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod agent;
 mod audio;
 mod config;
+mod export;
+mod import;
 mod llm;
+mod search;
+mod shortcuts;
 mod system;
 mod transcription;
+mod tray;
 mod util;
 
+use crate::agent::trigger::TriggerState;
+use crate::audio::meter::LevelMeterState;
 use crate::config::watcher::WatcherState;
 use crate::llm::router::LlmRouterState;
 use crate::transcription::control::TranscriptionState;
+use crate::transcription::replay::ReplayState;
 use crate::util::error_handler::show_error;
 use log::{info, LevelFilter};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::image::Image;
-use tauri::menu::{Menu, MenuItem};
-use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-use tauri::utils::platform::resource_dir;
-use tauri::{async_runtime, App, AppHandle, Listener, Manager, RunEvent, WebviewWindow};
+use tauri::{async_runtime, AppHandle, Manager, RunEvent, WebviewWindow};
 use tokio::sync::{Mutex, RwLock};
 
 const MAIN_WINDOW_WIDTH: f64 = 600.0;
@@ -57,11 +63,19 @@ async fn main() {
         .manage(TranscriptionState {
             session: Arc::new(Mutex::new(None)),
         })
+        .manage(ReplayState {
+            replay: Arc::new(Mutex::new(None)),
+        })
+        .manage(TriggerState::default())
+        .manage(LevelMeterState {
+            meters: Arc::new(Mutex::new(HashMap::new())),
+        })
         .manage(WatcherState {
             watcher: Arc::new(Mutex::new(None)),
         })
         .manage(LlmRouterState {
             ollama: Arc::new(RwLock::new(None)),
+            context_lengths: Arc::new(RwLock::new(HashMap::new())),
         })
         .plugin(
             tauri_plugin_log::Builder::default()
@@ -71,15 +85,35 @@ async fn main() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             llm::router::llm_talk,
+            agent::budget::estimate_prompt_tokens,
+            agent::condense::condense_transcription_history,
+            agent::evaluate::evaluate_agent,
+            agent::trigger::watch_agent_trigger,
+            agent::trigger::unwatch_agent_trigger,
             llm::ollama::setup_ollama,
             llm::ollama::start_and_get_llm_model_options_ollama,
             audio::devices::get_listen_device_options,
             audio::devices::get_hidden_device,
             audio::driver::is_driver_installed,
             audio::driver::install_driver,
+            audio::meter::start_level_meter,
+            audio::meter::stop_level_meter,
             transcription::model::list_available_transcription_models,
             transcription::control::start_transcription,
             transcription::control::stop_transcription,
+            transcription::control::pause_device,
+            transcription::control::resume_device,
+            transcription::control::get_session_timeline,
+            transcription::control::add_bookmark,
+            transcription::control::add_note,
+            transcription::control::record_agent_output,
+            transcription::replay::replay_session,
+            transcription::replay::stop_replay,
+            transcription::replay::get_replay,
+            search::index::search_transcripts,
+            search::semantic::semantic_search,
+            export::export_session,
+            import::import_session,
             config::agents::open_agent_config_folder,
             config::agents::get_all_agent_configs,
             config::agents::save_agent_config,
@@ -92,11 +126,19 @@ async fn main() {
                 .map_err(|e| format!("Failed to create or restore main window during setup: {}", e))?;
             let is_dark_mode = window.theme().unwrap_or(tauri::Theme::Light) == tauri::Theme::Dark;
 
+            // Keep track of audio devices coming and going
+            audio::device_watcher::start_device_watcher(app.handle().clone());
+
             // Setup app Tray and related events
-            if let Err(e) = setup_tray(app, is_dark_mode) {
+            if let Err(e) = tray::setup_tray(app, is_dark_mode, open_or_restore_main_window) {
                 show_error(format!("Failed to setup tray: {}", e), app.handle().clone());
             }
 
+            // Global hotkeys that work while the app is in the background
+            if let Err(e) = shortcuts::setup_shortcuts(app) {
+                show_error(format!("Failed to setup shortcuts: {}", e), app.handle().clone());
+            }
+
             Ok(())
         })
         .on_menu_event({
@@ -114,9 +156,7 @@ async fn main() {
                     should_exit.store(true, Ordering::SeqCst);
                     app_handle.exit(0);
                 }
-                _ => {
-                    println!("menu item {:?} not handled", event.id);
-                }
+                id => tray::on_menu_event(app_handle, id),
             }
         })
         .build(tauri::generate_context!())
@@ -179,87 +219,10 @@ async fn main() {
         });
 }
 
-fn setup_tray(app: &App, is_dark_mode: bool) -> Result<(), String> {
-    let menu = Menu::with_items(
-        app,
-        &[
-            &MenuItem::with_id(app, "main", "Open", true, None::<&str>)
-                .map_err(|e| format!("Failed to create menu item: {}", e))?,
-            &MenuItem::with_id(app, "exit", "Exit", true, None::<&str>)
-                .map_err(|e| format!("Failed to create menu item: {}", e))?,
-        ],
-    )
-    .map_err(|e| format!("Failed to create menu: {}", e))?;
-
-    let resource_path = resource_dir(app.package_info(), &app.env())
-        .map_err(|e| format!("Failed to locate resource directory: {}", e))?;
-    let icon_path = resource_path
-        .join("resources")
-        .join("icons")
-        .join(is_dark_mode.then_some(ICON_DARK).unwrap_or(ICON_LIGHT));
-    TrayIconBuilder::with_id(TRAY_ID)
-        .menu(&menu)
-        .show_menu_on_left_click(false)
-        .on_tray_icon_event(|tray, event| match event {
-            TrayIconEvent::Click {
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
-                ..
-            } => {
-                // in this example, let's show and focus the main window when the tray is clicked
-                let app = tray.app_handle();
-                if let Err(e) = open_or_restore_main_window(app) {
-                    show_error(format!("Failed to open main window: {}", e), app.clone());
-                }
-            }
-            _ => {}
-        })
-        .icon(Image::from_path(icon_path).map_err(|e| format!("Failed to load icon: {}", e))?)
-        .build(app)
-        .map_err(|e| format!("Failed to create tray icon: {}", e))?;
-
-    // Listen for theme changes
-    let app_handle = app.handle().clone();
-    app.listen_any("tauri://theme-changed", move |event| {
-        let is_dark_mode = match event.payload() {
-            "true" | "dark" | "\"dark\"" => true,
-            "false" | "light" | "\"light\"" => false,
-            _ => return,
-        };
-        if let Err(e) = update_tray_icon(&app_handle, is_dark_mode) {
-            show_error(
-                format!("Failed to update tray icon: {}", e),
-                app_handle.clone(),
-            );
-        }
-    });
-    Ok(())
-}
-
-const TRAY_ID: &str = "ollisten-tray";
-const ICON_LIGHT: &str = "ollisten-logo-circle-black.png";
-const ICON_DARK: &str = "ollisten-logo-circle-white.png";
-
-fn update_tray_icon(app: &AppHandle, is_dark_mode: bool) -> Result<(), String> {
-    let tray = app
-        .tray_by_id(TRAY_ID)
-        .ok_or_else(|| "Tray icon not found".to_string())?;
-
-    let resource_path = resource_dir(app.package_info(), &app.env())
-        .map_err(|e| format!("Failed to locate resource directory: {}", e))?;
-    let icon_path = resource_path
-        .join("resources")
-        .join("icons")
-        .join(is_dark_mode.then_some(ICON_DARK).unwrap_or(ICON_LIGHT));
-    let icon = Image::from_path(icon_path).map_err(|e| format!("Failed to load icon: {}", e))?;
-
-    tray.set_icon(Some(icon))
-        .map_err(|e| format!("Failed to set tray icon: {}", e))?;
-
-    Ok(())
-}
-
 async fn release_all_resources(app: AppHandle) -> Result<(), String> {
+    // Stop level meters
+    audio::meter::stop_level_meter(app.state::<LevelMeterState>()).await?;
+
     // Stop transcription
     transcription::control::stop_transcription(app.clone(), app.state::<TranscriptionState>()).await
 }
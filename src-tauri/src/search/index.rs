@@ -0,0 +1,543 @@
+use crate::transcription::control::running_session_id;
+use crate::transcription::sessions::{list_session_files, load_session, SessionFile};
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+use crate::util::paths::get_app_path;
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::AppHandle;
+
+// Private use characters marking matches in snippets, they never show up in transcripts
+const HIGHLIGHT_START: char = '\u{E000}';
+const HIGHLIGHT_END: char = '\u{E001}';
+const DEFAULT_LIMIT: usize = 50;
+// Words around a match to show in the snippet
+const SNIPPET_TOKENS: usize = 24;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchFilters {
+    // Unix time in milliseconds of when the entry happened
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    // Host, Guest or agent names, any speaker if not given
+    pub speakers: Option<Vec<String>>,
+    // Only look at earlier meetings
+    pub exclude_running_session: bool,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub session_id: String,
+    // Unix time in milliseconds
+    pub session_started_at: u64,
+    // Position of the entry in the session timeline
+    pub entry_index: usize,
+    // Milliseconds since the session started
+    pub at_ms: u64,
+    pub speaker: String,
+    // transcript, note, bookmark or agentOutput
+    pub kind: String,
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+/// Searches saved sessions, best matches first. Words must all be present
+/// in an entry, words in double quotes must be present as a phrase.
+#[tauri::command]
+pub async fn search_transcripts(
+    app_handle: AppHandle,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<SearchHit>, String> {
+    let filters = filters.unwrap_or_default();
+    let match_query = to_match_query(&query).ok_or_else(|| "Search query is empty".to_string())?;
+    let excluded_session_id = match filters.exclude_running_session {
+        true => running_session_id(&app_handle).await,
+        false => None,
+    };
+
+    // Bring the index up to date with sessions saved since the last search
+    let files = list_session_files().await?;
+    let stale_ids = {
+        let files = files.clone();
+        run_blocking(move || stale_session_ids(&open_index()?, &files)).await?
+    };
+    let mut sessions = Vec::new();
+    for id in stale_ids {
+        match load_session(&id).await {
+            Ok(session) => sessions.push(session),
+            Err(e) => warn!("Not indexing session {}: {}", id, e),
+        }
+    }
+
+    run_blocking(move || {
+        let mut connection = open_index()?;
+        update_index(&mut connection, &files, &sessions)?;
+        query_index(
+            &connection,
+            &match_query,
+            &filters,
+            excluded_session_id.as_deref(),
+        )
+    })
+    .await
+}
+
+pub async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Search failed: {}", e))?
+}
+
+pub fn open_index() -> Result<Connection, String> {
+    let path = get_app_path()?.join("search.sqlite");
+    let connection =
+        Connection::open(path).map_err(|e| format!("Failed to open search index: {}", e))?;
+    // Another search may be updating the index at the same time
+    connection
+        .busy_timeout(Duration::from_secs(10))
+        .map_err(|e| format!("Failed to open search index: {}", e))?;
+    create_index_tables(&connection)?;
+    Ok(connection)
+}
+
+fn create_index_tables(connection: &Connection) -> Result<(), String> {
+    connection
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS indexed_sessions (
+                id TEXT PRIMARY KEY,
+                modified_ms INTEGER NOT NULL
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS entries USING fts5(
+                text,
+                speaker UNINDEXED,
+                kind UNINDEXED,
+                session_id UNINDEXED,
+                session_started_at UNINDEXED,
+                entry_index UNINDEXED,
+                at_ms UNINDEXED,
+                time UNINDEXED,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );",
+        )
+        .map_err(|e| format!("Failed to create search index: {}", e))
+}
+
+/// Sessions saved after they were indexed, or never indexed at all
+fn stale_session_ids(
+    connection: &Connection,
+    files: &[SessionFile],
+) -> Result<Vec<String>, String> {
+    let indexed = indexed_sessions(connection)?;
+    Ok(files
+        .iter()
+        .filter(|file| indexed.get(&file.id) != Some(&file.modified_ms))
+        .map(|file| file.id.clone())
+        .collect())
+}
+
+fn indexed_sessions(connection: &Connection) -> Result<HashMap<String, u64>, String> {
+    let mut statement = connection
+        .prepare("SELECT id, modified_ms FROM indexed_sessions")
+        .map_err(|e| format!("Failed to read search index: {}", e))?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })
+        .map_err(|e| format!("Failed to read search index: {}", e))?;
+    rows.collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("Failed to read search index: {}", e))
+}
+
+/// Replaces the entries of the given sessions and drops sessions whose file is gone
+fn update_index(
+    connection: &mut Connection,
+    files: &[SessionFile],
+    sessions: &[SessionTimeline],
+) -> Result<(), String> {
+    let transaction = connection
+        .transaction()
+        .map_err(|e| format!("Failed to update search index: {}", e))?;
+
+    let existing_ids: HashSet<&str> = files.iter().map(|file| file.id.as_str()).collect();
+    let removed_ids: Vec<String> = indexed_sessions(&transaction)?
+        .into_keys()
+        .filter(|id| !existing_ids.contains(id.as_str()))
+        .collect();
+    for id in removed_ids {
+        remove_session(&transaction, &id)?;
+    }
+
+    for session in sessions {
+        let Some(file) = files.iter().find(|file| file.id == session.id) else {
+            continue;
+        };
+        remove_session(&transaction, &session.id)?;
+        for (entry_index, entry) in session.entries.iter().enumerate() {
+            let Some((kind, speaker, text)) = searchable_fields(entry) else {
+                continue;
+            };
+            let at_ms = entry.time_ms();
+            transaction
+                .execute(
+                    "INSERT INTO entries
+                        (text, speaker, kind, session_id, session_started_at, entry_index, at_ms, time)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        text,
+                        speaker,
+                        kind,
+                        session.id,
+                        session.started_at as i64,
+                        entry_index as i64,
+                        at_ms as i64,
+                        (session.started_at + at_ms) as i64,
+                    ],
+                )
+                .map_err(|e| format!("Failed to update search index: {}", e))?;
+        }
+        transaction
+            .execute(
+                "INSERT INTO indexed_sessions (id, modified_ms) VALUES (?1, ?2)",
+                params![session.id, file.modified_ms as i64],
+            )
+            .map_err(|e| format!("Failed to update search index: {}", e))?;
+        info!("Indexed session {}", session.id);
+    }
+
+    transaction
+        .commit()
+        .map_err(|e| format!("Failed to update search index: {}", e))
+}
+
+fn remove_session(connection: &Connection, id: &str) -> Result<(), String> {
+    connection
+        .execute("DELETE FROM entries WHERE session_id = ?1", params![id])
+        .and_then(|_| connection.execute("DELETE FROM indexed_sessions WHERE id = ?1", params![id]))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to update search index: {}", e))
+}
+
+/// Kind, speaker and text of entries worth finding
+pub fn searchable_fields(entry: &TimelineEntry) -> Option<(&'static str, &str, &str)> {
+    match entry {
+        TimelineEntry::Transcript { speaker, text, .. } => {
+            Some(("transcript", speaker.as_str(), text.as_str()))
+        }
+        // Written by the person running the app
+        TimelineEntry::Note { text, .. } => Some(("note", "Host", text.as_str())),
+        TimelineEntry::Bookmark {
+            label: Some(label), ..
+        } => Some(("bookmark", "Host", label.as_str())),
+        TimelineEntry::AgentOutput {
+            agent_name, text, ..
+        } => Some(("agentOutput", agent_name.as_str(), text.as_str())),
+        TimelineEntry::Bookmark { label: None, .. } | TimelineEntry::DevicePaused { .. } => None,
+    }
+}
+
+fn query_index(
+    connection: &Connection,
+    match_query: &str,
+    filters: &SearchFilters,
+    excluded_session_id: Option<&str>,
+) -> Result<Vec<SearchHit>, String> {
+    let speakers = filters
+        .speakers
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| format!("Invalid speakers: {}", e))?;
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT session_id, session_started_at, entry_index, at_ms, speaker, kind,
+                snippet(entries, 0, ?1, ?2, '…', {})
+            FROM entries
+            WHERE entries MATCH ?3
+                AND (?4 IS NULL OR time >= ?4)
+                AND (?5 IS NULL OR time <= ?5)
+                AND (?6 IS NULL OR lower(speaker) IN (SELECT lower(value) FROM json_each(?6)))
+                AND (?7 IS NULL OR session_id != ?7)
+            ORDER BY rank
+            LIMIT ?8",
+            SNIPPET_TOKENS
+        ))
+        .map_err(|e| format!("Failed to search: {}", e))?;
+    let hits = statement
+        .query_map(
+            params![
+                HIGHLIGHT_START.to_string(),
+                HIGHLIGHT_END.to_string(),
+                match_query,
+                filters.from.map(|from| from as i64),
+                filters.to.map(|to| to as i64),
+                speakers,
+                excluded_session_id,
+                filters.limit.unwrap_or(DEFAULT_LIMIT) as i64,
+            ],
+            |row| {
+                Ok(SearchHit {
+                    session_id: row.get(0)?,
+                    session_started_at: row.get::<_, i64>(1)? as u64,
+                    entry_index: row.get::<_, i64>(2)? as usize,
+                    at_ms: row.get::<_, i64>(3)? as u64,
+                    speaker: row.get(4)?,
+                    kind: row.get(5)?,
+                    snippet: parse_snippet(&row.get::<_, String>(6)?),
+                })
+            },
+        )
+        .map_err(|e| format!("Failed to search: {}", e))?;
+    hits.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to search: {}", e))
+}
+
+/// Turns what was typed into an FTS5 query, quoting everything so that
+/// characters such as - or : are searched for instead of read as operators
+fn to_match_query(query: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    // Odd parts are between double quotes
+    for (index, part) in query.split('"').enumerate() {
+        if index % 2 == 1 {
+            if !part.trim().is_empty() {
+                terms.push(format!("\"{}\"", part.trim()));
+            }
+        } else {
+            terms.extend(part.split_whitespace().map(|word| format!("\"{}\"", word)));
+        }
+    }
+    match terms.is_empty() {
+        true => None,
+        false => Some(terms.join(" ")),
+    }
+}
+
+fn parse_snippet(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts: Vec<SnippetPart> = Vec::new();
+    let mut highlighted = false;
+    for part in snippet.split_inclusive([HIGHLIGHT_START, HIGHLIGHT_END]) {
+        let (text, next_highlighted) = match part.strip_suffix(HIGHLIGHT_START) {
+            Some(text) => (text, true),
+            None => match part.strip_suffix(HIGHLIGHT_END) {
+                Some(text) => (text, false),
+                None => (part, highlighted),
+            },
+        };
+        if !text.is_empty() {
+            parts.push(SnippetPart {
+                text: text.to_string(),
+                highlighted,
+            });
+        }
+        highlighted = next_highlighted;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn said(speaker: &str, text: &str, start_ms: u64) -> TimelineEntry {
+        TimelineEntry::Transcript {
+            device_id: 0,
+            device_uid: "mic".to_string(),
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+            confidence: 1.0,
+            start_ms,
+            end_ms: start_ms + 500,
+        }
+    }
+
+    fn session(started_at: u64, entries: Vec<TimelineEntry>) -> SessionTimeline {
+        SessionTimeline::from_entries(started_at, entries)
+    }
+
+    fn file(session: &SessionTimeline, modified_ms: u64) -> SessionFile {
+        SessionFile {
+            id: session.id.clone(),
+            modified_ms,
+        }
+    }
+
+    fn index() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        create_index_tables(&connection).unwrap();
+        connection
+    }
+
+    fn search(connection: &Connection, query: &str, filters: &SearchFilters) -> Vec<String> {
+        search_excluding(connection, query, filters, None)
+    }
+
+    fn search_excluding(
+        connection: &Connection,
+        query: &str,
+        filters: &SearchFilters,
+        excluded_session_id: Option<&str>,
+    ) -> Vec<String> {
+        let match_query = to_match_query(query).unwrap();
+        query_index(connection, &match_query, filters, excluded_session_id)
+            .unwrap()
+            .into_iter()
+            .map(|hit| format!("{} {} {}", hit.session_id, hit.entry_index, hit.speaker))
+            .collect()
+    }
+
+    #[test]
+    fn updates_stale_and_removed_sessions() {
+        let mut connection = index();
+        let first = session(1000, vec![said("Host", "budget review", 0)]);
+        let second = session(2000, vec![said("Guest", "budget questions", 0)]);
+        let files = [file(&first, 10), file(&second, 10)];
+        assert_eq!(
+            stale_session_ids(&connection, &files).unwrap(),
+            ["1000", "2000"]
+        );
+        update_index(&mut connection, &files, &[first.clone(), second.clone()]).unwrap();
+        assert!(stale_session_ids(&connection, &files).unwrap().is_empty());
+
+        // Saved again with more said, and the other one deleted
+        let mut first = first;
+        first.entries.push(said("Guest", "budget approved", 5000));
+        let files = [file(&first, 20)];
+        assert_eq!(stale_session_ids(&connection, &files).unwrap(), ["1000"]);
+        update_index(&mut connection, &files, &[first]).unwrap();
+        let mut hits = search(&connection, "budget", &SearchFilters::default());
+        hits.sort();
+        assert_eq!(hits, ["1000 0 Host", "1000 1 Guest"]);
+        assert_eq!(
+            indexed_sessions(&connection).unwrap(),
+            HashMap::from([("1000".to_string(), 20)])
+        );
+    }
+
+    #[test]
+    fn filters_by_time_speaker_and_session() {
+        let mut connection = index();
+        let earlier = session(
+            10_000,
+            vec![
+                said("Host", "launch plan", 0),
+                said("Guest", "launch date", 2000),
+                TimelineEntry::AgentOutput {
+                    agent_name: "Summary".to_string(),
+                    at_ms: 3000,
+                    text: "launch next week".to_string(),
+                    json: None,
+                },
+            ],
+        );
+        let running = session(50_000, vec![said("Guest", "launch party", 0)]);
+        let files = [file(&earlier, 1), file(&running, 1)];
+        update_index(&mut connection, &files, &[earlier, running]).unwrap();
+
+        let mut all = search(&connection, "launch", &SearchFilters::default());
+        all.sort();
+        assert_eq!(
+            all,
+            [
+                "10000 0 Host",
+                "10000 1 Guest",
+                "10000 2 Summary",
+                "50000 0 Guest"
+            ]
+        );
+
+        // Entries happen at the start of the session plus their offset
+        let window = SearchFilters {
+            from: Some(12_000),
+            to: Some(13_000),
+            ..SearchFilters::default()
+        };
+        let mut hits = search(&connection, "launch", &window);
+        hits.sort();
+        assert_eq!(hits, ["10000 1 Guest", "10000 2 Summary"]);
+
+        let speakers = SearchFilters {
+            speakers: Some(vec!["guest".to_string(), "SUMMARY".to_string()]),
+            ..SearchFilters::default()
+        };
+        let mut hits = search_excluding(&connection, "launch", &speakers, Some("50000"));
+        hits.sort();
+        assert_eq!(hits, ["10000 1 Guest", "10000 2 Summary"]);
+
+        let limited = SearchFilters {
+            limit: Some(1),
+            ..SearchFilters::default()
+        };
+        assert_eq!(search(&connection, "launch", &limited).len(), 1);
+        assert!(search(&connection, "launch week party", &SearchFilters::default()).is_empty());
+    }
+
+    fn part(text: &str, highlighted: bool) -> SnippetPart {
+        SnippetPart {
+            text: text.to_string(),
+            highlighted,
+        }
+    }
+
+    #[test]
+    fn quotes_words_and_phrases() {
+        assert_eq!(
+            to_match_query(r#"budget "next quarter"  review"#).as_deref(),
+            Some(r#""budget" "next quarter" "review""#)
+        );
+        assert_eq!(to_match_query(r#"  ""  "#), None);
+    }
+
+    #[test]
+    fn reads_an_unterminated_quote_as_a_phrase() {
+        assert_eq!(
+            to_match_query(r#"ask "about the launch"#).as_deref(),
+            Some(r#""ask" "about the launch""#)
+        );
+    }
+
+    #[test]
+    fn searches_for_operator_characters() {
+        assert_eq!(
+            to_match_query("follow-up NOT owner: anna*").as_deref(),
+            Some(r#""follow-up" "NOT" "owner:" "anna*""#)
+        );
+    }
+
+    #[test]
+    fn splits_snippets_at_highlights() {
+        let snippet = format!(
+            "{s}Budget{e} for the {s}next{e} quarter",
+            s = HIGHLIGHT_START,
+            e = HIGHLIGHT_END
+        );
+        assert_eq!(
+            parse_snippet(&snippet),
+            [
+                part("Budget", true),
+                part(" for the ", false),
+                part("next", true),
+                part(" quarter", false),
+            ]
+        );
+
+        let snippet = format!("the {}launch{}", HIGHLIGHT_START, HIGHLIGHT_END);
+        assert_eq!(
+            parse_snippet(&snippet),
+            [part("the ", false), part("launch", true)]
+        );
+        assert_eq!(parse_snippet("no match"), [part("no match", false)]);
+    }
+}
@@ -0,0 +1,686 @@
+use crate::config::app_config::load_app_config;
+use crate::llm::ollama::embed_ollama;
+use crate::search::index::{open_index, run_blocking, searchable_fields, SearchFilters};
+use crate::transcription::control::running_session_id;
+use crate::transcription::sessions::{list_session_files, load_session, SessionFile};
+use crate::transcription::timeline::SessionTimeline;
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{async_runtime, AppHandle};
+use tokio::sync::Mutex;
+
+// Entries shorter than this, such as "Yeah, sounds good.", are close to everything and say nothing
+const MIN_WORDS: usize = 4;
+// Texts sent to Ollama per request
+const EMBEDDING_BATCH_SIZE: usize = 32;
+
+// Held while embedding, two runs at the same time would embed the same new entries twice
+static EMBEDDING_LOCK: Mutex<()> = Mutex::const_new(());
+// Sessions were saved since embedding last looked at them
+static EMBEDDING_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticHit {
+    pub session_id: String,
+    // Unix time in milliseconds
+    pub session_started_at: u64,
+    // Position of the entry in the session timeline
+    pub entry_index: usize,
+    // Milliseconds since the session started
+    pub at_ms: u64,
+    pub speaker: String,
+    // transcript, note, bookmark or agentOutput
+    pub kind: String,
+    pub text: String,
+    // Cosine similarity to the query, higher is closer
+    pub score: f32,
+}
+
+struct EmbeddedSession {
+    modified_ms: u64,
+    model: String,
+    // Entries of the session looked at so far, sessions only ever grow
+    entry_count: usize,
+}
+
+struct EmbeddedEntry {
+    entry_index: usize,
+    at_ms: u64,
+    speaker: String,
+    kind: &'static str,
+    text: String,
+    vector: Vec<f32>,
+}
+
+struct SessionEmbeddings {
+    file: SessionFile,
+    started_at: u64,
+    entry_count: usize,
+    // Whether embeddings made so far are thrown away rather than added to
+    replace: bool,
+    entries: Vec<EmbeddedEntry>,
+}
+
+/// Entries of saved sessions closest in meaning to the query, closest first.
+/// Only sessions embedded so far are searched, the rest are embedded in the background.
+#[tauri::command]
+pub async fn semantic_search(
+    app_handle: AppHandle,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<SemanticHit>, String> {
+    let filters = filters.unwrap_or_default();
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+    let settings = load_app_config().await?.search.unwrap_or_default();
+    let model = settings.embedding_model;
+    let limit = filters.limit.unwrap_or(settings.related_top_k);
+    let excluded_session_id = match filters.exclude_running_session {
+        true => running_session_id(&app_handle).await,
+        false => None,
+    };
+
+    // Catches up on sessions saved since, e.g. after the embedding model changed
+    spawn_embedding(app_handle);
+
+    let query_vector = embed_ollama(&model, vec![query])
+        .await?
+        .pop()
+        .ok_or_else(|| "Ollama returned no embedding".to_string())?;
+
+    run_blocking(move || {
+        find_closest(
+            &open_embedding_index()?,
+            &query_vector,
+            &model,
+            &filters,
+            excluded_session_id.as_deref(),
+            limit,
+        )
+    })
+    .await
+}
+
+/// Embeds saved sessions that changed since they were last embedded, without making anyone
+/// wait for it. The running session is embedded once it stops.
+pub fn spawn_embedding(app_handle: AppHandle) {
+    EMBEDDING_REQUESTED.store(true, Ordering::SeqCst);
+    async_runtime::spawn(async move {
+        loop {
+            {
+                // Whoever embeds already picks up the request once done
+                let Ok(_lock) = EMBEDDING_LOCK.try_lock() else {
+                    return;
+                };
+                while EMBEDDING_REQUESTED.swap(false, Ordering::SeqCst) {
+                    if let Err(e) = embed_saved_sessions(&app_handle).await {
+                        warn!("Failed to embed sessions: {}", e);
+                    }
+                }
+            }
+            // Requested right as the lock was released
+            if !EMBEDDING_REQUESTED.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+    });
+}
+
+/// Embeds session by session, so searches find each one as soon as it is done
+async fn embed_saved_sessions(app_handle: &AppHandle) -> Result<(), String> {
+    let model = load_app_config()
+        .await?
+        .search
+        .unwrap_or_default()
+        .embedding_model;
+    let running_session_id = running_session_id(app_handle).await;
+    let files = list_session_files().await?;
+    let embedded = run_blocking(|| embedded_sessions(&open_embedding_index()?)).await?;
+
+    let removed_files = files.clone();
+    run_blocking(move || remove_deleted_sessions(&mut open_embedding_index()?, &removed_files))
+        .await?;
+
+    for file in files {
+        if Some(&file.id) == running_session_id.as_ref() {
+            continue;
+        }
+        let previous = embedded.get(&file.id);
+        if is_embedded(previous, &file, &model) {
+            continue;
+        }
+        let session = match load_session(&file.id).await {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("Not embedding session {}: {}", file.id, e);
+                continue;
+            }
+        };
+        let first_entry = first_entry_to_embed(previous, &model, session.entries.len());
+        let update = SessionEmbeddings {
+            started_at: session.started_at,
+            entry_count: session.entries.len(),
+            replace: first_entry == 0,
+            entries: embed_entries(&model, &session, first_entry).await?,
+            file,
+        };
+        let model = model.clone();
+        run_blocking(move || update_embeddings(&mut open_embedding_index()?, &update, &model))
+            .await?;
+    }
+    Ok(())
+}
+
+fn open_embedding_index() -> Result<Connection, String> {
+    let connection = open_index()?;
+    create_embedding_tables(&connection)?;
+    Ok(connection)
+}
+
+fn create_embedding_tables(connection: &Connection) -> Result<(), String> {
+    connection
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS embedded_sessions (
+                id TEXT PRIMARY KEY,
+                modified_ms INTEGER NOT NULL,
+                model TEXT NOT NULL,
+                entry_count INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS embeddings (
+                session_id TEXT NOT NULL,
+                session_started_at INTEGER NOT NULL,
+                entry_index INTEGER NOT NULL,
+                at_ms INTEGER NOT NULL,
+                time INTEGER NOT NULL,
+                speaker TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                text TEXT NOT NULL,
+                vector BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS embeddings_session_id ON embeddings (session_id);",
+        )
+        .map_err(|e| format!("Failed to create embedding index: {}", e))
+}
+
+/// Whether the session is embedded with the model as it was last saved
+fn is_embedded(previous: Option<&EmbeddedSession>, file: &SessionFile, model: &str) -> bool {
+    previous
+        .is_some_and(|previous| previous.modified_ms == file.modified_ms && previous.model == model)
+}
+
+/// Sessions only ever grow, so only entries added since are embedded unless the model changed
+fn first_entry_to_embed(
+    previous: Option<&EmbeddedSession>,
+    model: &str,
+    entry_count: usize,
+) -> usize {
+    match previous {
+        Some(previous) if previous.model == model => previous.entry_count.min(entry_count),
+        _ => 0,
+    }
+}
+
+fn embedded_sessions(connection: &Connection) -> Result<HashMap<String, EmbeddedSession>, String> {
+    let mut statement = connection
+        .prepare("SELECT id, modified_ms, model, entry_count FROM embedded_sessions")
+        .map_err(|e| format!("Failed to read embedding index: {}", e))?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                EmbeddedSession {
+                    modified_ms: row.get::<_, i64>(1)? as u64,
+                    model: row.get(2)?,
+                    entry_count: row.get::<_, i64>(3)? as usize,
+                },
+            ))
+        })
+        .map_err(|e| format!("Failed to read embedding index: {}", e))?;
+    rows.collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("Failed to read embedding index: {}", e))
+}
+
+async fn embed_entries(
+    model: &str,
+    session: &SessionTimeline,
+    first_entry: usize,
+) -> Result<Vec<EmbeddedEntry>, String> {
+    let mut entries: Vec<EmbeddedEntry> = session
+        .entries
+        .iter()
+        .enumerate()
+        .skip(first_entry)
+        .filter_map(|(entry_index, entry)| {
+            let (kind, speaker, text) = searchable_fields(entry)?;
+            (text.split_whitespace().count() >= MIN_WORDS).then(|| EmbeddedEntry {
+                entry_index,
+                at_ms: entry.time_ms(),
+                speaker: speaker.to_string(),
+                kind,
+                text: text.to_string(),
+                vector: Vec::new(),
+            })
+        })
+        .collect();
+    if entries.is_empty() {
+        return Ok(entries);
+    }
+
+    info!(
+        "Embedding {} entries of session {}",
+        entries.len(),
+        session.id
+    );
+    for batch in entries.chunks_mut(EMBEDDING_BATCH_SIZE) {
+        // Who said it helps telling apart e.g. a question from an answer to it
+        let texts = batch
+            .iter()
+            .map(|entry| format!("{}: {}", entry.speaker, entry.text))
+            .collect();
+        let vectors = embed_ollama(model, texts).await?;
+        if vectors.len() != batch.len() {
+            return Err(format!(
+                "Ollama returned {} embeddings for {} texts",
+                vectors.len(),
+                batch.len()
+            ));
+        }
+        for (entry, vector) in batch.iter_mut().zip(vectors) {
+            entry.vector = vector;
+        }
+    }
+    Ok(entries)
+}
+
+/// Drops embeddings of sessions that were deleted
+fn remove_deleted_sessions(
+    connection: &mut Connection,
+    files: &[SessionFile],
+) -> Result<(), String> {
+    let transaction = connection
+        .transaction()
+        .map_err(|e| format!("Failed to update embedding index: {}", e))?;
+    let existing_ids: HashSet<&str> = files.iter().map(|file| file.id.as_str()).collect();
+    let removed_ids: Vec<String> = embedded_sessions(&transaction)?
+        .into_keys()
+        .filter(|id| !existing_ids.contains(id.as_str()))
+        .collect();
+    for id in removed_ids {
+        remove_embeddings(&transaction, &id)?;
+    }
+    transaction
+        .commit()
+        .map_err(|e| format!("Failed to update embedding index: {}", e))
+}
+
+fn update_embeddings(
+    connection: &mut Connection,
+    update: &SessionEmbeddings,
+    model: &str,
+) -> Result<(), String> {
+    let transaction = connection
+        .transaction()
+        .map_err(|e| format!("Failed to update embedding index: {}", e))?;
+
+    if update.replace {
+        remove_embeddings(&transaction, &update.file.id)?;
+    }
+    for entry in &update.entries {
+        transaction
+            .execute(
+                "INSERT INTO embeddings
+                    (session_id, session_started_at, entry_index, at_ms, time, speaker, kind, text, vector)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    update.file.id,
+                    update.started_at as i64,
+                    entry.entry_index as i64,
+                    entry.at_ms as i64,
+                    (update.started_at + entry.at_ms) as i64,
+                    entry.speaker,
+                    entry.kind,
+                    entry.text,
+                    to_blob(&entry.vector),
+                ],
+            )
+            .map_err(|e| format!("Failed to update embedding index: {}", e))?;
+    }
+    transaction
+        .execute(
+            "INSERT OR REPLACE INTO embedded_sessions (id, modified_ms, model, entry_count)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![
+                update.file.id,
+                update.file.modified_ms as i64,
+                model,
+                update.entry_count as i64
+            ],
+        )
+        .map_err(|e| format!("Failed to update embedding index: {}", e))?;
+
+    transaction
+        .commit()
+        .map_err(|e| format!("Failed to update embedding index: {}", e))
+}
+
+fn remove_embeddings(connection: &Connection, id: &str) -> Result<(), String> {
+    connection
+        .execute("DELETE FROM embeddings WHERE session_id = ?1", params![id])
+        .and_then(|_| {
+            connection.execute("DELETE FROM embedded_sessions WHERE id = ?1", params![id])
+        })
+        .map(|_| ())
+        .map_err(|e| format!("Failed to update embedding index: {}", e))
+}
+
+/// Compares the query with every stored embedding, fast enough for years of meetings
+fn find_closest(
+    connection: &Connection,
+    query_vector: &[f32],
+    model: &str,
+    filters: &SearchFilters,
+    excluded_session_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<SemanticHit>, String> {
+    let speakers = filters
+        .speakers
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| format!("Invalid speakers: {}", e))?;
+    let mut statement = connection
+        .prepare(
+            "SELECT e.session_id, e.session_started_at, e.entry_index, e.at_ms, e.speaker, e.kind, e.text, e.vector
+            FROM embeddings e
+            JOIN embedded_sessions s ON s.id = e.session_id
+            WHERE s.model = ?1
+                AND (?2 IS NULL OR e.time >= ?2)
+                AND (?3 IS NULL OR e.time <= ?3)
+                AND (?4 IS NULL OR lower(e.speaker) IN (SELECT lower(value) FROM json_each(?4)))
+                AND (?5 IS NULL OR e.session_id != ?5)",
+        )
+        .map_err(|e| format!("Failed to search: {}", e))?;
+    let rows = statement
+        .query_map(
+            params![
+                model,
+                filters.from.map(|from| from as i64),
+                filters.to.map(|to| to as i64),
+                speakers,
+                excluded_session_id,
+            ],
+            |row| {
+                Ok(SemanticHit {
+                    session_id: row.get(0)?,
+                    session_started_at: row.get::<_, i64>(1)? as u64,
+                    entry_index: row.get::<_, i64>(2)? as usize,
+                    at_ms: row.get::<_, i64>(3)? as u64,
+                    speaker: row.get(4)?,
+                    kind: row.get(5)?,
+                    text: row.get(6)?,
+                    score: cosine_similarity(query_vector, &from_blob(&row.get::<_, Vec<u8>>(7)?)),
+                })
+            },
+        )
+        .map_err(|e| format!("Failed to search: {}", e))?;
+    let mut hits = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to search: {}", e))?;
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a: f32 = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    match norm_a * norm_b {
+        norm if norm > 0.0 => dot / norm,
+        _ => 0.0,
+    }
+}
+
+fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: &str, modified_ms: u64) -> SessionFile {
+        SessionFile {
+            id: id.to_string(),
+            modified_ms,
+        }
+    }
+
+    fn entry(entry_index: usize, speaker: &str, text: &str, vector: Vec<f32>) -> EmbeddedEntry {
+        EmbeddedEntry {
+            entry_index,
+            at_ms: entry_index as u64 * 1000,
+            speaker: speaker.to_string(),
+            kind: "transcript",
+            text: text.to_string(),
+            vector,
+        }
+    }
+
+    fn index() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        create_embedding_tables(&connection).unwrap();
+        connection
+    }
+
+    fn embed(
+        connection: &mut Connection,
+        file: SessionFile,
+        started_at: u64,
+        replace: bool,
+        entries: Vec<EmbeddedEntry>,
+        model: &str,
+    ) {
+        let entry_count = entries.last().map_or(0, |entry| entry.entry_index + 1);
+        let update = SessionEmbeddings {
+            file,
+            started_at,
+            entry_count,
+            replace,
+            entries,
+        };
+        update_embeddings(connection, &update, model).unwrap();
+    }
+
+    fn texts(hits: &[SemanticHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.text.as_str()).collect()
+    }
+
+    #[test]
+    fn stores_vectors_as_blobs() {
+        let vector = vec![1.5, -0.25, 0.0, f32::MAX, f32::MIN_POSITIVE];
+        assert_eq!(to_blob(&vector).len(), 20);
+        assert_eq!(from_blob(&to_blob(&vector)), vector);
+        assert!(from_blob(&[]).is_empty());
+    }
+
+    #[test]
+    fn compares_vectors_by_direction() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        // Vectors of another model or without a direction are close to nothing
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn embeds_only_what_was_added_with_the_same_model() {
+        let previous = EmbeddedSession {
+            modified_ms: 100,
+            model: "nomic".to_string(),
+            entry_count: 3,
+        };
+        assert!(is_embedded(Some(&previous), &file("a", 100), "nomic"));
+        assert!(!is_embedded(Some(&previous), &file("a", 200), "nomic"));
+        assert!(!is_embedded(Some(&previous), &file("a", 100), "other"));
+        assert!(!is_embedded(None, &file("a", 100), "nomic"));
+
+        assert_eq!(first_entry_to_embed(Some(&previous), "nomic", 5), 3);
+        // Shorter than before, such as a session imported again under the same id
+        assert_eq!(first_entry_to_embed(Some(&previous), "nomic", 2), 2);
+        assert_eq!(first_entry_to_embed(Some(&previous), "other", 5), 0);
+        assert_eq!(first_entry_to_embed(None, "nomic", 5), 0);
+    }
+
+    #[test]
+    fn adds_to_or_replaces_embeddings_of_a_session() {
+        let mut connection = index();
+        let said = |index: usize| entry(index, "Host", &format!("line {}", index), vec![1.0]);
+        embed(
+            &mut connection,
+            file("a", 100),
+            0,
+            true,
+            vec![said(0), said(1)],
+            "nomic",
+        );
+        embed(
+            &mut connection,
+            file("a", 200),
+            0,
+            false,
+            vec![said(2)],
+            "nomic",
+        );
+        let hits = find_closest(
+            &connection,
+            &[1.0],
+            "nomic",
+            &SearchFilters::default(),
+            None,
+            10,
+        )
+        .unwrap();
+        assert_eq!(hits.len(), 3);
+        let embedded = embedded_sessions(&connection).unwrap();
+        assert_eq!(embedded["a"].modified_ms, 200);
+        assert_eq!(embedded["a"].entry_count, 3);
+
+        embed(
+            &mut connection,
+            file("a", 300),
+            0,
+            true,
+            vec![said(0)],
+            "other",
+        );
+        assert!(find_closest(
+            &connection,
+            &[1.0],
+            "nomic",
+            &SearchFilters::default(),
+            None,
+            10
+        )
+        .unwrap()
+        .is_empty());
+        let hits = find_closest(
+            &connection,
+            &[1.0],
+            "other",
+            &SearchFilters::default(),
+            None,
+            10,
+        )
+        .unwrap();
+        assert_eq!(texts(&hits), ["line 0"]);
+
+        remove_deleted_sessions(&mut connection, &[file("b", 100)]).unwrap();
+        assert!(embedded_sessions(&connection).unwrap().is_empty());
+    }
+
+    #[test]
+    fn finds_closest_entries_within_the_filters() {
+        let mut connection = index();
+        embed(
+            &mut connection,
+            file("earlier", 100),
+            10_000,
+            true,
+            vec![
+                entry(0, "Host", "pricing plans", vec![1.0, 0.0]),
+                entry(1, "Guest", "pricing questions", vec![0.9, 0.1]),
+            ],
+            "nomic",
+        );
+        embed(
+            &mut connection,
+            file("running", 100),
+            50_000,
+            true,
+            vec![entry(0, "Guest", "weather talk", vec![0.0, 1.0])],
+            "nomic",
+        );
+
+        let all = SearchFilters::default();
+        let hits = find_closest(&connection, &[1.0, 0.0], "nomic", &all, None, 10).unwrap();
+        assert_eq!(
+            texts(&hits),
+            ["pricing plans", "pricing questions", "weather talk"]
+        );
+        assert_eq!(hits[1].at_ms, 1000);
+        assert_eq!(hits[1].session_started_at, 10_000);
+        assert_eq!(
+            texts(&find_closest(&connection, &[1.0, 0.0], "nomic", &all, None, 1).unwrap()),
+            ["pricing plans"]
+        );
+
+        let guests = SearchFilters {
+            speakers: Some(vec!["guest".to_string()]),
+            ..SearchFilters::default()
+        };
+        let hits = find_closest(
+            &connection,
+            &[1.0, 0.0],
+            "nomic",
+            &guests,
+            Some("running"),
+            10,
+        )
+        .unwrap();
+        assert_eq!(texts(&hits), ["pricing questions"]);
+
+        // Entries happen at the start of the session plus their offset
+        let later = SearchFilters {
+            from: Some(11_000),
+            to: Some(50_000),
+            ..SearchFilters::default()
+        };
+        let hits = find_closest(&connection, &[1.0, 0.0], "nomic", &later, None, 10).unwrap();
+        assert_eq!(texts(&hits), ["pricing questions", "weather talk"]);
+        assert!(
+            find_closest(&connection, &[1.0, 0.0], "other", &all, None, 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::agent::trigger::trigger_hotkey_agents;
+use crate::config::app_config::{load_app_config, ShortcutSettings};
+use crate::transcription::control::{add_session_bookmark, toggle_host_devices_paused};
+use crate::util::error_handler::show_error;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tauri::{async_runtime, App, AppHandle, Emitter};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+const SHORTCUT_PRESSED_EVENT_TYPE: &str = "shortcut-pressed";
+const AGENTS_PAUSE_REQUESTED_EVENT_TYPE: &str = "agents-pause-requested";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    ToggleTranscription,
+    PauseAgents,
+    TriggerAgents,
+    Bookmark,
+    ToggleHostMute,
+}
+
+impl ShortcutAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShortcutAction::ToggleTranscription => "toggle transcription",
+            ShortcutAction::PauseAgents => "pause agents",
+            ShortcutAction::TriggerAgents => "trigger agents",
+            ShortcutAction::Bookmark => "bookmark",
+            ShortcutAction::ToggleHostMute => "mute microphone",
+        }
+    }
+}
+
+/// Actions the client owns, such as which devices and model to start transcription with
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutPressedEvent {
+    pub r#type: String,
+    pub action: ShortcutAction,
+}
+
+/// Asks every agent window to pause or resume, so all agents end up in the same state
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentsPauseRequestedEvent {
+    pub r#type: String,
+    pub paused: bool,
+}
+
+static AGENTS_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Whether agents were last asked to pause, agents resumed from their own window are not tracked
+pub fn agents_paused() -> bool {
+    AGENTS_PAUSED.load(Ordering::SeqCst)
+}
+
+/// Registers the shortcuts configured in ollisten.yaml, problems with individual
+/// shortcuts are reported without keeping the others from working
+pub fn setup_shortcuts(app: &App) -> Result<(), String> {
+    // Filled in once the config is read, keyed by shortcut id
+    let actions: Arc<RwLock<HashMap<u32, ShortcutAction>>> = Arc::new(RwLock::new(HashMap::new()));
+
+    app.handle()
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler({
+                    let actions = actions.clone();
+                    move |app_handle, shortcut, event| {
+                        if event.state() != ShortcutState::Pressed {
+                            return;
+                        }
+                        let action = match actions.read() {
+                            Ok(actions) => actions.get(&shortcut.id()).copied(),
+                            Err(e) => {
+                                error!("Failed to read shortcut actions: {}", e);
+                                return;
+                            }
+                        };
+                        if let Some(action) = action {
+                            info!("Shortcut {} pressed", action.label());
+                            let app_handle = app_handle.clone();
+                            async_runtime::spawn(async move {
+                                if let Err(e) =
+                                    run_shortcut_action(app_handle.clone(), action).await
+                                {
+                                    show_error(
+                                        format!("Failed to {}: {}", action.label(), e),
+                                        app_handle,
+                                    );
+                                }
+                            });
+                        }
+                    }
+                })
+                .build(),
+        )
+        .map_err(|e| format!("Failed to setup global shortcuts: {}", e))?;
+
+    let app_handle = app.handle().clone();
+    async_runtime::spawn(async move {
+        let settings = match load_app_config().await {
+            Ok(app_config) => app_config.shortcuts.unwrap_or_default(),
+            Err(e) => {
+                show_error(
+                    format!("Failed to read shortcuts, using defaults: {}", e),
+                    app_handle.clone(),
+                );
+                ShortcutSettings::default()
+            }
+        };
+        register_shortcuts(&app_handle, &settings, &actions);
+    });
+    Ok(())
+}
+
+fn register_shortcuts(
+    app_handle: &AppHandle,
+    settings: &ShortcutSettings,
+    actions: &RwLock<HashMap<u32, ShortcutAction>>,
+) {
+    let bindings = [
+        (
+            ShortcutAction::ToggleTranscription,
+            &settings.toggle_transcription,
+        ),
+        (ShortcutAction::PauseAgents, &settings.pause_agents),
+        (ShortcutAction::TriggerAgents, &settings.trigger_agents),
+        (ShortcutAction::Bookmark, &settings.bookmark),
+        (ShortcutAction::ToggleHostMute, &settings.toggle_host_mute),
+    ];
+
+    let mut registered: HashMap<u32, ShortcutAction> = HashMap::new();
+    for (action, accelerator) in bindings {
+        let Some(accelerator) = accelerator
+            .as_deref()
+            .map(str::trim)
+            .filter(|accelerator| !accelerator.is_empty())
+        else {
+            continue;
+        };
+        let shortcut: Shortcut = match accelerator.parse() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                show_error(
+                    format!(
+                        "Invalid shortcut {} to {}: {}",
+                        accelerator,
+                        action.label(),
+                        e
+                    ),
+                    app_handle.clone(),
+                );
+                continue;
+            }
+        };
+
+        // Same keys written differently, e.g. Alt+Cmd+T and Cmd+Alt+T, have the same id
+        if let Some(other) = registered.get(&shortcut.id()) {
+            show_error(
+                format!(
+                    "Shortcut {} is set to both {} and {}, only the first one works",
+                    accelerator,
+                    other.label(),
+                    action.label()
+                ),
+                app_handle.clone(),
+            );
+            continue;
+        }
+        if let Err(e) = app_handle.global_shortcut().register(shortcut) {
+            show_error(
+                format!(
+                    "Failed to register shortcut {} to {}, another application may be using it: {}",
+                    accelerator,
+                    action.label(),
+                    e
+                ),
+                app_handle.clone(),
+            );
+            continue;
+        }
+        info!("Registered shortcut {} to {}", accelerator, action.label());
+        registered.insert(shortcut.id(), action);
+    }
+
+    match actions.write() {
+        Ok(mut actions) => *actions = registered,
+        Err(e) => error!("Failed to store shortcut actions: {}", e),
+    }
+}
+
+/// Also used by the tray menu, which offers the same actions
+pub async fn run_shortcut_action(
+    app_handle: AppHandle,
+    action: ShortcutAction,
+) -> Result<(), String> {
+    match action {
+        ShortcutAction::ToggleTranscription | ShortcutAction::TriggerAgents => {
+            if action == ShortcutAction::TriggerAgents {
+                // Agents with a trigger only answer the shortcut if it says so
+                trigger_hotkey_agents(&app_handle).await;
+            }
+            app_handle
+                .emit(
+                    SHORTCUT_PRESSED_EVENT_TYPE,
+                    ShortcutPressedEvent {
+                        r#type: SHORTCUT_PRESSED_EVENT_TYPE.to_string(),
+                        action,
+                    },
+                )
+                .map_err(|e| format!("Failed to emit event: {}", e))
+        }
+        ShortcutAction::PauseAgents => {
+            let paused = !AGENTS_PAUSED.fetch_xor(true, Ordering::SeqCst);
+            app_handle
+                .emit(
+                    AGENTS_PAUSE_REQUESTED_EVENT_TYPE,
+                    AgentsPauseRequestedEvent {
+                        r#type: AGENTS_PAUSE_REQUESTED_EVENT_TYPE.to_string(),
+                        paused,
+                    },
+                )
+                .map_err(|e| format!("Failed to emit event: {}", e))
+        }
+        ShortcutAction::Bookmark => add_session_bookmark(app_handle, None).await.map(|_| ()),
+        ShortcutAction::ToggleHostMute => toggle_host_devices_paused(app_handle).await,
+    }
+}
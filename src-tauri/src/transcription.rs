@@ -1,5 +1,10 @@
+pub mod context;
 pub mod control;
-mod cpal_macos_hack;
-mod event;
+pub mod event;
 pub mod model;
+pub mod replay;
+pub mod sessions;
+pub mod speech_to_text;
+pub mod timeline;
+pub mod vocabulary;
 mod voice_audio_detector_ext_v2;
@@ -0,0 +1,130 @@
+/// How much of the previous text is passed on, Whisper only uses the last 224 tokens of its prompt
+const MAX_CONTEXT_CHARS: usize = 300;
+
+/// Longest run of words that can be repeated between two overlapping chunks
+const MAX_STITCH_WORDS: usize = 12;
+
+/// Shortest run of words taken as repeated, a single word such as "the" repeats by chance
+const MIN_STITCH_WORDS: usize = 2;
+
+/// The end of what was said on a device so far, used to continue sentences across chunks
+#[derive(Default)]
+pub struct RollingContext {
+    tail: String,
+}
+
+impl RollingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prompt for the next decode, the glossary followed by the previous text
+    pub fn prompt(&self, glossary: Option<&str>, carry_over: bool) -> Option<String> {
+        let tail = Some(self.tail.as_str()).filter(|tail| carry_over && !tail.is_empty());
+        match (glossary, tail) {
+            (Some(glossary), Some(tail)) => Some(format!("{} {}", glossary, tail)),
+            (Some(glossary), None) => Some(glossary.to_string()),
+            (None, Some(tail)) => Some(tail.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// Remember text that was emitted, keeping only the last few sentences
+    pub fn push(&mut self, text: &str) {
+        if !self.tail.is_empty() {
+            self.tail.push(' ');
+        }
+        self.tail.push_str(text.trim());
+
+        if self.tail.len() > MAX_CONTEXT_CHARS {
+            // Cut at a word boundary so the prompt never starts mid-word
+            let mut cut = self.tail.len() - MAX_CONTEXT_CHARS;
+            while !self.tail.is_char_boundary(cut) {
+                cut += 1;
+            }
+            let cut = self.tail[cut..]
+                .find(' ')
+                .map_or(self.tail.len(), |space| cut + space + 1);
+            self.tail = self.tail[cut..].to_string();
+        }
+    }
+
+    /// Drops words at the start of `text` that repeat the end of the previous text, which
+    /// happens when a chunk starts with audio the previous chunk already ended with
+    pub fn stitch(&self, text: &str) -> String {
+        let previous: Vec<String> = self.tail.split_whitespace().map(normalize_word).collect();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let next: Vec<String> = words.iter().map(|word| normalize_word(word)).collect();
+
+        let max_overlap = MAX_STITCH_WORDS.min(previous.len()).min(next.len());
+        let overlap = (MIN_STITCH_WORDS..=max_overlap)
+            .rev()
+            .find(|&count| previous[previous.len() - count..] == next[..count])
+            .unwrap_or(0);
+
+        words[overlap..].join(" ")
+    }
+}
+
+fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(text: &str) -> RollingContext {
+        let mut context = RollingContext::new();
+        context.push(text);
+        context
+    }
+
+    #[test]
+    fn stitches_repeated_words_of_overlapping_chunks() {
+        let context = context("We should ship the release on Friday.");
+        assert_eq!(
+            context.stitch("on friday, and then take the weekend off"),
+            "and then take the weekend off"
+        );
+        assert_eq!(
+            context.stitch("Nothing repeats here"),
+            "Nothing repeats here"
+        );
+    }
+
+    #[test]
+    fn keeps_a_single_word_repeated_by_chance() {
+        let context = context("Let me share the");
+        assert_eq!(context.stitch("the screen now"), "the screen now");
+    }
+
+    #[test]
+    fn cuts_context_at_a_word_on_a_char_boundary() {
+        let mut context = RollingContext::new();
+        let text = vec!["äöüx"; 100].join(" ");
+        // The cut lands inside a two byte character
+        assert!(!text.is_char_boundary(text.len() - MAX_CONTEXT_CHARS));
+        context.push(&text);
+        assert!(context.tail.len() <= MAX_CONTEXT_CHARS);
+        assert!(context.tail.starts_with("äöüx "));
+        assert!(text.ends_with(&context.tail));
+    }
+
+    #[test]
+    fn prompts_with_glossary_and_previous_text() {
+        let context = context("Hello there.");
+        assert_eq!(
+            context.prompt(Some("Ollisten."), true).as_deref(),
+            Some("Ollisten. Hello there.")
+        );
+        assert_eq!(
+            context.prompt(Some("Ollisten."), false).as_deref(),
+            Some("Ollisten.")
+        );
+        assert_eq!(RollingContext::new().prompt(None, true), None);
+    }
+}
@@ -1,19 +1,70 @@
-use crate::transcription::cpal_macos_hack::create_cpal_mic;
+use crate::agent::finalize::spawn_finalizers;
+use crate::agent::trigger::observe_transcription;
+use crate::audio::device_watcher::DeviceChange;
+use crate::audio::devices::{
+    fetch_hidden_output_device, resolve_device, DeviceOption, DeviceSelector, DEFAULT_DEVICE_ID,
+    DEFAULT_DEVICE_UID,
+};
+use crate::audio::input::{AudioInputStream, SampleStream};
+use crate::audio::meter::{spawn_level_emitter, LevelMeter, MeteredStream};
+use crate::audio::noise::{spawn_noise_level_emitter, NoiseSuppressedStream};
+use crate::audio::resample::ResampledStream;
+use crate::config::app_config::{load_app_config, TranscriptionSettings};
+use crate::search::semantic::spawn_embedding;
+use crate::transcription::context::RollingContext;
 use crate::transcription::event::TranscriptionEvent;
 use crate::transcription::model::TranscriptionModel;
+use crate::transcription::replay::{abort_replay, record_replayed_output};
+use crate::transcription::sessions::save_session;
+use crate::transcription::speech_to_text::{
+    join_segments, load_speech_to_text, SpeechToText, SpeechToTextBackend, TranscribeOptions,
+    SPEECH_SAMPLE_RATE,
+};
+use crate::transcription::timeline::{SessionTimeline, TimelineEntry};
+use crate::transcription::vocabulary::{load_vocabulary, read_global_vocabulary, Vocabulary};
 use crate::transcription::voice_audio_detector_ext_v2::VoiceActivityRechunkerStreamV2;
+use crate::util::error_handler::show_error;
 use kalosm::sound::*;
-use log::{error, info};
-use std::collections::HashMap;
+use log::{error, info, warn};
+use rodio::Source;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::{Mutex, MutexGuard};
 
+/// How long changes to the session record wait to be saved, saving is also done on stop
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+static SESSION_SAVE_LOCK: Mutex<()> = Mutex::const_new(());
+
 pub struct TranscriptionSession {
     pub model_type: TranscriptionModel,
-    pub model: Whisper,
+    pub backend: SpeechToTextBackend,
+    pub model: Arc<dyn SpeechToText>,
+    pub vocabulary: Vocabulary,
+    pub settings: TranscriptionSettings,
+    // Keyed by the device id the client asked for, even after the device got rebound under a new id
     pub listeners: HashMap<i32, Box<dyn FnMut() + Send + Sync>>,
+    // Persistent device UIDs used to find a device again after it reconnects
+    pub device_uids: HashMap<i32, String>,
+    // Devices that went away and are waiting to come back
+    pub lost_devices: HashSet<i32>,
+    // Devices muted by the user, their listeners are stopped until resumed
+    pub paused_devices: HashSet<i32>,
+    // Shared with the listeners, which record what they transcribe
+    pub timeline: Arc<Mutex<SessionTimeline>>,
+    // Audio of this device is what the other side of the meeting says
+    pub guest_device_uid: Option<String>,
+}
+
+impl TranscriptionSession {
+    /// A stopped session keeps its model loaded until the next start
+    pub fn is_running(&self) -> bool {
+        !self.listeners.is_empty()
+            || !self.paused_devices.is_empty()
+            || !self.lost_devices.is_empty()
+    }
 }
 
 pub struct TranscriptionState {
@@ -24,26 +75,50 @@ pub struct TranscriptionState {
 pub async fn start_transcription(
     app_handle: AppHandle,
     model_type: TranscriptionModel,
-    device_ids: Vec<i32>,
+    device_ids: Vec<DeviceSelector>,
+    mode_id: Option<String>,
+    agent_names: Option<Vec<String>>,
     state: State<'_, TranscriptionState>,
 ) -> Result<(), String> {
-    // Validate device IDs - only -1 (default device) or positive IDs are allowed
-    for &device_id in &device_ids {
-        if device_id < -1 {
-            return Err(format!("Invalid device ID: {}. Device IDs must be -1 (default) or positive integers.", device_id));
+    // Vocabulary of the mode and agents being started on top of the global one
+    let vocabulary = match load_vocabulary(mode_id.as_deref(), agent_names.as_deref()).await {
+        Ok(vocabulary) => vocabulary,
+        // A broken agent file is no reason not to transcribe
+        Err(e) => {
+            show_error(
+                format!("Failed to load vocabulary, using the global one: {}", e),
+                app_handle.clone(),
+            );
+            read_global_vocabulary().await.unwrap_or_else(|e| {
+                error!("Failed to read global vocabulary: {}", e);
+                Vocabulary::default()
+            })
         }
-    }
+    };
+    let settings = load_app_config().await?.transcription.unwrap_or_default();
+    let backend = settings.backend.clone();
+
+    // Resolve UIDs and legacy numeric ids to the devices they currently point to
+    let devices = device_ids
+        .iter()
+        .map(resolve_device)
+        .collect::<Result<Vec<_>, _>>()?;
+    let device_ids: Vec<i32> = devices.iter().map(|device| device.id).collect();
 
     let main_app_handle = app_handle.clone();
 
     let mut session = state.session.lock().await;
 
-    // Check if we are already listening with same configuration
-    if let Some(ref session) = *session {
-        let active_sessions = &session.listeners;
+    // Check if we are already listening with same configuration, a stopped session keeps its devices
+    if let Some(session) = session.as_ref().filter(|session| session.is_running()) {
         let active_model_type = session.model_type;
-        let active_device_ids: Vec<i32> = active_sessions.keys().cloned().collect();
-        if active_device_ids == device_ids && active_model_type == model_type {
+        let active_device_ids: BTreeSet<i32> = session.device_uids.keys().cloned().collect();
+        if active_device_ids == device_ids.iter().cloned().collect()
+            && active_model_type == model_type
+            && session.backend == backend
+            && session.vocabulary == vocabulary
+            && session.settings == settings
+        {
             info!("Already listening to the same device ids and using the same model, skipping start.");
             return Ok(());
         }
@@ -57,12 +132,25 @@ pub async fn start_transcription(
     .await
     .map_err(|e| format!("Failed to send started event: {}", e))?;
 
+    // Restarting with another configuration mid-meeting keeps adding to the same record
+    let timeline = match session.as_ref().filter(|session| session.is_running()) {
+        Some(session) => session.timeline.clone(),
+        None => {
+            let timeline = Arc::new(Mutex::new(SessionTimeline::new()));
+            spawn_session_saver(&timeline);
+            timeline
+        }
+    };
+
+    // Agents can't tell a replay from the meeting, so only one of them runs
+    abort_replay(app_handle.clone()).await?;
+
     // Stop any existing transcription
-    abort_all_handles(&mut session)?;
+    abort_all_handles(&mut session).await?;
 
     info!(
-        "Command: Starting transcription with model: {:?}",
-        model_type
+        "Command: Starting transcription with model: {:?} backend: {:?}",
+        model_type, backend
     );
 
     // Create a channel for asynchronous communication from the loading handler
@@ -79,112 +167,64 @@ pub async fn start_transcription(
     });
 
     // Build transcription model with loading handler to track progress
-    let tx_clone = tx.clone();
-    let model = WhisperBuilder::default()
-        .with_source(model_type.to_whisper_source())
-        .build_with_loading_handler(move |loading| match loading {
-            ModelLoadingProgress::Downloading { source, progress } => {
-                let _ = tx_clone.try_send(TranscriptionEvent::TranscriptionDownloadProgress {
-                    source: source.to_string(),
-                    size: progress.size,
-                    progress: progress.progress,
-                });
-            }
-            ModelLoadingProgress::Loading { progress } => {
-                let _ = tx_clone
-                    .try_send(TranscriptionEvent::TranscriptionLoadingProgress { progress });
-            }
-        })
-        .await
-        .map_err(|e| format!("Failed to load model: {}", e))?;
-
-    let mut listeners: HashMap<i32, Box<dyn FnMut() + Send + Sync>> = HashMap::new();
-    for device_id in device_ids {
-        // Set up the microphone
-        let mic = match device_id < 0 {
-            true => MicInput::default(),
-            false => create_cpal_mic(device_id as u32)?,
-        };
-
-        // Create the audio stream
-        let mic_stream = mic.stream();
-        let stream = mic_stream.voice_activity_stream();
-        let stream = VoiceActivityRechunkerStreamV2::new(
-            stream,
-            0.6,                          // start_threshold
-            Duration::from_millis(250),   // start_window
-            0.3,                          // end_threshold
-            Duration::from_millis(100),   // end_window
-            Duration::from_millis(750),   // time_before_speech
-            Duration::from_millis(10000), // max_duration
-            3.0,                          // decay_factor
+    let model = load_speech_to_text(&backend, model_type, tx.clone()).await?;
+    if !model.uses_initial_prompt() && !vocabulary.terms.is_empty() {
+        warn!(
+            "Transcription backend {:?} takes no initial prompt, {} vocabulary terms only apply through replacements",
+            backend,
+            vocabulary.terms.len()
         );
+    }
 
-        // Clone necessary values for the task
-        let model_clone = model.clone();
+    let mut new_session = TranscriptionSession {
+        listeners: HashMap::new(),
+        // Remember the UIDs so devices can be found again if they reconnect under a new id
+        device_uids: devices
+            .iter()
+            .map(|device| (device.id, device.uid.clone()))
+            .collect(),
+        lost_devices: HashSet::new(),
+        paused_devices: HashSet::new(),
+        timeline,
+        guest_device_uid: fetch_hidden_output_device()
+            .ok()
+            .flatten()
+            .map(|device| device.uid),
+        model_type,
+        backend,
+        model,
+        vocabulary,
+        settings,
+    };
+    for device in devices {
+        let device_id = device.id;
+
+        let abort = spawn_device_listener(
+            main_app_handle.clone(),
+            &new_session,
+            device_id,
+            device.uid.clone(),
+            &device,
+        )
+        .await?;
 
         // Emit transcription started event
         send_event(
             main_app_handle.clone(),
-            TranscriptionEvent::TranscriptionStarted { device_id },
+            TranscriptionEvent::TranscriptionStarted {
+                device_id,
+                device_uid: device.uid,
+            },
         )
         .await
         .map_err(|e| format!("Failed to send transcription event: {}", e))?;
 
-        // Spawn a task to handle the transcription
-        let (abort_sender, abort_receiver) = tokio::sync::oneshot::channel();
-        let task_handle = main_app_handle.clone();
-        let handle = tokio::spawn(async move {
-            tokio::select! {
-                _ = abort_receiver => {},
-                _ = async {
-                    let mut text_stream = stream.transcribe(model_clone);
-                    while let Some(chunk) = text_stream.next().await {
-                        // Skip empty chunks
-                        if chunk.text().is_empty() {
-                            continue;
-                        }
-
-                        // Emit the transcribed text with device identifier
-                        if let Err(e) = send_event(
-                            task_handle.clone(),
-                            TranscriptionEvent::TranscriptionData {
-                                device_id,
-                                text: chunk.text().to_string(),
-                                confidence: chunk.confidence(),
-                            },
-                        )
-                        .await
-                        {
-                            error!("Failed to send transcription event: {}", e);
-                        }
-                        break;
-                    }
-                } => {}
-            }
-        });
-
         // Store the abort handle
-        listeners.insert(
-            device_id,
-            Box::new({
-                let mut abort_sender = Some(abort_sender);
-                move || {
-                    if let Some(sender) = abort_sender.take() {
-                        let _ = sender.send(());
-                    }
-                    handle.abort();
-                }
-            }),
-        );
+        new_session.listeners.insert(device_id, abort);
     }
 
     // Update handles and model in session
-    *session = Some(TranscriptionSession {
-        listeners,
-        model_type,
-        model: model.clone(),
-    });
+    *session = Some(new_session);
 
     // Release the lock
     drop(session);
@@ -208,11 +248,29 @@ pub async fn stop_transcription(
     let mut session = state.session.lock().await;
 
     // Stop any existing transcription
-    abort_all_handles(&mut session)?;
+    let was_running = session.as_ref().is_some_and(|session| session.is_running());
+    abort_all_handles(&mut session).await?;
+    let mut ended_session_id = None;
+    if let Some(ref session) = *session {
+        record(&session.timeline, SessionTimeline::end).await;
+        // Finalizers read the record from disk
+        save_timeline(&session.timeline).await;
+        let timeline = session.timeline.lock().await;
+        // Empty sessions are never saved
+        if was_running && !timeline.entries.is_empty() {
+            ended_session_id = Some(timeline.id.clone());
+        }
+    }
 
     // Unlock
     drop(session);
 
+    if let Some(session_id) = ended_session_id {
+        spawn_finalizers(app_handle.clone(), session_id);
+        // Earlier meetings agents look up include this one from now on
+        spawn_embedding(app_handle.clone());
+    }
+
     // Emit model initializing event
     send_event(app_handle, TranscriptionEvent::TranscriptionStopped)
         .await
@@ -222,22 +280,634 @@ pub async fn stop_transcription(
 }
 
 /// Abort all handles for given session
-fn abort_all_handles(session: &mut MutexGuard<Option<TranscriptionSession>>) -> Result<(), String> {
+async fn abort_all_handles(
+    session: &mut MutexGuard<'_, Option<TranscriptionSession>>,
+) -> Result<(), String> {
     if let Some(ref mut session) = **session {
         for (id, mut abort) in session.listeners.drain() {
             info!("Stopping transcription for device {}", id);
             abort();
         }
+        session.lost_devices.clear();
+        let paused_devices: Vec<i32> = session.paused_devices.drain().collect();
+        record(&session.timeline, |timeline| {
+            for device_id in paused_devices {
+                timeline.pause_ended(device_id);
+            }
+        })
+        .await;
+    }
+    Ok(())
+}
+
+/// Applies a change to the timeline, which the session saver writes out shortly after
+async fn record<T>(
+    timeline: &Mutex<SessionTimeline>,
+    update: impl FnOnce(&mut SessionTimeline) -> T,
+) -> T {
+    let mut timeline = timeline.lock().await;
+    let result = update(&mut timeline);
+    timeline.unsaved = true;
+    result
+}
+
+/// Saves the session record every few seconds while it changes, until the session is replaced
+fn spawn_session_saver(timeline: &Arc<Mutex<SessionTimeline>>) {
+    let timeline = Arc::downgrade(timeline);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(SESSION_SAVE_INTERVAL).await;
+            let Some(timeline) = timeline.upgrade() else {
+                return;
+            };
+            save_timeline(&timeline).await;
+        }
+    });
+}
+
+/// Writes the session record if it changed, holding the timeline only to copy it
+async fn save_timeline(timeline: &Mutex<SessionTimeline>) {
+    // Keeps an older copy from being written over a newer one
+    let _lock = SESSION_SAVE_LOCK.lock().await;
+    let snapshot = {
+        let mut timeline = timeline.lock().await;
+        // Sessions where nothing happened aren't worth keeping
+        if !timeline.unsaved || timeline.entries.is_empty() {
+            return;
+        }
+        timeline.unsaved = false;
+        timeline.clone()
+    };
+    if let Err(e) = save_session(&snapshot).await {
+        warn!("Failed to save session {}: {}", snapshot.id, e);
+        // Tried again with the next save
+        timeline.lock().await.unsaved = true;
+    }
+}
+
+/// Stops transcribing a device without unloading the model or touching other devices
+#[tauri::command]
+pub async fn pause_device(
+    app_handle: AppHandle,
+    device: DeviceSelector,
+    state: State<'_, TranscriptionState>,
+) -> Result<(), String> {
+    let mut session = state.session.lock().await;
+    let session = running_session(&mut session)?;
+    let device_id = find_session_device(session, &device)?;
+    pause_session_device(app_handle, session, device_id).await
+}
+
+/// Starts transcribing a paused device again
+#[tauri::command]
+pub async fn resume_device(
+    app_handle: AppHandle,
+    device: DeviceSelector,
+    state: State<'_, TranscriptionState>,
+) -> Result<(), String> {
+    let mut session = state.session.lock().await;
+    let session = running_session(&mut session)?;
+    let device_id = find_session_device(session, &device)?;
+    resume_session_device(app_handle, session, device_id).await
+}
+
+#[tauri::command]
+pub async fn get_session_timeline(
+    state: State<'_, TranscriptionState>,
+) -> Result<Option<SessionTimeline>, String> {
+    let session = state.session.lock().await;
+    match session.as_ref() {
+        Some(session) => Ok(Some(session.timeline.lock().await.clone())),
+        None => Ok(None),
+    }
+}
+
+/// Marks the current moment of the running session, e.g. where a new topic starts
+#[tauri::command]
+pub async fn add_bookmark(
+    app_handle: AppHandle,
+    label: Option<String>,
+) -> Result<TimelineEntry, String> {
+    add_session_bookmark(app_handle, label).await
+}
+
+/// Adds a note to the running session at the current moment
+#[tauri::command]
+pub async fn add_note(app_handle: AppHandle, text: String) -> Result<TimelineEntry, String> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Note is empty".to_string());
+    }
+    let timeline = running_session_timeline(&app_handle).await?;
+    let entry = record(&timeline, |timeline| timeline.note(text.clone())).await;
+    send_event(
+        app_handle,
+        TranscriptionEvent::TranscriptionNote {
+            at_ms: entry.time_ms(),
+            text,
+        },
+    )
+    .await?;
+    Ok(entry)
+}
+
+/// Keeps what agents answered during the session next to what was said
+#[tauri::command]
+pub async fn record_agent_output(
+    app_handle: AppHandle,
+    agent_name: String,
+    text: String,
+    json: Option<serde_json::Value>,
+) -> Result<(), String> {
+    let timeline = match running_session_timeline(&app_handle).await {
+        Ok(timeline) => timeline,
+        // Agents answering a replayed session
+        Err(e) => match record_replayed_output(&app_handle, agent_name, text, json).await? {
+            true => return Ok(()),
+            false => return Err(e),
+        },
+    };
+    record(&timeline, |timeline| {
+        timeline.agent_output(agent_name, text, json)
+    })
+    .await;
+    Ok(())
+}
+
+/// Pauses every device except the hidden output device, or resumes them if all are paused already
+pub async fn toggle_host_devices_paused(app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<TranscriptionState>();
+    let mut session = state.session.lock().await;
+    let Ok(session) = running_session(&mut session) else {
+        return Ok(());
+    };
+    let host_device_ids: Vec<i32> = session
+        .device_uids
+        .iter()
+        .filter(|(_, uid)| Some(*uid) != session.guest_device_uid.as_ref())
+        .map(|(device_id, _)| *device_id)
+        .collect();
+
+    let pause = host_device_ids
+        .iter()
+        .any(|device_id| !session.paused_devices.contains(device_id));
+    for device_id in host_device_ids {
+        match pause {
+            true => pause_session_device(app_handle.clone(), session, device_id).await?,
+            false => resume_session_device(app_handle.clone(), session, device_id).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Marks the current moment of the running session
+pub async fn add_session_bookmark(
+    app_handle: AppHandle,
+    label: Option<String>,
+) -> Result<TimelineEntry, String> {
+    let label = label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+    let timeline = running_session_timeline(&app_handle).await?;
+    let entry = record(&timeline, |timeline| timeline.bookmark(label.clone())).await;
+    send_event(
+        app_handle,
+        TranscriptionEvent::TranscriptionBookmarked {
+            at_ms: entry.time_ms(),
+            label,
+        },
+    )
+    .await?;
+    Ok(entry)
+}
+
+/// Id the running session is saved under
+pub async fn running_session_id(app_handle: &AppHandle) -> Option<String> {
+    let timeline = running_session_timeline(app_handle).await.ok()?;
+    let id = timeline.lock().await.id.clone();
+    Some(id)
+}
+
+async fn running_session_timeline(
+    app_handle: &AppHandle,
+) -> Result<Arc<Mutex<SessionTimeline>>, String> {
+    let state = app_handle.state::<TranscriptionState>();
+    let session = state.session.lock().await;
+    session
+        .as_ref()
+        .filter(|session| session.is_running())
+        .map(|session| session.timeline.clone())
+        .ok_or_else(|| "Transcription is not running".to_string())
+}
+
+/// The session while it transcribes, a stopped one only keeps its devices for the next start
+fn running_session(
+    session: &mut Option<TranscriptionSession>,
+) -> Result<&mut TranscriptionSession, String> {
+    session
+        .as_mut()
+        .filter(|session| session.is_running())
+        .ok_or_else(|| "Transcription is not running".to_string())
+}
+
+/// Session device id of a device selected by the id the client started it with or its UID
+fn find_session_device(
+    session: &TranscriptionSession,
+    device: &DeviceSelector,
+) -> Result<i32, String> {
+    session
+        .device_uids
+        .iter()
+        .find(|(device_id, uid)| match device {
+            DeviceSelector::Id(id) => *device_id == id,
+            DeviceSelector::Uid(selected_uid) => *uid == selected_uid,
+        })
+        .map(|(device_id, _)| *device_id)
+        .ok_or_else(|| format!("Device {:?} is not being transcribed", device))
+}
+
+async fn pause_session_device(
+    app_handle: AppHandle,
+    session: &mut TranscriptionSession,
+    device_id: i32,
+) -> Result<(), String> {
+    if !session.paused_devices.insert(device_id) {
+        return Ok(());
+    }
+    info!("Pausing transcription for device {}", device_id);
+    if let Some(mut abort) = session.listeners.remove(&device_id) {
+        abort();
+    }
+    let device_uid = session
+        .device_uids
+        .get(&device_id)
+        .cloned()
+        .unwrap_or_default();
+    record(&session.timeline, |timeline| {
+        timeline.pause_started(device_id, device_uid)
+    })
+    .await;
+    send_event(
+        app_handle,
+        TranscriptionEvent::TranscriptionDevicePaused { device_id },
+    )
+    .await
+}
+
+async fn resume_session_device(
+    app_handle: AppHandle,
+    session: &mut TranscriptionSession,
+    device_id: i32,
+) -> Result<(), String> {
+    if !session.paused_devices.remove(&device_id) {
+        return Ok(());
     }
+    info!("Resuming transcription for device {}", device_id);
+    record(&session.timeline, |timeline| {
+        timeline.pause_ended(device_id)
+    })
+    .await;
+    send_event(
+        app_handle.clone(),
+        TranscriptionEvent::TranscriptionDeviceResumed { device_id },
+    )
+    .await?;
+
+    // A device lost while paused is picked up by the device watcher when it comes back
+    if session.lost_devices.contains(&device_id) {
+        return Ok(());
+    }
+    let device_uid = session
+        .device_uids
+        .get(&device_id)
+        .cloned()
+        .ok_or_else(|| format!("Device {} is not being transcribed", device_id))?;
+    let device = match resolve_device(&DeviceSelector::Uid(device_uid.clone())) {
+        Ok(device) => device,
+        Err(e) => {
+            info!("Device {} went away while paused: {}", device_id, e);
+            session.lost_devices.insert(device_id);
+            return send_event(
+                app_handle,
+                TranscriptionEvent::TranscriptionDeviceLost { device_id },
+            )
+            .await;
+        }
+    };
+    let abort = spawn_device_listener(app_handle, session, device_id, device_uid, &device).await?;
+    session.listeners.insert(device_id, abort);
     Ok(())
 }
 
-async fn send_event(app_handle: AppHandle, event: TranscriptionEvent) -> Result<(), String> {
+/// Starts transcribing a single device and returns a function that stops it.
+/// Audio is captured from `bind_device` but reported as `device_id`, so a device
+/// that reconnected under a new id keeps the identity the client knows it by.
+async fn spawn_device_listener(
+    app_handle: AppHandle,
+    session: &TranscriptionSession,
+    device_id: i32,
+    device_uid: String,
+    bind_device: &DeviceOption,
+) -> Result<Box<dyn FnMut() + Send + Sync>, String> {
+    let model = session.model.clone();
+    let settings = &session.settings;
+    // Not built for engines that would ignore it
+    let glossary = match session.model.uses_initial_prompt() {
+        true => session.vocabulary.initial_prompt(),
+        false => None,
+    };
+    let replacer = session.vocabulary.replacer();
+    let carry_over_context = settings.carry_over_context && session.model.uses_initial_prompt();
+    let timeline = session.timeline.clone();
+    let speaker = match session.guest_device_uid.as_ref() == Some(&device_uid) {
+        true => "Guest",
+        false => "Host",
+    };
+
+    // Set up the microphone
+    let input = AudioInputStream::open(bind_device).await?;
+    // Downmix and resample to the 16kHz mono the voice activity detector and Whisper expect
+    let channels = input.channels();
+    let sample_rate = input.sample_rate();
+    let input = ResampledStream::new(input, channels, sample_rate);
+    // Quiet background noise before it reaches the voice activity detector, if enabled for the device
+    let input = NoiseSuppressedStream::new(
+        input,
+        settings
+            .noise_suppression
+            .get(&device_uid)
+            .copied()
+            .unwrap_or(0.0),
+        device_id,
+        spawn_noise_level_emitter(app_handle.clone()),
+    );
+
+    // Create the audio stream, metering levels on the way to the rechunker
+    let stream = MeteredStream::new(
+        SampleStream::new(input, SPEECH_SAMPLE_RATE).voice_activity_stream(),
+        LevelMeter::new(device_id),
+        spawn_level_emitter(app_handle.clone()),
+    );
+    let stream = VoiceActivityRechunkerStreamV2::new(
+        stream,
+        0.6,                          // start_threshold
+        Duration::from_millis(250),   // start_window
+        0.3,                          // end_threshold
+        Duration::from_millis(100),   // end_window
+        Duration::from_millis(750),   // time_before_speech
+        Duration::from_millis(10000), // max_duration
+        3.0,                          // decay_factor
+    )
+    .with_overlap(Duration::from_millis(settings.chunk_overlap_ms));
+
+    // Spawn a task to handle the transcription
+    let (abort_sender, abort_receiver) = tokio::sync::oneshot::channel();
+    let handle = tokio::spawn(async move {
+        tokio::select! {
+            _ = abort_receiver => {},
+            _ = async {
+                let mut stream = stream;
+                let mut context = RollingContext::new();
+                while let Some(samples) = stream.next().await {
+                    // Continue from what was said before so sentences survive chunk boundaries
+                    let options = TranscribeOptions {
+                        initial_prompt: context.prompt(glossary.as_deref(), carry_over_context),
+                    };
+                    let duration = samples.total_duration().unwrap_or_default();
+                    let segments = match model.transcribe(samples, &options).await {
+                        Ok(segments) => segments,
+                        Err(e) => {
+                            error!("Failed to transcribe chunk for device {}: {}", device_id, e);
+                            let _ = send_event(
+                                app_handle.clone(),
+                                TranscriptionEvent::TranscriptionError { message: e },
+                            )
+                            .await;
+                            continue;
+                        }
+                    };
+
+                    // Skip empty chunks
+                    let Some(chunk) = join_segments(&segments) else {
+                        continue;
+                    };
+                    let mut text = replacer.apply(&chunk.text);
+                    if stream.last_chunk_overlaps() {
+                        text = context.stitch(&text);
+                    }
+                    if text.is_empty() {
+                        continue;
+                    }
+                    context.push(&text);
+                    record(&timeline, |timeline| {
+                        timeline.push_transcript(
+                            device_id,
+                            device_uid.clone(),
+                            speaker.to_string(),
+                            text.clone(),
+                            chunk.confidence,
+                            duration,
+                        )
+                    })
+                    .await;
+
+                    // Emit the transcribed text with device identifier
+                    if let Err(e) = send_event(
+                        app_handle.clone(),
+                        TranscriptionEvent::TranscriptionData {
+                            device_id,
+                            device_uid: device_uid.clone(),
+                            speaker: speaker.to_string(),
+                            text,
+                            confidence: chunk.confidence,
+                        },
+                    )
+                    .await
+                    {
+                        error!("Failed to send transcription event: {}", e);
+                    }
+                }
+            } => {}
+        }
+    });
+
+    Ok(Box::new({
+        let mut abort_sender = Some(abort_sender);
+        move || {
+            if let Some(sender) = abort_sender.take() {
+                let _ = sender.send(());
+            }
+            handle.abort();
+        }
+    }))
+}
+
+/// Stops listening to devices that went away and rebinds them once they come back
+pub async fn on_device_change(app_handle: AppHandle, change: DeviceChange) -> Result<(), String> {
+    let state = app_handle.state::<TranscriptionState>();
+    let mut session = state.session.lock().await;
+    let Some(ref mut session) = *session else {
+        return Ok(());
+    };
+
+    match change {
+        DeviceChange::Removed(device) => {
+            let lost_device_ids: Vec<i32> = session
+                .listeners
+                .keys()
+                .filter(|id| session.device_uids.get(id) == Some(&device.uid))
+                .cloned()
+                .collect();
+            for device_id in lost_device_ids {
+                info!("Device {} ({}) went away", device_id, device.name);
+                if let Some(mut abort) = session.listeners.remove(&device_id) {
+                    abort();
+                }
+                session.lost_devices.insert(device_id);
+                send_event(
+                    app_handle.clone(),
+                    TranscriptionEvent::TranscriptionDeviceLost { device_id },
+                )
+                .await?;
+            }
+        }
+        DeviceChange::Added(device) => {
+            let restored_device_ids: Vec<i32> = session
+                .lost_devices
+                .iter()
+                .filter(|id| session.device_uids.get(id) == Some(&device.uid))
+                .cloned()
+                .collect();
+            for device_id in restored_device_ids {
+                info!(
+                    "Device {} ({}) is back with id {}",
+                    device_id, device.name, device.id
+                );
+                if session.paused_devices.contains(&device_id) {
+                    // Stays quiet until resumed, which rebinds it then
+                    session.lost_devices.remove(&device_id);
+                    send_event(
+                        app_handle.clone(),
+                        TranscriptionEvent::TranscriptionDeviceRestored { device_id },
+                    )
+                    .await?;
+                    continue;
+                }
+                let abort = spawn_device_listener(
+                    app_handle.clone(),
+                    session,
+                    device_id,
+                    device.uid.clone(),
+                    &device,
+                )
+                .await?;
+                session.listeners.insert(device_id, abort);
+                session.lost_devices.remove(&device_id);
+                send_event(
+                    app_handle.clone(),
+                    TranscriptionEvent::TranscriptionDeviceRestored { device_id },
+                )
+                .await?;
+            }
+        }
+        DeviceChange::DefaultChanged(_) => {
+            // The default device is resolved when its stream opens, reopen it to follow the new one
+            if let Some(mut abort) = session.listeners.remove(&DEFAULT_DEVICE_ID) {
+                info!("Default input device changed, rebinding");
+                abort();
+                let default_device = resolve_device(&DeviceSelector::Id(DEFAULT_DEVICE_ID))?;
+                let abort = spawn_device_listener(
+                    app_handle.clone(),
+                    session,
+                    DEFAULT_DEVICE_ID,
+                    DEFAULT_DEVICE_UID.to_string(),
+                    &default_device,
+                )
+                .await?;
+                session.listeners.insert(DEFAULT_DEVICE_ID, abort);
+                send_event(
+                    app_handle.clone(),
+                    TranscriptionEvent::TranscriptionDeviceRestored {
+                        device_id: DEFAULT_DEVICE_ID,
+                    },
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn send_event(app_handle: AppHandle, event: TranscriptionEvent) -> Result<(), String> {
     info!("Sending {:?}", event);
 
     app_handle
-        .emit(event.variant_name(), event)
+        .emit(event.variant_name(), &event)
         .map_err(|e| format!("Failed to emit event: {}", e))?;
 
+    // After the transcription itself, so agents have it by the time they are triggered
+    observe_transcription(&app_handle, &event).await;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::speech_to_text::TranscribeFuture;
+    use rodio::buffer::SamplesBuffer;
+
+    struct SilentModel;
+
+    impl SpeechToText for SilentModel {
+        fn transcribe<'a>(
+            &'a self,
+            _samples: SamplesBuffer<f32>,
+            _options: &'a TranscribeOptions,
+        ) -> TranscribeFuture<'a> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+    }
+
+    fn session_of(device_id: i32) -> TranscriptionSession {
+        TranscriptionSession {
+            model_type: TranscriptionModel::Tiny,
+            backend: SpeechToTextBackend::Whisper,
+            model: Arc::new(SilentModel),
+            vocabulary: Vocabulary::default(),
+            settings: TranscriptionSettings::default(),
+            listeners: HashMap::from([(
+                device_id,
+                Box::new(|| {}) as Box<dyn FnMut() + Send + Sync>,
+            )]),
+            device_uids: HashMap::from([(device_id, "mic".to_string())]),
+            lost_devices: HashSet::new(),
+            paused_devices: HashSet::new(),
+            timeline: Arc::new(Mutex::new(SessionTimeline::new())),
+            guest_device_uid: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn devices_cant_be_paused_after_stop() {
+        let state = Mutex::new(Some(session_of(3)));
+        let mut session = state.lock().await;
+        let running = running_session(&mut session).unwrap();
+        assert_eq!(
+            find_session_device(running, &DeviceSelector::Uid("mic".to_string())),
+            Ok(3)
+        );
+
+        abort_all_handles(&mut session).await.unwrap();
+        assert_eq!(
+            running_session(&mut session).err().as_deref(),
+            Some("Transcription is not running")
+        );
+        // Kept for the next start, which isn't mistaken for one already listening
+        let stopped = session.as_ref().unwrap();
+        assert!(stopped.device_uids.contains_key(&3));
+        assert!(!stopped.is_running());
+        assert!(!stopped.timeline.lock().await.unsaved);
+    }
+}
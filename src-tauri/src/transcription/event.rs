@@ -1,5 +1,15 @@
 use serde::Serialize;
 
+/// Events after which the session runs with different devices or none at all
+pub const SESSION_STATE_EVENT_TYPES: &[&str] = &[
+    "TranscriptionStarted",
+    "TranscriptionDeviceLost",
+    "TranscriptionDeviceRestored",
+    "TranscriptionDevicePaused",
+    "TranscriptionDeviceResumed",
+    "TranscriptionStopped",
+];
+
 // Event types for the transcription channel
 #[derive(Debug, Serialize, Clone)]
 #[serde(tag = "type")]
@@ -17,14 +27,36 @@ pub enum TranscriptionEvent {
         progress: f32, // from 0 to 1
     },
     #[serde(rename_all = "camelCase")]
-    TranscriptionStarted { device_id: i32 },
+    TranscriptionStarted { device_id: i32, device_uid: String },
     #[serde(rename_all = "camelCase")]
     TranscriptionData {
         device_id: i32,
+        device_uid: String,
+        // Host or Guest, or whoever spoke in a replayed session
+        speaker: String,
         text: String,
         confidence: f64,
     },
     #[serde(rename_all = "camelCase")]
+    TranscriptionDeviceLost { device_id: i32 },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionDeviceRestored { device_id: i32 },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionDevicePaused { device_id: i32 },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionDeviceResumed { device_id: i32 },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionBookmarked { at_ms: u64, label: Option<String> },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionNote { at_ms: u64, text: String },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionReplayStarted {
+        replay_id: String,
+        session_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    TranscriptionReplayFinished { replay_id: String },
+    #[serde(rename_all = "camelCase")]
     TranscriptionError { message: String },
     #[serde(rename_all = "camelCase")]
     TranscriptionStopped,
@@ -42,6 +74,14 @@ impl TranscriptionEvent {
             }
             TranscriptionEvent::TranscriptionStarted { .. } => "TranscriptionStarted",
             TranscriptionEvent::TranscriptionData { .. } => "TranscriptionData",
+            TranscriptionEvent::TranscriptionDeviceLost { .. } => "TranscriptionDeviceLost",
+            TranscriptionEvent::TranscriptionDeviceRestored { .. } => "TranscriptionDeviceRestored",
+            TranscriptionEvent::TranscriptionDevicePaused { .. } => "TranscriptionDevicePaused",
+            TranscriptionEvent::TranscriptionDeviceResumed { .. } => "TranscriptionDeviceResumed",
+            TranscriptionEvent::TranscriptionBookmarked { .. } => "TranscriptionBookmarked",
+            TranscriptionEvent::TranscriptionNote { .. } => "TranscriptionNote",
+            TranscriptionEvent::TranscriptionReplayStarted { .. } => "TranscriptionReplayStarted",
+            TranscriptionEvent::TranscriptionReplayFinished { .. } => "TranscriptionReplayFinished",
             TranscriptionEvent::TranscriptionError { .. } => "TranscriptionError",
             TranscriptionEvent::TranscriptionStopped => "TranscriptionStopped",
         }
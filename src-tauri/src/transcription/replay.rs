@@ -0,0 +1,351 @@
+use crate::transcription::control::{send_event, TranscriptionState};
+use crate::transcription::event::TranscriptionEvent;
+use crate::transcription::sessions::load_session;
+use crate::transcription::timeline::TimelineEntry;
+use crate::util::paths::get_app_sub_path;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+// Agents answer on their own interval, answers this long after the last entry still count
+const REPLAY_ANSWER_GRACE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplaySpeed {
+    // 1 for the pace of the meeting, 10 for ten times as fast
+    Factor(f64),
+    // Everything at once
+    Instant,
+}
+
+pub struct ReplayState {
+    pub replay: Arc<Mutex<Option<Replay>>>,
+}
+
+/// The running replay, kept a while after its last entry for agents still answering it
+pub struct Replay {
+    pub record: ReplayRecord,
+    // How far into the session the replay got
+    position_ms: Arc<AtomicU64>,
+    // Every entry was sent and the client told so
+    finished: Arc<AtomicBool>,
+    abort: Box<dyn FnMut() + Send + Sync>,
+}
+
+/// What agents answered during the meeting next to what they answered when it was replayed,
+/// saved to ~/.ollisten/replays/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRecord {
+    pub id: String,
+    pub session_id: String,
+    pub speed: ReplaySpeed,
+    // Unix time in milliseconds
+    pub started_at: u64,
+    // Agent outputs of the session as it happened
+    pub original: Vec<TimelineEntry>,
+    // Agent outputs of the replay, timed by how far into the session it was
+    pub replayed: Vec<TimelineEntry>,
+}
+
+/// Sends a saved session to agents again through the same events as live transcription.
+/// Agents still answer on their own interval, so at higher speeds each answer covers more.
+#[tauri::command]
+pub async fn replay_session(
+    app_handle: AppHandle,
+    session_id: String,
+    speed: ReplaySpeed,
+    state: State<'_, ReplayState>,
+) -> Result<String, String> {
+    if let ReplaySpeed::Factor(factor) = speed {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(format!("Invalid replay speed: {}", factor));
+        }
+    }
+    {
+        let transcription = app_handle.state::<TranscriptionState>();
+        let session = transcription.session.lock().await;
+        if session.as_ref().is_some_and(|session| session.is_running()) {
+            return Err("Stop transcription before replaying a session".to_string());
+        }
+    }
+    let session = load_session(&session_id).await?;
+
+    let mut replay = state.replay.lock().await;
+    if let Some(ref mut previous) = *replay {
+        (previous.abort)();
+    }
+
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    let record = ReplayRecord {
+        id: format!("{}-{}", session.id, started_at),
+        session_id: session.id.clone(),
+        speed,
+        started_at,
+        original: session
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, TimelineEntry::AgentOutput { .. }))
+            .cloned()
+            .collect(),
+        replayed: Vec::new(),
+    };
+    save_replay(&record).await?;
+    info!(
+        "Replaying session {} at {:?} as {}",
+        session.id, speed, record.id
+    );
+
+    let mut entries: Vec<(u64, TimelineEntry)> = session
+        .entries
+        .into_iter()
+        .map(|entry| (entry.recorded_ms(), entry))
+        .collect();
+    entries.sort_by_key(|(recorded_ms, _)| *recorded_ms);
+
+    send_event(
+        app_handle.clone(),
+        TranscriptionEvent::TranscriptionReplayStarted {
+            replay_id: record.id.clone(),
+            session_id: session.id,
+        },
+    )
+    .await?;
+
+    let position_ms = Arc::new(AtomicU64::new(0));
+    let finished = Arc::new(AtomicBool::new(false));
+    let handle = tokio::spawn({
+        let app_handle = app_handle.clone();
+        let position_ms = position_ms.clone();
+        let finished = finished.clone();
+        let replay_id = record.id.clone();
+        async move {
+            let mut previous_ms = 0;
+            for (recorded_ms, entry) in entries {
+                if let ReplaySpeed::Factor(factor) = speed {
+                    let wait_ms = recorded_ms.saturating_sub(previous_ms) as f64 / factor;
+                    tokio::time::sleep(Duration::from_secs_f64(wait_ms / 1000.0)).await;
+                }
+                previous_ms = recorded_ms;
+                position_ms.store(recorded_ms, Ordering::SeqCst);
+                let Some(event) = replay_event(entry) else {
+                    continue;
+                };
+                if let Err(e) = send_event(app_handle.clone(), event).await {
+                    error!("Failed to send replayed event: {}", e);
+                }
+            }
+            finished.store(true, Ordering::SeqCst);
+            if let Err(e) = send_event(
+                app_handle.clone(),
+                TranscriptionEvent::TranscriptionReplayFinished {
+                    replay_id: replay_id.clone(),
+                },
+            )
+            .await
+            {
+                error!("Failed to send replay finished event: {}", e);
+            }
+
+            tokio::time::sleep(REPLAY_ANSWER_GRACE).await;
+            let state = app_handle.state::<ReplayState>();
+            close_replay(&mut *state.replay.lock().await, &replay_id);
+        }
+    });
+
+    let replay_id = record.id.clone();
+    *replay = Some(Replay {
+        record,
+        position_ms,
+        finished,
+        abort: Box::new(move || handle.abort()),
+    });
+    Ok(replay_id)
+}
+
+#[tauri::command]
+pub async fn stop_replay(app_handle: AppHandle) -> Result<(), String> {
+    abort_replay(app_handle).await
+}
+
+/// Stops the running replay, if any
+pub async fn abort_replay(app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<ReplayState>();
+    let Some(mut replay) = state.replay.lock().await.take() else {
+        return Ok(());
+    };
+    (replay.abort)();
+    if replay.finished.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    send_event(
+        app_handle,
+        TranscriptionEvent::TranscriptionReplayFinished {
+            replay_id: replay.record.id,
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn get_replay(replay_id: String) -> Result<ReplayRecord, String> {
+    let content = fs::read_to_string(replay_path(&replay_id)?)
+        .await
+        .map_err(|e| format!("Failed to read replay {}: {}", replay_id, e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse replay {}: {}", replay_id, e))
+}
+
+/// Records an answer of an agent to the running replay, false if nothing is replayed
+pub async fn record_replayed_output(
+    app_handle: &AppHandle,
+    agent_name: String,
+    text: String,
+    json: Option<serde_json::Value>,
+) -> Result<bool, String> {
+    let state = app_handle.state::<ReplayState>();
+    let mut replay = state.replay.lock().await;
+    let Some(record) = add_replayed_output(&mut replay, agent_name, text, json) else {
+        return Ok(false);
+    };
+    save_replay(record).await?;
+    Ok(true)
+}
+
+fn add_replayed_output(
+    replay: &mut Option<Replay>,
+    agent_name: String,
+    text: String,
+    json: Option<serde_json::Value>,
+) -> Option<&ReplayRecord> {
+    let replay = replay.as_mut()?;
+    replay.record.replayed.push(TimelineEntry::AgentOutput {
+        agent_name,
+        at_ms: replay.position_ms.load(Ordering::SeqCst),
+        text,
+        json,
+    });
+    Some(&replay.record)
+}
+
+/// Stops taking answers for the replay, unless another one took its place already
+fn close_replay(replay: &mut Option<Replay>, replay_id: &str) {
+    if replay
+        .as_ref()
+        .is_some_and(|replay| replay.record.id == replay_id)
+    {
+        *replay = None;
+    }
+}
+
+/// Events live transcription sends for the entry, agents give their own answers
+fn replay_event(entry: TimelineEntry) -> Option<TranscriptionEvent> {
+    match entry {
+        TimelineEntry::Transcript {
+            device_id,
+            device_uid,
+            speaker,
+            text,
+            confidence,
+            ..
+        } => Some(TranscriptionEvent::TranscriptionData {
+            device_id,
+            device_uid,
+            speaker,
+            text,
+            confidence,
+        }),
+        TimelineEntry::Bookmark { at_ms, label } => {
+            Some(TranscriptionEvent::TranscriptionBookmarked { at_ms, label })
+        }
+        TimelineEntry::Note { at_ms, text } => {
+            Some(TranscriptionEvent::TranscriptionNote { at_ms, text })
+        }
+        TimelineEntry::DevicePaused { .. } | TimelineEntry::AgentOutput { .. } => None,
+    }
+}
+
+async fn save_replay(record: &ReplayRecord) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(record)
+        .map_err(|e| format!("Failed to serialize replay: {}", e))?;
+    fs::write(replay_path(&record.id)?, content)
+        .await
+        .map_err(|e| format!("Failed to write replay file: {}", e))
+}
+
+fn replay_path(id: &str) -> Result<PathBuf, String> {
+    // Ids end up in file names, keep them from pointing anywhere else
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid replay id: {}", id));
+    }
+    Ok(get_app_sub_path("replays")?.join(format!("{}.json", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay_of(id: &str, speed: ReplaySpeed) -> Replay {
+        Replay {
+            record: ReplayRecord {
+                id: id.to_string(),
+                session_id: "session".to_string(),
+                speed,
+                started_at: 0,
+                original: Vec::new(),
+                replayed: Vec::new(),
+            },
+            position_ms: Arc::new(AtomicU64::new(0)),
+            finished: Arc::new(AtomicBool::new(false)),
+            abort: Box::new(|| {}),
+        }
+    }
+
+    #[test]
+    fn records_answers_after_an_instant_replay() {
+        let mut replay = Some(replay_of("instant", ReplaySpeed::Instant));
+        // Every entry went out at once, agents answer after that
+        let position_ms = replay.as_ref().unwrap().position_ms.clone();
+        position_ms.store(90_000, Ordering::SeqCst);
+        replay
+            .as_ref()
+            .unwrap()
+            .finished
+            .store(true, Ordering::SeqCst);
+
+        let record =
+            add_replayed_output(&mut replay, "summary".to_string(), "Done".to_string(), None)
+                .unwrap();
+        assert_eq!(
+            record.replayed,
+            [TimelineEntry::AgentOutput {
+                agent_name: "summary".to_string(),
+                at_ms: 90_000,
+                text: "Done".to_string(),
+                json: None,
+            }]
+        );
+
+        // A newer replay isn't closed by the older one running out
+        close_replay(&mut replay, "older");
+        assert!(replay.is_some());
+        close_replay(&mut replay, "instant");
+        assert!(
+            add_replayed_output(&mut replay, "summary".to_string(), "Late".to_string(), None)
+                .is_none()
+        );
+    }
+}
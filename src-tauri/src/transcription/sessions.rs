@@ -0,0 +1,104 @@
+use crate::transcription::timeline::SessionTimeline;
+use crate::util::paths::get_app_sub_path;
+use log::warn;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+
+/// Writes the session to ~/.ollisten/sessions/<id>.json, replacing the previous save at once
+/// so a crash mid-write never leaves a broken record behind
+pub async fn save_session(timeline: &SessionTimeline) -> Result<(), String> {
+    let path = session_path(&timeline.id)?;
+    let temp_path = path.with_extension("json.tmp");
+    let content = serde_json::to_string_pretty(timeline)
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    fs::write(&temp_path, content)
+        .await
+        .map_err(|e| format!("Failed to write session file: {}", e))?;
+    fs::rename(&temp_path, &path)
+        .await
+        .map_err(|e| format!("Failed to write session file: {}", e))
+}
+
+pub async fn load_session(id: &str) -> Result<SessionTimeline, String> {
+    let path = session_path(id)?;
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read session {}: {}", id, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse session {}: {}", id, e))
+}
+
+/// The id itself if no session is saved under it yet, otherwise the id with a number appended
+pub async fn unused_session_id(id: &str) -> Result<String, String> {
+    let mut candidate = id.to_string();
+    let mut number = 1;
+    while fs::try_exists(session_path(&candidate)?)
+        .await
+        .map_err(|e| format!("Failed to read sessions directory: {}", e))?
+    {
+        number += 1;
+        candidate = format!("{}-{}", id, number);
+    }
+    Ok(candidate)
+}
+
+/// A saved session file, without reading what is in it
+#[derive(Debug, Clone)]
+pub struct SessionFile {
+    pub id: String,
+    // Unix time in milliseconds, changes whenever the session is saved
+    pub modified_ms: u64,
+}
+
+pub async fn list_session_files() -> Result<Vec<SessionFile>, String> {
+    let mut entries = fs::read_dir(get_app_sub_path("sessions")?)
+        .await
+        .map_err(|e| format!("Failed to read sessions directory: {}", e))?;
+    let mut files = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read sessions directory: {}", e))?
+    {
+        let path = entry.path();
+        if path
+            .extension()
+            .map_or(true, |extension| extension != "json")
+        {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let modified_ms = match entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+        {
+            Ok(modified) => modified
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            Err(e) => {
+                warn!("Skipping session {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        files.push(SessionFile {
+            id: id.to_string(),
+            modified_ms,
+        });
+    }
+    Ok(files)
+}
+
+fn session_path(id: &str) -> Result<PathBuf, String> {
+    // Ids end up in file names, keep them from pointing anywhere else
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid session id: {}", id));
+    }
+    Ok(get_app_sub_path("sessions")?.join(format!("{}.json", id)))
+}
@@ -0,0 +1,166 @@
+pub mod external_http;
+#[cfg(test)]
+pub mod fake;
+pub mod whisper;
+pub mod whisper_cpp;
+
+use crate::transcription::event::TranscriptionEvent;
+use crate::transcription::model::TranscriptionModel;
+use crate::util::paths::get_app_sub_path;
+use rodio::buffer::SamplesBuffer;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Sample rate every speech-to-text engine here expects its input in
+pub const SPEECH_SAMPLE_RATE: u32 = 16000;
+
+pub type TranscribeFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<TranscriptSegment>, String>> + Send + 'a>>;
+
+/// A speech-to-text engine turning one chunk of speech into text
+pub trait SpeechToText: Send + Sync {
+    fn transcribe<'a>(
+        &'a self,
+        samples: SamplesBuffer<f32>,
+        options: &'a TranscribeOptions,
+    ) -> TranscribeFuture<'a>;
+
+    /// Whether `TranscribeOptions::initial_prompt` reaches the engine, vocabulary terms and
+    /// carried over context only bias engines that take it
+    fn uses_initial_prompt(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TranscribeOptions {
+    // Text the engine should expect to hear, engines that can't be prompted ignore it
+    pub initial_prompt: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranscriptSegment {
+    pub text: String,
+    // From 0 to 1
+    pub confidence: f64,
+}
+
+/// Which engine transcribes the session, the kalosm Whisper model is used if none is given
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum SpeechToTextBackend {
+    Whisper,
+    #[serde(rename_all = "camelCase")]
+    WhisperCpp {
+        // GGML model file, relative paths are resolved against ~/.ollisten/models
+        model_path: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ExternalHttp {
+        // Base of an OpenAI compatible server, e.g. http://localhost:8000
+        base_url: String,
+        model: String,
+        api_key: Option<String>,
+    },
+}
+
+/// Builds the engine for a backend, reporting download and loading progress to `progress`
+pub async fn load_speech_to_text(
+    backend: &SpeechToTextBackend,
+    model_type: TranscriptionModel,
+    progress: mpsc::Sender<TranscriptionEvent>,
+) -> Result<Arc<dyn SpeechToText>, String> {
+    Ok(match backend {
+        SpeechToTextBackend::Whisper => {
+            Arc::new(whisper::WhisperSpeechToText::load(model_type, progress).await?)
+        }
+        SpeechToTextBackend::WhisperCpp { model_path } => {
+            let model_path = resolve_model_path(model_path)?;
+            Arc::new(whisper_cpp::WhisperCppSpeechToText::load(model_path).await?)
+        }
+        SpeechToTextBackend::ExternalHttp {
+            base_url,
+            model,
+            api_key,
+        } => Arc::new(external_http::ExternalHttpSpeechToText::new(
+            base_url.clone(),
+            model.clone(),
+            api_key.clone(),
+        )),
+    })
+}
+
+fn resolve_model_path(model_path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(model_path);
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    Ok(get_app_sub_path("models")?.join(path))
+}
+
+/// Converts a chunk to the mono 16kHz samples speech models are trained on
+pub fn to_speech_samples(samples: SamplesBuffer<f32>) -> Vec<f32> {
+    rodio::source::UniformSourceIterator::new(samples, 1, SPEECH_SAMPLE_RATE).collect()
+}
+
+/// Joins the segments of a chunk into a single piece of text, None if nothing was said
+pub fn join_segments(segments: &[TranscriptSegment]) -> Option<TranscriptSegment> {
+    // Empty segments say nothing about how well the rest was heard
+    let spoken: Vec<(&str, f64)> = segments
+        .iter()
+        .map(|segment| (segment.text.trim(), segment.confidence))
+        .filter(|(text, _)| !text.is_empty())
+        .collect();
+    if spoken.is_empty() {
+        return None;
+    }
+    let texts: Vec<&str> = spoken.iter().map(|(text, _)| *text).collect();
+    let confidence =
+        spoken.iter().map(|(_, confidence)| confidence).sum::<f64>() / spoken.len() as f64;
+    Some(TranscriptSegment {
+        text: texts.join(" "),
+        confidence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeSpeechToText;
+    use super::*;
+
+    #[tokio::test]
+    async fn joins_scripted_segments_and_skips_silence() {
+        let stt = FakeSpeechToText::new(vec!["Hello".to_string(), " ".to_string()]);
+        let chunk = SamplesBuffer::new(1, SPEECH_SAMPLE_RATE, vec![0.0; 1600]);
+
+        let options = TranscribeOptions::default();
+
+        let first = join_segments(&stt.transcribe(chunk.clone(), &options).await.unwrap());
+        let second = join_segments(&stt.transcribe(chunk, &options).await.unwrap());
+
+        assert_eq!(first.map(|segment| segment.text), Some("Hello".to_string()));
+        assert_eq!(second, None);
+        assert_eq!(stt.received_durations_ms(), vec![100, 100]);
+    }
+
+    #[test]
+    fn averages_confidence_of_spoken_segments() {
+        let segment = |text: &str, confidence| TranscriptSegment {
+            text: text.to_string(),
+            confidence,
+        };
+        let chunk = join_segments(&[
+            segment("Hello", 0.9),
+            segment("", 0.0),
+            segment(" ", 0.1),
+            segment("there", 0.7),
+        ])
+        .unwrap();
+        assert_eq!(chunk.text, "Hello there");
+        assert!((chunk.confidence - 0.8).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::transcription::speech_to_text::{
+    to_speech_samples, SpeechToText, TranscribeFuture, TranscribeOptions, TranscriptSegment,
+    SPEECH_SAMPLE_RATE,
+};
+use reqwest::multipart::{Form, Part};
+use rodio::buffer::SamplesBuffer;
+use serde::Deserialize;
+use std::io::Cursor;
+
+/// Posts chunks to a local OpenAI compatible `/v1/audio/transcriptions` server
+pub struct ExternalHttpSpeechToText {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+    segments: Option<Vec<TranscriptionResponseSegment>>,
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponseSegment {
+    text: String,
+    avg_logprob: Option<f64>,
+}
+
+impl ExternalHttpSpeechToText {
+    pub fn new(base_url: String, model: String, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/v1/audio/transcriptions", base_url.trim_end_matches('/')),
+            model,
+            api_key,
+        }
+    }
+}
+
+impl SpeechToText for ExternalHttpSpeechToText {
+    fn transcribe<'a>(
+        &'a self,
+        samples: SamplesBuffer<f32>,
+        options: &'a TranscribeOptions,
+    ) -> TranscribeFuture<'a> {
+        Box::pin(async move {
+            let wav = encode_wav(&to_speech_samples(samples))?;
+            let file = Part::bytes(wav)
+                .file_name("audio.wav")
+                .mime_str("audio/wav")
+                .map_err(|e| format!("Failed to build transcription request: {}", e))?;
+            let mut form = Form::new()
+                .part("file", file)
+                .text("model", self.model.clone())
+                .text("response_format", "verbose_json");
+            if let Some(initial_prompt) = &options.initial_prompt {
+                form = form.text("prompt", initial_prompt.clone());
+            }
+
+            let mut request = self.client.post(&self.url).multipart(form);
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+
+            let response = request
+                .send()
+                .await
+                .map_err(|e| format!("Failed to reach transcription server {}: {}", self.url, e))?
+                .error_for_status()
+                .map_err(|e| format!("Transcription server returned an error: {}", e))?
+                .json::<TranscriptionResponse>()
+                .await
+                .map_err(|e| format!("Failed to parse transcription response: {}", e))?;
+
+            // Servers that ignore verbose_json only return the text
+            Ok(match response.segments {
+                Some(segments) if !segments.is_empty() => segments
+                    .into_iter()
+                    .map(|segment| TranscriptSegment {
+                        text: segment.text,
+                        confidence: segment.avg_logprob.map_or(1.0, f64::exp),
+                    })
+                    .collect(),
+                _ => vec![TranscriptSegment {
+                    text: response.text,
+                    confidence: 1.0,
+                }],
+            })
+        })
+    }
+}
+
+fn encode_wav(samples: &[f32]) -> Result<Vec<u8>, String> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SPEECH_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, spec)
+        .map_err(|e| format!("Failed to encode audio: {}", e))?;
+    for sample in samples {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(|e| format!("Failed to encode audio: {}", e))?;
+    }
+    writer
+        .finalize()
+        .map_err(|e| format!("Failed to encode audio: {}", e))?;
+    Ok(cursor.into_inner())
+}
@@ -0,0 +1,52 @@
+use crate::transcription::speech_to_text::{
+    SpeechToText, TranscribeFuture, TranscribeOptions, TranscriptSegment,
+};
+use rodio::buffer::SamplesBuffer;
+use rodio::Source;
+use std::sync::Mutex;
+
+/// Returns scripted text in order, one entry per chunk, and records what it was given
+pub struct FakeSpeechToText {
+    script: Vec<String>,
+    received: Mutex<Vec<SamplesBuffer<f32>>>,
+}
+
+impl FakeSpeechToText {
+    pub fn new(script: Vec<String>) -> Self {
+        Self {
+            script,
+            received: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn received_durations_ms(&self) -> Vec<u128> {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|samples| samples.total_duration().unwrap_or_default().as_millis())
+            .collect()
+    }
+}
+
+impl SpeechToText for FakeSpeechToText {
+    fn transcribe<'a>(
+        &'a self,
+        samples: SamplesBuffer<f32>,
+        _options: &'a TranscribeOptions,
+    ) -> TranscribeFuture<'a> {
+        Box::pin(async move {
+            let mut received = self.received.lock().unwrap();
+            // Loop over the script so long inputs stay deterministic
+            let text = match self.script.is_empty() {
+                true => String::new(),
+                false => self.script[received.len() % self.script.len()].clone(),
+            };
+            received.push(samples);
+            Ok(vec![TranscriptSegment {
+                text,
+                confidence: 1.0,
+            }])
+        })
+    }
+}
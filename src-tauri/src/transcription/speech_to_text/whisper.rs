@@ -0,0 +1,71 @@
+use crate::transcription::event::TranscriptionEvent;
+use crate::transcription::model::TranscriptionModel;
+use crate::transcription::speech_to_text::{
+    SpeechToText, TranscribeFuture, TranscribeOptions, TranscriptSegment,
+};
+use kalosm::sound::*;
+use rodio::buffer::SamplesBuffer;
+use tokio::sync::mpsc;
+
+/// Whisper running in process through kalosm
+pub struct WhisperSpeechToText {
+    model: Whisper,
+}
+
+impl WhisperSpeechToText {
+    /// Downloads the model if needed and loads it, tracking progress as events
+    pub async fn load(
+        model_type: TranscriptionModel,
+        progress: mpsc::Sender<TranscriptionEvent>,
+    ) -> Result<Self, String> {
+        let model = WhisperBuilder::default()
+            .with_source(model_type.to_whisper_source())
+            .build_with_loading_handler(move |loading| match loading {
+                ModelLoadingProgress::Downloading {
+                    source,
+                    progress: download,
+                } => {
+                    let _ = progress.try_send(TranscriptionEvent::TranscriptionDownloadProgress {
+                        source: source.to_string(),
+                        size: download.size,
+                        progress: download.progress,
+                    });
+                }
+                ModelLoadingProgress::Loading { progress: loading } => {
+                    let _ = progress.try_send(TranscriptionEvent::TranscriptionLoadingProgress {
+                        progress: loading,
+                    });
+                }
+            })
+            .await
+            .map_err(|e| format!("Failed to load model: {}", e))?;
+
+        Ok(Self { model })
+    }
+}
+
+impl SpeechToText for WhisperSpeechToText {
+    // kalosm has no way to prompt Whisper, the vocabulary only applies through replacements
+    fn uses_initial_prompt(&self) -> bool {
+        false
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        samples: SamplesBuffer<f32>,
+        // Has no initial prompt, see uses_initial_prompt
+        _options: &'a TranscribeOptions,
+    ) -> TranscribeFuture<'a> {
+        Box::pin(async move {
+            let mut segments = Vec::new();
+            let mut text_stream = self.model.transcribe(samples);
+            while let Some(segment) = text_stream.next().await {
+                segments.push(TranscriptSegment {
+                    text: segment.text().to_string(),
+                    confidence: segment.confidence(),
+                });
+            }
+            Ok(segments)
+        })
+    }
+}
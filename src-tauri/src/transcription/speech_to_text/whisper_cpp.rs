@@ -0,0 +1,100 @@
+use crate::transcription::speech_to_text::{
+    to_speech_samples, SpeechToText, TranscribeFuture, TranscribeOptions, TranscriptSegment,
+};
+use log::info;
+use rodio::buffer::SamplesBuffer;
+use std::path::PathBuf;
+use std::sync::Arc;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// Whisper through whisper.cpp with a local GGML model file
+pub struct WhisperCppSpeechToText {
+    context: Arc<WhisperContext>,
+}
+
+impl WhisperCppSpeechToText {
+    pub async fn load(model_path: PathBuf) -> Result<Self, String> {
+        if !model_path.is_file() {
+            return Err(format!(
+                "whisper.cpp model not found at {}",
+                model_path.display()
+            ));
+        }
+
+        info!("Loading whisper.cpp model {}", model_path.display());
+        let context = tokio::task::spawn_blocking(move || {
+            let path = model_path
+                .to_str()
+                .ok_or_else(|| format!("Invalid model path: {}", model_path.display()))?;
+            WhisperContext::new_with_params(path, WhisperContextParameters::default())
+                .map_err(|e| format!("Failed to load whisper.cpp model: {}", e))
+        })
+        .await
+        .map_err(|e| format!("Failed to load whisper.cpp model: {}", e))??;
+
+        Ok(Self {
+            context: Arc::new(context),
+        })
+    }
+}
+
+impl SpeechToText for WhisperCppSpeechToText {
+    fn transcribe<'a>(
+        &'a self,
+        samples: SamplesBuffer<f32>,
+        options: &'a TranscribeOptions,
+    ) -> TranscribeFuture<'a> {
+        let context = self.context.clone();
+        let initial_prompt = options.initial_prompt.clone();
+        Box::pin(async move {
+            // whisper.cpp is blocking, keep it off the async runtime
+            tokio::task::spawn_blocking(move || {
+                let samples = to_speech_samples(samples);
+
+                let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+                params.set_print_progress(false);
+                params.set_print_realtime(false);
+                params.set_print_special(false);
+                params.set_print_timestamps(false);
+                if let Some(initial_prompt) = &initial_prompt {
+                    params.set_initial_prompt(initial_prompt);
+                }
+
+                let mut state = context
+                    .create_state()
+                    .map_err(|e| format!("Failed to create whisper.cpp state: {}", e))?;
+                state
+                    .full(params, &samples)
+                    .map_err(|e| format!("Failed to transcribe with whisper.cpp: {}", e))?;
+
+                let segment_count = state
+                    .full_n_segments()
+                    .map_err(|e| format!("Failed to read whisper.cpp segments: {}", e))?;
+                let mut segments = Vec::new();
+                for segment in 0..segment_count {
+                    let text = state
+                        .full_get_segment_text(segment)
+                        .map_err(|e| format!("Failed to read whisper.cpp segment: {}", e))?;
+
+                    // Average token probability stands in for a confidence score
+                    let token_count = state.full_n_tokens(segment).unwrap_or(0);
+                    let confidence = match token_count {
+                        0 => 0.0,
+                        _ => {
+                            (0..token_count)
+                                .filter_map(|token| state.full_get_token_prob(segment, token).ok())
+                                .map(|probability| probability as f64)
+                                .sum::<f64>()
+                                / token_count as f64
+                        }
+                    };
+
+                    segments.push(TranscriptSegment { text, confidence });
+                }
+                Ok(segments)
+            })
+            .await
+            .map_err(|e| format!("whisper.cpp task failed: {}", e))?
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use crate::agent::finalize::FinalizerOutput;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A meeting as it was transcribed, saved to ~/.ollisten/sessions/ while it runs.
+/// Times of entries are in milliseconds since the session started.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTimeline {
+    pub id: String,
+    // Unix time in milliseconds
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    // Entries in the order they were recorded, text is recorded after it was spoken
+    pub entries: Vec<TimelineEntry>,
+    // What finalizer agents made of the meeting once it ended
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summaries: Vec<FinalizerOutput>,
+    #[serde(skip, default = "Instant::now")]
+    started: Instant,
+    // Changed since it was last saved
+    #[serde(skip)]
+    pub unsaved: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum TimelineEntry {
+    #[serde(rename_all = "camelCase")]
+    Transcript {
+        device_id: i32,
+        device_uid: String,
+        // Host or Guest
+        speaker: String,
+        text: String,
+        confidence: f64,
+        start_ms: u64,
+        end_ms: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    DevicePaused {
+        device_id: i32,
+        device_uid: String,
+        start_ms: u64,
+        // None while the device is still paused
+        end_ms: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Bookmark { at_ms: u64, label: Option<String> },
+    #[serde(rename_all = "camelCase")]
+    Note { at_ms: u64, text: String },
+    #[serde(rename_all = "camelCase")]
+    AgentOutput {
+        agent_name: String,
+        at_ms: u64,
+        text: String,
+        // Provided if the agent uses structured output
+        json: Option<serde_json::Value>,
+    },
+}
+
+impl TimelineEntry {
+    /// When the entry starts, entries sorted by this are in the order things happened
+    pub fn time_ms(&self) -> u64 {
+        match self {
+            TimelineEntry::Transcript { start_ms, .. } => *start_ms,
+            TimelineEntry::DevicePaused { start_ms, .. } => *start_ms,
+            TimelineEntry::Bookmark { at_ms, .. } => *at_ms,
+            TimelineEntry::Note { at_ms, .. } => *at_ms,
+            TimelineEntry::AgentOutput { at_ms, .. } => *at_ms,
+        }
+    }
+
+    /// When the entry was known during the meeting, text only once it was said
+    pub fn recorded_ms(&self) -> u64 {
+        match self {
+            TimelineEntry::Transcript { end_ms, .. } => *end_ms,
+            entry => entry.time_ms(),
+        }
+    }
+}
+
+impl SessionTimeline {
+    pub fn new() -> Self {
+        let started_at = unix_time_ms();
+        Self {
+            // Sessions are saved by id, the start time keeps them unique and sorted
+            id: started_at.to_string(),
+            started_at,
+            ended_at: None,
+            entries: Vec::new(),
+            summaries: Vec::new(),
+            started: Instant::now(),
+            unsaved: false,
+        }
+    }
+
+    /// A session recorded elsewhere, such as an imported transcript
+    pub fn from_entries(started_at: u64, entries: Vec<TimelineEntry>) -> Self {
+        let duration_ms = entries
+            .iter()
+            .map(TimelineEntry::recorded_ms)
+            .max()
+            .unwrap_or(0);
+        Self {
+            id: started_at.to_string(),
+            started_at,
+            ended_at: Some(started_at + duration_ms),
+            entries,
+            summaries: Vec::new(),
+            started: Instant::now(),
+            unsaved: false,
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Records text of audio that just ended and lasted `duration`
+    pub fn push_transcript(
+        &mut self,
+        device_id: i32,
+        device_uid: String,
+        speaker: String,
+        text: String,
+        confidence: f64,
+        duration: Duration,
+    ) {
+        let end_ms = self.elapsed_ms();
+        self.entries.push(TimelineEntry::Transcript {
+            device_id,
+            device_uid,
+            speaker,
+            text,
+            confidence,
+            start_ms: end_ms.saturating_sub(duration.as_millis() as u64),
+            end_ms,
+        });
+    }
+
+    pub fn pause_started(&mut self, device_id: i32, device_uid: String) {
+        let start_ms = self.elapsed_ms();
+        self.entries.push(TimelineEntry::DevicePaused {
+            device_id,
+            device_uid,
+            start_ms,
+            end_ms: None,
+        });
+    }
+
+    /// Closes the open paused span of a device, if there is one
+    pub fn pause_ended(&mut self, device_id: i32) {
+        let now = self.elapsed_ms();
+        for entry in self.entries.iter_mut().rev() {
+            match entry {
+                TimelineEntry::DevicePaused {
+                    device_id: paused_device_id,
+                    end_ms: end_ms @ None,
+                    ..
+                } if *paused_device_id == device_id => {
+                    *end_ms = Some(now);
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn bookmark(&mut self, label: Option<String>) -> TimelineEntry {
+        let entry = TimelineEntry::Bookmark {
+            at_ms: self.elapsed_ms(),
+            label,
+        };
+        self.entries.push(entry.clone());
+        entry
+    }
+
+    pub fn note(&mut self, text: String) -> TimelineEntry {
+        let entry = TimelineEntry::Note {
+            at_ms: self.elapsed_ms(),
+            text,
+        };
+        self.entries.push(entry.clone());
+        entry
+    }
+
+    pub fn agent_output(
+        &mut self,
+        agent_name: String,
+        text: String,
+        json: Option<serde_json::Value>,
+    ) {
+        let at_ms = self.elapsed_ms();
+        self.entries.push(TimelineEntry::AgentOutput {
+            agent_name,
+            at_ms,
+            text,
+            json,
+        });
+    }
+
+    pub fn end(&mut self) {
+        self.ended_at = Some(unix_time_ms());
+    }
+}
+
+impl Default for SessionTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
@@ -0,0 +1,371 @@
+use crate::config::agents::{read_agent_config, read_agent_configs};
+use crate::config::app_config::load_app_config;
+use crate::util::paths::get_app_path;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use tokio::fs;
+
+/// Fraction of a term's length that may be misheard and still be replaced, used if none is configured
+const DEFAULT_FUZZINESS: f64 = 0.2;
+
+/// Terms shorter than this are only replaced on an exact match, fuzzy matching them hits common words
+const MIN_FUZZY_LENGTH: usize = 5;
+
+/// Whisper only looks at the last 224 tokens of its prompt, keep the glossary well below that
+const MAX_INITIAL_PROMPT_CHARS: usize = 600;
+
+/// Words the transcription should get right, kept in ~/.ollisten/vocabulary.yaml and
+/// extended by the agents and the mode being run
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Vocabulary {
+    // Product names, customer names and acronyms given to Whisper as its initial prompt, the
+    // built in Whisper backend takes no prompt so there they are only matched like replacements
+    #[serde(default)]
+    pub terms: Vec<String>,
+    // Correct spelling mapped to the ways it tends to be misheard
+    #[serde(default)]
+    pub replacements: BTreeMap<String, Vec<String>>,
+    // From 0 (exact matches only) to 1, how different a heard phrase may be from a term
+    pub fuzziness: Option<f64>,
+}
+
+impl Vocabulary {
+    /// Adds terms and replacements of a more specific vocabulary, its fuzziness wins if set
+    pub fn extend(&mut self, other: &Vocabulary) {
+        for term in &other.terms {
+            if !self.terms.contains(term) {
+                self.terms.push(term.clone());
+            }
+        }
+        for (replacement, variants) in &other.replacements {
+            let existing = self.replacements.entry(replacement.clone()).or_default();
+            for variant in variants {
+                if !existing.contains(variant) {
+                    existing.push(variant.clone());
+                }
+            }
+        }
+        if other.fuzziness.is_some() {
+            self.fuzziness = other.fuzziness;
+        }
+    }
+
+    /// Glossary of all correct spellings, None if there is nothing to bias towards
+    pub fn initial_prompt(&self) -> Option<String> {
+        let mut prompt = String::new();
+        // Lowercase, terms that only differ in case are listed once
+        let mut listed = HashSet::new();
+        for term in self.terms.iter().chain(self.replacements.keys()) {
+            let term = term.trim();
+            if term.is_empty() || !listed.insert(term.to_lowercase()) {
+                continue;
+            }
+            if prompt.len() + term.len() + 2 > MAX_INITIAL_PROMPT_CHARS {
+                warn!(
+                    "Vocabulary is too long for the initial prompt, skipping the rest from {}",
+                    term
+                );
+                break;
+            }
+            if !prompt.is_empty() {
+                prompt.push_str(", ");
+            }
+            prompt.push_str(term);
+        }
+        match prompt.is_empty() {
+            true => None,
+            false => Some(format!("{}.", prompt)),
+        }
+    }
+
+    /// Builds the dictionary applied to transcribed text
+    pub fn replacer(&self) -> VocabularyReplacer {
+        let fuzziness = self.fuzziness.unwrap_or(DEFAULT_FUZZINESS).clamp(0.0, 1.0);
+        let mut patterns = Vec::new();
+        for (replacement, variants) in &self.replacements {
+            // The correct spelling itself is matched too, so near misses of it get fixed
+            for variant in variants.iter().chain(std::iter::once(replacement)) {
+                patterns.extend(Pattern::new(variant, replacement, fuzziness));
+            }
+        }
+        for term in &self.terms {
+            patterns.extend(Pattern::new(term, term, fuzziness));
+        }
+        // Longer phrases first so "acme corp" wins over "acme"
+        patterns.sort_by_key(|pattern| std::cmp::Reverse(pattern.words));
+        VocabularyReplacer { patterns }
+    }
+}
+
+struct Pattern {
+    words: usize,
+    normalized: String,
+    replacement: String,
+    max_distance: usize,
+}
+
+impl Pattern {
+    fn new(variant: &str, replacement: &str, fuzziness: f64) -> Option<Self> {
+        let normalized = normalize(variant);
+        if normalized.is_empty() {
+            return None;
+        }
+        let length = normalized.chars().count();
+        let max_distance = match length < MIN_FUZZY_LENGTH {
+            true => 0,
+            false => (length as f64 * fuzziness).floor() as usize,
+        };
+        Some(Self {
+            words: normalized.split(' ').count(),
+            normalized,
+            replacement: replacement.to_string(),
+            max_distance,
+        })
+    }
+}
+
+/// Replaces misheard phrases with their correct spelling, tolerating small differences
+pub struct VocabularyReplacer {
+    patterns: Vec<Pattern>,
+}
+
+impl VocabularyReplacer {
+    pub fn apply(&self, text: &str) -> String {
+        if self.patterns.is_empty() {
+            return text.to_string();
+        }
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut output: Vec<String> = Vec::with_capacity(words.len());
+        let mut index = 0;
+        'words: while index < words.len() {
+            for pattern in &self.patterns {
+                let Some(window) = words.get(index..index + pattern.words) else {
+                    continue;
+                };
+                let heard = normalize(&window.join(" "));
+                if levenshtein(&heard, &pattern.normalized) > pattern.max_distance {
+                    continue;
+                }
+
+                // Keep punctuation around the phrase, e.g. "(olisten," becomes "(Ollisten,"
+                let prefix: String = window[0]
+                    .chars()
+                    .take_while(|c| !c.is_alphanumeric())
+                    .collect();
+                let suffix: String = window[window.len() - 1]
+                    .chars()
+                    .rev()
+                    .take_while(|c| !c.is_alphanumeric())
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                    .collect();
+                output.push(format!("{}{}{}", prefix, pattern.replacement, suffix));
+                index += pattern.words;
+                continue 'words;
+            }
+            output.push(words[index].to_string());
+            index += 1;
+        }
+        output.join(" ")
+    }
+}
+
+/// Lowercase words without punctuation separated by single spaces
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Global vocabulary extended by the mode and then by each agent being run,
+/// all agents are used if no names are given, same as when starting all agents
+pub async fn load_vocabulary(
+    mode_id: Option<&str>,
+    agent_names: Option<&[String]>,
+) -> Result<Vocabulary, String> {
+    let mut vocabulary = read_global_vocabulary().await?;
+
+    if let Some(mode_id) = mode_id {
+        let app_config = load_app_config().await?;
+        if let Some(mode_vocabulary) = app_config
+            .modes
+            .as_ref()
+            .and_then(|modes| modes.get(mode_id))
+            .and_then(|mode| mode.vocabulary.as_ref())
+        {
+            vocabulary.extend(mode_vocabulary);
+        }
+    }
+
+    let agents = match agent_names {
+        Some(agent_names) => agent_names
+            .iter()
+            .map(|agent_name| read_agent_config(agent_name))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => read_agent_configs()?
+            .into_iter()
+            .map(|agent_config| agent_config.agent)
+            .collect(),
+    };
+    for agent in agents {
+        if let Some(agent_vocabulary) = &agent.vocabulary {
+            vocabulary.extend(agent_vocabulary);
+        }
+    }
+
+    info!(
+        "Loaded vocabulary with {} terms and {} replacements",
+        vocabulary.terms.len(),
+        vocabulary.replacements.len()
+    );
+    Ok(vocabulary)
+}
+
+/// Vocabulary of ~/.ollisten/vocabulary.yaml alone
+pub async fn read_global_vocabulary() -> Result<Vocabulary, String> {
+    let vocabulary_path = get_app_path()?.join("vocabulary.yaml");
+    if !vocabulary_path.is_file() {
+        return Ok(Vocabulary::default());
+    }
+
+    let content = fs::read_to_string(&vocabulary_path)
+        .await
+        .map_err(|e| format!("Failed to read vocabulary file: {}", e))?;
+    if content.trim().is_empty() {
+        return Ok(Vocabulary::default());
+    }
+    serde_yaml::from_str(&content).map_err(|e| {
+        format!(
+            "Failed to parse vocabulary file {}: {}",
+            vocabulary_path.display(),
+            e
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(terms: &[&str], replacements: &[(&str, &[&str])]) -> Vocabulary {
+        Vocabulary {
+            terms: terms.iter().map(|term| term.to_string()).collect(),
+            replacements: replacements
+                .iter()
+                .map(|(replacement, variants)| {
+                    (
+                        replacement.to_string(),
+                        variants.iter().map(|variant| variant.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            fuzziness: None,
+        }
+    }
+
+    #[test]
+    fn replaces_exact_hits_keeping_punctuation() {
+        let replacer = vocabulary(&[], &[("Ollisten", &["oh listen"])]).replacer();
+        assert_eq!(
+            replacer.apply("We use (oh listen, daily"),
+            "We use (Ollisten, daily"
+        );
+    }
+
+    #[test]
+    fn replaces_fuzzy_hits_within_the_threshold() {
+        let replacer = vocabulary(&["Kubernetes"], &[]).replacer();
+        // Two of ten characters differ, the default fuzziness allows two
+        assert_eq!(
+            replacer.apply("deployed on kubernotos today"),
+            "deployed on Kubernetes today"
+        );
+    }
+
+    #[test]
+    fn keeps_near_misses_beyond_the_threshold() {
+        let replacer = vocabulary(&["Kubernetes", "Acme"], &[]).replacer();
+        assert_eq!(
+            replacer.apply("kobernotos is not it"),
+            "kobernotos is not it"
+        );
+        // Short terms only match exactly
+        assert_eq!(replacer.apply("acne treatment"), "acne treatment");
+
+        let exact = Vocabulary {
+            fuzziness: Some(0.0),
+            ..vocabulary(&["Kubernetes"], &[])
+        };
+        assert_eq!(exact.replacer().apply("kubernetis"), "kubernetis");
+    }
+
+    #[test]
+    fn ignores_case_and_prefers_longer_phrases() {
+        let replacer = vocabulary(&["Acme"], &[("Acme Corp", &[])]).replacer();
+        assert_eq!(
+            replacer.apply("ACME CORP bought acme"),
+            "Acme Corp bought Acme"
+        );
+    }
+
+    #[test]
+    fn merges_terms_and_replacements() {
+        let mut merged = vocabulary(&["Acme"], &[("Ollisten", &["oh listen"])]);
+        merged.extend(&Vocabulary {
+            fuzziness: Some(0.5),
+            ..vocabulary(
+                &["Acme", "Kubernetes"],
+                &[("Ollisten", &["oh listen", "all listen"])],
+            )
+        });
+        assert_eq!(merged.terms, ["Acme", "Kubernetes"]);
+        assert_eq!(merged.replacements["Ollisten"], ["oh listen", "all listen"]);
+        assert_eq!(merged.fuzziness, Some(0.5));
+
+        // Unset fuzziness of the more specific vocabulary keeps the current one
+        merged.extend(&vocabulary(&[], &[]));
+        assert_eq!(merged.fuzziness, Some(0.5));
+        assert_eq!(
+            merged.initial_prompt().as_deref(),
+            Some("Acme, Kubernetes, Ollisten.")
+        );
+    }
+
+    #[test]
+    fn lists_terms_contained_in_other_terms() {
+        let vocabulary = vocabulary(
+            &["Acme Corp", "Acmeville", "ACME", "Acme"],
+            &[("acme", &[])],
+        );
+        assert_eq!(
+            vocabulary.initial_prompt().as_deref(),
+            Some("Acme Corp, Acmeville, ACME.")
+        );
+    }
+}
@@ -1,3 +1,7 @@
+// Test only, the app is a binary crate so nothing outside its own tests could use it
+#[cfg(test)]
+pub mod script;
+
 use futures_core::ready;
 use kalosm::sound::VoiceActivityDetectorOutput;
 use rodio::buffer::SamplesBuffer;
@@ -24,6 +28,9 @@ pub struct VoiceActivityRechunkerStreamV2<S> {
     duration_in_window: Duration,
     voice_probabilities_window_sum: f32,
     voice_probabilities_before_window_sum: f32,
+    overlap: Duration,
+    voice_run_overlaps: bool,
+    last_chunk_overlaps: bool,
 }
 
 impl<S> VoiceActivityRechunkerStreamV2<S> {
@@ -56,9 +63,25 @@ impl<S> VoiceActivityRechunkerStreamV2<S> {
             duration_in_window: Duration::ZERO,
             voice_probabilities_window_sum: 0.0,
             voice_probabilities_before_window_sum: 0.0,
+            overlap: Duration::ZERO,
+            voice_run_overlaps: false,
+            last_chunk_overlaps: false,
         }
     }
 
+    /// Repeat the last `overlap` of audio at the start of the next chunk when a voice run
+    /// is cut at `max_duration`, so words on the boundary are heard whole at least once
+    pub fn with_overlap(mut self, overlap: Duration) -> Self {
+        // A run has to make progress before it is cut again
+        self.overlap = overlap.min(self.max_duration / 2);
+        self
+    }
+
+    /// True if the chunk returned last starts with audio the chunk before it ended with
+    pub fn last_chunk_overlaps(&self) -> bool {
+        self.last_chunk_overlaps
+    }
+
     fn add_sample(&mut self, probability: f32, len: Duration, window: Duration) {
         // info!(
         //     "add_sample: probability: {}, len: {}s, window: {}s",
@@ -71,8 +94,9 @@ impl<S> VoiceActivityRechunkerStreamV2<S> {
             .push_front((probability, len));
         self.voice_probabilities_window_sum += probability * len.as_secs_f32();
         self.duration_in_window += len;
-        // If the buffer is full, remove the first probability from the rolling average
-        while self.duration_in_window > window {
+        // If the buffer is full, remove the first probability from the rolling average,
+        // always keep the latest so a zero window follows the latest probability
+        while self.duration_in_window > window && self.voice_probabilities_window.len() > 1 {
             self.pop_last_sample();
         }
     }
@@ -88,24 +112,56 @@ impl<S> VoiceActivityRechunkerStreamV2<S> {
     }
 
     fn window_rolling_average(&self) -> f32 {
-        self.voice_probabilities_window_sum / self.duration_in_window.as_secs_f32()
+        if self.duration_in_window.is_zero() {
+            // Empty outputs carry no evidence either way, fall back to the latest probability
+            return self
+                .voice_probabilities_window
+                .front()
+                .map_or(0.0, |(probability, _)| *probability);
+        }
+        // Rounding in the running sum can dip below zero once only silence is left
+        (self.voice_probabilities_window_sum / self.duration_in_window.as_secs_f32()).max(0.0)
     }
 
     fn voice_rolling_average(&self) -> f32 {
+        let duration =
+            self.duration_in_voice.as_secs_f32() + self.include_duration_before.as_secs_f32();
+        if duration == 0.0 {
+            return self.window_rolling_average();
+        }
         (self.voice_probabilities_window_sum + self.voice_probabilities_before_window_sum)
-            / (self.duration_in_voice.as_secs_f32() + self.include_duration_before.as_secs_f32())
+            / duration
     }
 
     /// Calculate the end threshold that determines at what rolling window average we should stop.
     /// perform an exponential decay by getting closer to the average voice level.
     fn decaying_end_threshold(&self) -> f32 {
         let voice_rolling_average = self.voice_rolling_average();
+        // Nothing to decay from or towards, the logarithm would not be finite
+        if self.end_threshold <= 0.0 || voice_rolling_average <= 0.0 || self.max_duration.is_zero()
+        {
+            return self.end_threshold.max(0.0);
+        }
         let k = self.decay_factor * (voice_rolling_average / self.end_threshold).ln()
             / self.max_duration.as_secs_f32();
         self.end_threshold * E.powf(k * self.duration_in_voice.as_secs_f32())
     }
 
-    fn finish_voice_run(&mut self) -> SamplesBuffer<f32> {
+    fn finish_voice_run(&mut self, cut_at_max_duration: bool) -> SamplesBuffer<f32> {
+        // Keep the end of a run that was cut short to start the next one with
+        let mut carried = VecDeque::new();
+        let mut carried_duration = Duration::ZERO;
+        if cut_at_max_duration {
+            for sample in self.buffer.iter().rev() {
+                if carried_duration >= self.overlap {
+                    break;
+                }
+                carried_duration +=
+                    rodio::Source::total_duration(sample).expect("samples must have a duration");
+                carried.push_front(sample.clone());
+            }
+        }
+
         let samples = SamplesBuffer::new(
             self.channels,
             self.sample_rate,
@@ -114,6 +170,7 @@ impl<S> VoiceActivityRechunkerStreamV2<S> {
                 .flatten()
                 .collect::<Vec<_>>(),
         );
+        self.last_chunk_overlaps = self.voice_run_overlaps;
         self.voice_probabilities_window_sum = 0.0;
         self.voice_probabilities_before_window_sum = 0.0;
         self.duration_in_window = Duration::ZERO;
@@ -122,6 +179,15 @@ impl<S> VoiceActivityRechunkerStreamV2<S> {
         self.duration_before_window = Duration::ZERO;
         self.duration_in_voice = Duration::ZERO;
         self.buffer.clear();
+        self.voice_run_overlaps = false;
+
+        // Speech is still going on, continue it in a new run starting with the carried audio
+        if !carried.is_empty() {
+            self.buffer = carried;
+            self.in_voice_run = true;
+            self.duration_in_voice = carried_duration;
+            self.voice_run_overlaps = true;
+        }
         samples
     }
 }
@@ -140,7 +206,8 @@ impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin> future
             let source = std::pin::pin!(&mut this.source);
             let next = ready!(source.poll_next(cx));
             if let Some(next) = next {
-                // Set the sample rate from the stream
+                // Set the format from the stream, chunks are built with the same format
+                this.channels = rodio::Source::channels(&next.samples);
                 this.sample_rate = rodio::Source::sample_rate(&next.samples);
                 let sample_duration = rodio::Source::total_duration(&next.samples)
                     .expect("samples must have a duration");
@@ -169,10 +236,9 @@ impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin> future
                     //     this.duration_in_voice.as_secs_f64(),
                     //     next.probability,
                     // );
-                    if rolling_average < decaying_end_threshold
-                        || this.duration_in_voice > this.max_duration
-                    {
-                        let samples = this.finish_voice_run();
+                    let cut_at_max_duration = this.duration_in_voice > this.max_duration;
+                    if rolling_average < decaying_end_threshold || cut_at_max_duration {
+                        let samples = this.finish_voice_run(cut_at_max_duration);
                         return Poll::Ready(Some(samples));
                     }
                 } else {
@@ -180,8 +246,11 @@ impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin> future
                     this.duration_before_window += sample_duration;
                     // If the pre-voice buffer is full, remove the first sample from it
                     while this.duration_before_window >= this.include_duration_before {
-                        let sample = this.buffer.pop_front()
-                            .expect("buffer should not be empty when duration_before_window >= include_duration_before");
+                        // Without any pre-roll the buffer runs empty
+                        let Some(sample) = this.buffer.pop_front() else {
+                            this.duration_before_window = Duration::ZERO;
+                            break;
+                        };
                         this.duration_before_window -= rodio::Source::total_duration(&sample)
                             .expect("samples must have a duration");
                     }
@@ -189,7 +258,7 @@ impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin> future
             } else {
                 // Finish off the current voice run if there is one
                 if this.in_voice_run {
-                    let samples = this.finish_voice_run();
+                    let samples = this.finish_voice_run(false);
                     return Poll::Ready(Some(samples));
                 }
                 // Otherwise, return None and finish the stream
@@ -198,3 +267,258 @@ impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin> future
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::script::{sample_positions, ScriptedVoiceActivity};
+    use super::*;
+    use proptest::prelude::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+    const STEP: Duration = Duration::from_millis(30);
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn samples(millis: u64) -> usize {
+        (millis * SAMPLE_RATE as u64 / 1000) as usize
+    }
+
+    fn script() -> ScriptedVoiceActivity {
+        ScriptedVoiceActivity::new(SAMPLE_RATE, STEP)
+    }
+
+    /// Same settings as transcription::control
+    fn rechunker(
+        script: ScriptedVoiceActivity,
+    ) -> VoiceActivityRechunkerStreamV2<ScriptedVoiceActivity> {
+        VoiceActivityRechunkerStreamV2::new(
+            script,
+            0.6,
+            ms(250),
+            0.3,
+            ms(100),
+            ms(750),
+            ms(10000),
+            3.0,
+        )
+    }
+
+    /// First and one past the last sample position of each chunk
+    fn boundaries(chunks: &[SamplesBuffer<f32>]) -> Vec<(usize, usize)> {
+        chunks
+            .iter()
+            .map(|chunk| {
+                let positions = sample_positions(chunk);
+                (positions[0], positions[positions.len() - 1] + 1)
+            })
+            .collect()
+    }
+
+    fn assert_contiguous(chunks: &[SamplesBuffer<f32>]) {
+        for chunk in chunks {
+            let positions = sample_positions(chunk);
+            assert!(!positions.is_empty(), "empty chunk");
+            assert!(
+                positions.windows(2).all(|pair| pair[1] == pair[0] + 1),
+                "chunk skips or repeats audio"
+            );
+        }
+    }
+
+    #[test]
+    fn silence_produces_no_chunks() {
+        let chunks = rechunker(script().then(0.0, ms(30000))).collect_chunks();
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn utterance_becomes_one_chunk_with_pre_roll() {
+        let chunks = rechunker(
+            script()
+                .then(0.0, ms(3000))
+                .then(1.0, ms(1500))
+                .then(0.0, ms(3000)),
+        )
+        .collect_chunks();
+
+        assert_contiguous(&chunks);
+        // Voice is detected 150ms in, the 720ms before that are kept as pre-roll
+        assert_eq!(boundaries(&chunks), vec![(samples(2400), samples(4560))]);
+    }
+
+    #[test]
+    fn utterances_separated_by_silence_become_separate_chunks() {
+        let chunks = rechunker(
+            script()
+                .then(0.0, ms(2000))
+                .then(1.0, ms(1200))
+                .then(0.0, ms(2000))
+                .then(0.9, ms(900))
+                .then(0.0, ms(2000)),
+        )
+        .collect_chunks();
+
+        assert_contiguous(&chunks);
+        assert_eq!(
+            boundaries(&chunks),
+            vec![(samples(1380), samples(3260)), (samples(4610), samples(6160))]
+        );
+    }
+
+    #[test]
+    fn stream_end_finishes_the_open_chunk() {
+        let chunks = rechunker(script().then(0.0, ms(1000)).then(1.0, ms(2000))).collect_chunks();
+
+        assert_eq!(boundaries(&chunks), vec![(samples(420), samples(3000))]);
+    }
+
+    #[test]
+    fn long_speech_is_split_without_losing_audio() {
+        let script = script().then(1.0, ms(25000));
+        let total = script.total_samples();
+        let chunks = rechunker(script).collect_chunks();
+
+        assert_contiguous(&chunks);
+        let boundaries = boundaries(&chunks);
+        assert!(boundaries.len() >= 3);
+        assert_eq!(boundaries[0].0, 0);
+        assert_eq!(boundaries[boundaries.len() - 1].1, total);
+        for pair in boundaries.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0, "gap or repeat between chunks");
+        }
+        for (start, end) in boundaries {
+            assert!(end - start <= samples(10000 + 750 + 30));
+        }
+    }
+
+    #[test]
+    fn overlap_repeats_the_end_of_a_cut_chunk() {
+        // Without an end threshold speech is only ever cut at max_duration
+        let chunks = VoiceActivityRechunkerStreamV2::new(
+            script().then(1.0, ms(25000)),
+            0.6,
+            ms(250),
+            0.0,
+            ms(100),
+            ms(750),
+            ms(10000),
+            3.0,
+        )
+        .with_overlap(ms(500))
+        .collect_chunks();
+
+        assert_contiguous(&chunks);
+        let boundaries = boundaries(&chunks);
+        let cut = boundaries
+            .windows(2)
+            .find(|pair| pair[1].0 < pair[0].1)
+            .expect("no overlapping chunks");
+        // Whole detector outputs are carried over, 17 of 30ms cover the 500ms
+        assert_eq!(cut[0].1 - cut[1].0, samples(510));
+    }
+
+    #[test]
+    fn zero_end_threshold_only_cuts_at_max_duration() {
+        let chunks = VoiceActivityRechunkerStreamV2::new(
+            script()
+                .then(0.0, ms(1000))
+                .then(1.0, ms(2000))
+                .then(0.0, ms(15000)),
+            0.6,
+            ms(250),
+            0.0,
+            ms(100),
+            ms(750),
+            ms(10000),
+            3.0,
+        )
+        .collect_chunks();
+
+        assert_contiguous(&chunks);
+        // Silence never drops below a threshold of 0, the chunk runs until it is too long
+        let boundaries = boundaries(&chunks);
+        assert_eq!(boundaries.len(), 1);
+        assert!(boundaries[0].1 - boundaries[0].0 > samples(10000));
+    }
+
+    #[test]
+    fn empty_windows_and_outputs_do_not_divide_by_zero() {
+        let chunks = VoiceActivityRechunkerStreamV2::new(
+            script()
+                .then_output(0.0, 0)
+                .then(0.0, ms(300))
+                .then_output(0.0, 0)
+                .then(1.0, ms(600))
+                .then_output(1.0, 0)
+                .then(0.0, ms(300)),
+            0.6,
+            Duration::ZERO,
+            0.3,
+            Duration::ZERO,
+            Duration::ZERO,
+            ms(10000),
+            3.0,
+        )
+        .collect_chunks();
+
+        // With no windows the latest probability decides, the first silent output ends the run
+        assert_eq!(boundaries(&chunks), vec![(samples(300), samples(930))]);
+    }
+
+    /// Segments of (probability, duration in ms)
+    fn arbitrary_script() -> impl Strategy<Value = Vec<(f32, u64)>> {
+        prop::collection::vec(
+            (
+                prop_oneof![Just(0.0f32), Just(1.0f32), 0.0f32..=1.0],
+                0u64..4000,
+            ),
+            1..12,
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn chunks_are_contiguous_ordered_and_cover_speech(
+            segments in arbitrary_script(),
+            overlap in prop_oneof![Just(0u64), 0u64..2000],
+        ) {
+            let mut script = script();
+            // Sample ranges of clear speech long enough to start a voice run
+            let mut speech = Vec::new();
+            for (probability, millis) in &segments {
+                let start = script.total_samples();
+                script = script.then(*probability, ms(*millis));
+                if *probability == 1.0 && *millis >= 300 {
+                    speech.push(start..script.total_samples());
+                }
+            }
+
+            let chunks = rechunker(script).with_overlap(ms(overlap)).collect_chunks();
+            assert_contiguous(&chunks);
+            let boundaries = boundaries(&chunks);
+
+            // Chunks only repeat audio when overlap is on, and then only the carried tail
+            for pair in boundaries.windows(2) {
+                prop_assert!(pair[0].0 < pair[1].0);
+                if overlap == 0 {
+                    prop_assert!(pair[0].1 <= pair[1].0);
+                } else {
+                    prop_assert!(pair[0].1 <= pair[1].0 + samples(overlap + 30));
+                }
+            }
+
+            // No clear speech is dropped
+            for range in speech {
+                for position in range {
+                    prop_assert!(
+                        boundaries.iter().any(|(start, end)| (*start..*end).contains(&position)),
+                        "speech sample {} is in no chunk",
+                        position
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::transcription::voice_audio_detector_ext_v2::VoiceActivityRechunkerStreamV2;
+use kalosm::sound::VoiceActivityDetectorOutput;
+use rodio::buffer::SamplesBuffer;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// Voice activity detector outputs played back from a script instead of a microphone.
+///
+/// Sample values count up from 0, so every sample in a chunk can be traced back to its
+/// position in the script.
+pub struct ScriptedVoiceActivity {
+    sample_rate: u32,
+    step: Duration,
+    next_sample: usize,
+    outputs: VecDeque<VoiceActivityDetectorOutput>,
+}
+
+impl ScriptedVoiceActivity {
+    /// Outputs are split into `step` long pieces, like the detector does with its frames
+    pub fn new(sample_rate: u32, step: Duration) -> Self {
+        Self {
+            sample_rate,
+            step,
+            next_sample: 0,
+            outputs: VecDeque::new(),
+        }
+    }
+
+    /// Appends `duration` of audio with the given voice probability
+    pub fn then(mut self, probability: f32, duration: Duration) -> Self {
+        let mut remaining = self.samples_in(duration);
+        let step = self.samples_in(self.step).max(1);
+        while remaining > 0 {
+            let len = remaining.min(step);
+            self.push_output(probability, len);
+            remaining -= len;
+        }
+        self
+    }
+
+    /// Appends a single output of exactly `samples` samples, which may be none
+    pub fn then_output(mut self, probability: f32, samples: usize) -> Self {
+        self.push_output(probability, samples);
+        self
+    }
+
+    /// Number of samples scripted so far
+    pub fn total_samples(&self) -> usize {
+        self.next_sample
+    }
+
+    fn samples_in(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate as f64).round() as usize
+    }
+
+    fn push_output(&mut self, probability: f32, len: usize) {
+        let samples = (self.next_sample..self.next_sample + len)
+            .map(|index| index as f32)
+            .collect::<Vec<_>>();
+        self.next_sample += len;
+        self.outputs.push_back(VoiceActivityDetectorOutput {
+            probability,
+            samples: SamplesBuffer::new(1, self.sample_rate, samples),
+        });
+    }
+}
+
+impl futures_core::Stream for ScriptedVoiceActivity {
+    type Item = VoiceActivityDetectorOutput;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().outputs.pop_front())
+    }
+}
+
+impl<S: futures_core::Stream<Item = VoiceActivityDetectorOutput> + Unpin>
+    VoiceActivityRechunkerStreamV2<S>
+{
+    /// Drains a source that never waits, such as a script, and returns every chunk
+    pub fn collect_chunks(mut self) -> Vec<SamplesBuffer<f32>> {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut chunks = Vec::new();
+        loop {
+            match futures_core::Stream::poll_next(Pin::new(&mut self), &mut cx) {
+                Poll::Ready(Some(chunk)) => chunks.push(chunk),
+                Poll::Ready(None) => return chunks,
+                Poll::Pending => panic!("collect_chunks needs a source that is always ready"),
+            }
+        }
+    }
+}
+
+/// Sample positions making up a chunk, see `ScriptedVoiceActivity`
+pub fn sample_positions(chunk: &SamplesBuffer<f32>) -> Vec<usize> {
+    chunk.clone().map(|sample| sample as usize).collect()
+}
@@ -0,0 +1,461 @@
+use crate::audio::devices::{fetch_hidden_output_device, list_all_devices};
+use crate::config::agents::read_agent_configs;
+use crate::config::app_config::load_app_config;
+use crate::shortcuts::{agents_paused, run_shortcut_action, ShortcutAction};
+use crate::transcription::control::TranscriptionState;
+use crate::transcription::event::SESSION_STATE_EVENT_TYPES;
+use crate::util::error_handler::show_error;
+use log::error;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::image::Image;
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::utils::platform::resource_dir;
+use tauri::{async_runtime, App, AppHandle, Emitter, Listener, Manager, WebviewWindow, Wry};
+use tokio::sync::Mutex;
+
+const TRAY_ID: &str = "ollisten-tray";
+const ICON_LIGHT: &str = "ollisten-logo-circle-black.png";
+const ICON_DARK: &str = "ollisten-logo-circle-white.png";
+
+const TRAY_ACTION_REQUESTED_EVENT_TYPE: &str = "tray-action-requested";
+
+const MODE_ITEM_PREFIX: &str = "mode:";
+const AGENT_ITEM_PREFIX: &str = "agent:";
+
+/// Events from the client and the agent config watcher that change what the menu shows
+const REFRESH_EVENT_TYPES: &[&str] = &[
+    "agent-window-open",
+    "agent-window-closed",
+    "agents-pause-requested",
+    "app-config-changed",
+    "file-agent-created",
+    "file-agent-deleted",
+    "file-agent-modified",
+];
+
+// Menus are rebuilt one at a time so an older state never replaces a newer one
+static REFRESH_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Starting and stopping agents is up to the main window, which owns the agent windows
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrayActionRequestedEvent {
+    pub r#type: String,
+    // One of startMode, stopMode, startAgent or stopAgent
+    pub action: String,
+    // Mode id or agent name
+    pub name: String,
+}
+
+pub fn setup_tray(
+    app: &App,
+    is_dark_mode: bool,
+    open_main_window: fn(&AppHandle) -> Result<WebviewWindow, String>,
+) -> Result<(), String> {
+    let menu = base_items(app.handle())
+        .and_then(|items| {
+            Menu::with_items(
+                app,
+                &items
+                    .iter()
+                    .map(|item| item as &dyn IsMenuItem<Wry>)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map_err(|e| format!("Failed to create menu: {}", e))?;
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_tray_icon_event(move |tray, event| match event {
+            TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } => {
+                // in this example, let's show and focus the main window when the tray is clicked
+                let app = tray.app_handle();
+                if let Err(e) = open_main_window(app) {
+                    show_error(format!("Failed to open main window: {}", e), app.clone());
+                }
+            }
+            _ => {}
+        })
+        .icon(load_icon(app.handle(), is_dark_mode)?)
+        .build(app)
+        .map_err(|e| format!("Failed to create tray icon: {}", e))?;
+
+    // Listen for theme changes
+    let app_handle = app.handle().clone();
+    app.listen_any("tauri://theme-changed", move |event| {
+        let is_dark_mode = match event.payload() {
+            "true" | "dark" | "\"dark\"" => true,
+            "false" | "light" | "\"light\"" => false,
+            _ => return,
+        };
+        if let Err(e) = update_tray_icon(&app_handle, is_dark_mode) {
+            show_error(
+                format!("Failed to update tray icon: {}", e),
+                app_handle.clone(),
+            );
+        }
+    });
+
+    // Keep the menu in line with the session, agents and modes
+    for event_type in SESSION_STATE_EVENT_TYPES.iter().chain(REFRESH_EVENT_TYPES) {
+        let app_handle = app.handle().clone();
+        app.listen_any(*event_type, move |_| refresh_tray_menu(app_handle.clone()));
+    }
+    refresh_tray_menu(app.handle().clone());
+
+    Ok(())
+}
+
+/// Menu items not handled in main.rs
+pub fn on_menu_event(app_handle: &AppHandle, id: &str) {
+    let action = match id {
+        "toggle-transcription" => Some(ShortcutAction::ToggleTranscription),
+        "toggle-host-mute" => Some(ShortcutAction::ToggleHostMute),
+        "pause-agents" => Some(ShortcutAction::PauseAgents),
+        "bookmark" => Some(ShortcutAction::Bookmark),
+        _ => None,
+    };
+    if let Some(action) = action {
+        let app_handle = app_handle.clone();
+        async_runtime::spawn(async move {
+            if let Err(e) = run_shortcut_action(app_handle.clone(), action).await {
+                show_error(format!("Failed to {}: {}", action.label(), e), app_handle);
+            }
+        });
+        return;
+    }
+
+    let app_handle = app_handle.clone();
+    let id = id.to_string();
+    async_runtime::spawn(async move {
+        if let Err(e) = request_toggle(&app_handle, &id).await {
+            show_error(e, app_handle.clone());
+        }
+        // Check items flip on click, put them back until the agent windows actually change
+        refresh_tray_menu(app_handle);
+    });
+}
+
+/// Asks the main window to start a mode or agent that is not running, or stop it otherwise
+async fn request_toggle(app_handle: &AppHandle, id: &str) -> Result<(), String> {
+    let running_agents = running_agent_names(app_handle);
+    let (running, start_action, stop_action, name) =
+        if let Some(mode_id) = id.strip_prefix(MODE_ITEM_PREFIX) {
+            let agents = load_app_config()
+                .await?
+                .modes
+                .and_then(|mut modes| modes.remove(mode_id))
+                .map(|mode| mode.agents)
+                .ok_or_else(|| format!("Mode {} not found", mode_id))?;
+            (
+                is_mode_running(&agents, &running_agents),
+                "startMode",
+                "stopMode",
+                mode_id,
+            )
+        } else if let Some(agent_name) = id.strip_prefix(AGENT_ITEM_PREFIX) {
+            (
+                running_agents.contains(agent_name),
+                "startAgent",
+                "stopAgent",
+                agent_name,
+            )
+        } else {
+            error!("Menu item {} not handled", id);
+            return Ok(());
+        };
+
+    app_handle
+        .emit(
+            TRAY_ACTION_REQUESTED_EVENT_TYPE,
+            TrayActionRequestedEvent {
+                r#type: TRAY_ACTION_REQUESTED_EVENT_TYPE.to_string(),
+                action: if running { stop_action } else { start_action }.to_string(),
+                name: name.to_string(),
+            },
+        )
+        .map_err(|e| format!("Failed to emit event: {}", e))
+}
+
+/// Agents run in windows labeled agent-<name>
+fn running_agent_names(app_handle: &AppHandle) -> HashSet<String> {
+    app_handle
+        .webview_windows()
+        .keys()
+        .filter_map(|label| label.strip_prefix("agent-").map(str::to_string))
+        .collect()
+}
+
+fn is_mode_running(agents: &[String], running_agents: &HashSet<String>) -> bool {
+    !agents.is_empty() && agents.iter().all(|agent| running_agents.contains(agent))
+}
+
+/// Rebuilds the tray menu in the background
+pub fn refresh_tray_menu(app_handle: AppHandle) {
+    async_runtime::spawn(async move {
+        let _guard = REFRESH_LOCK.lock().await;
+        if let Err(e) = rebuild_tray_menu(&app_handle).await {
+            error!("Failed to rebuild tray menu: {}", e);
+        }
+    });
+}
+
+/// What the menu shows, gathered before any item is created
+struct TrayStatus {
+    // Model and device lines, None if transcription is not running
+    session_lines: Option<Vec<String>>,
+    host_muted: bool,
+    agents_paused: bool,
+    // Id, label, agents of the mode
+    modes: Vec<(String, String, Vec<String>)>,
+    agent_names: Vec<String>,
+    running_agents: HashSet<String>,
+}
+
+async fn read_tray_status(app_handle: &AppHandle) -> TrayStatus {
+    let hidden_device_uid = fetch_hidden_output_device()
+        .ok()
+        .flatten()
+        .map(|device| device.uid);
+    let (session_lines, host_muted) = {
+        let state = app_handle.state::<TranscriptionState>();
+        let session = state.session.lock().await;
+        match session.as_ref().filter(|session| session.is_running()) {
+            None => (None, false),
+            Some(session) => {
+                let mut lines = vec![format!("Transcribing with {:?}", session.model_type)];
+                let devices = list_all_devices().unwrap_or_default();
+                let mut device_ids: Vec<&i32> = session.device_uids.keys().collect();
+                device_ids.sort();
+                for device_id in device_ids {
+                    let uid = &session.device_uids[device_id];
+                    let name = devices
+                        .iter()
+                        .find(|device| device.uid == *uid)
+                        .map_or(uid.as_str(), |device| device.name.as_str());
+                    lines.push(
+                        match (
+                            session.paused_devices.contains(device_id),
+                            session.lost_devices.contains(device_id),
+                        ) {
+                            (true, _) => format!("{} (muted)", name),
+                            (false, true) => format!("{} (disconnected)", name),
+                            (false, false) => name.to_string(),
+                        },
+                    );
+                }
+                let host_muted = session
+                    .device_uids
+                    .iter()
+                    .filter(|(_, uid)| Some(*uid) != hidden_device_uid.as_ref())
+                    .all(|(device_id, _)| session.paused_devices.contains(device_id));
+                (Some(lines), host_muted)
+            }
+        }
+    };
+
+    // A broken ollisten.yaml or agent file only hides the modes or agents, the transcription
+    // and pause items keep working
+    let app_modes = load_app_config()
+        .await
+        .inspect_err(|e| error!("Failed to list modes for the tray: {}", e))
+        .ok()
+        .and_then(|app_config| app_config.modes)
+        .unwrap_or_default();
+    let mut modes: Vec<(String, String, Vec<String>)> = app_modes
+        .into_iter()
+        .map(|(mode_id, mode)| (mode_id, mode.label, mode.agents))
+        .collect();
+    modes.sort_by(|(_, a, _), (_, b, _)| a.cmp(b));
+    let agent_configs = read_agent_configs()
+        .inspect_err(|e| error!("Failed to list agents for the tray: {}", e))
+        .unwrap_or_default();
+    let mut agent_names: Vec<String> = agent_configs
+        .into_iter()
+        .map(|agent_config| agent_config.name)
+        .collect();
+    agent_names.sort();
+
+    TrayStatus {
+        session_lines,
+        host_muted,
+        agents_paused: agents_paused(),
+        modes,
+        agent_names,
+        running_agents: running_agent_names(app_handle),
+    }
+}
+
+async fn rebuild_tray_menu(app_handle: &AppHandle) -> Result<(), String> {
+    let tray = app_handle
+        .tray_by_id(TRAY_ID)
+        .ok_or_else(|| "Tray icon not found".to_string())?;
+    let status = read_tray_status(app_handle).await;
+    let menu =
+        build_menu(app_handle, &status).map_err(|e| format!("Failed to create menu: {}", e))?;
+    tray.set_menu(Some(menu))
+        .map_err(|e| format!("Failed to set tray menu: {}", e))
+}
+
+fn build_menu(app_handle: &AppHandle, status: &TrayStatus) -> tauri::Result<Menu<Wry>> {
+    let running = status.session_lines.is_some();
+    let mut items: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::new();
+
+    // Session status, which model and devices are being transcribed
+    match &status.session_lines {
+        None => items.push(Box::new(MenuItem::new(
+            app_handle,
+            "Transcription stopped",
+            false,
+            None::<&str>,
+        )?)),
+        Some(lines) => {
+            for line in lines {
+                items.push(Box::new(MenuItem::new(
+                    app_handle,
+                    line,
+                    false,
+                    None::<&str>,
+                )?));
+            }
+        }
+    }
+    items.push(Box::new(PredefinedMenuItem::separator(app_handle)?));
+
+    // Quick actions
+    items.push(Box::new(MenuItem::with_id(
+        app_handle,
+        "toggle-transcription",
+        if running {
+            "Stop transcription"
+        } else {
+            "Start transcription"
+        },
+        true,
+        None::<&str>,
+    )?));
+    items.push(Box::new(MenuItem::with_id(
+        app_handle,
+        "toggle-host-mute",
+        if status.host_muted {
+            "Unmute microphone"
+        } else {
+            "Mute microphone"
+        },
+        running,
+        None::<&str>,
+    )?));
+    items.push(Box::new(MenuItem::with_id(
+        app_handle,
+        "pause-agents",
+        if status.agents_paused {
+            "Resume agents"
+        } else {
+            "Pause agents"
+        },
+        true,
+        None::<&str>,
+    )?));
+    items.push(Box::new(MenuItem::with_id(
+        app_handle,
+        "bookmark",
+        "Bookmark",
+        running,
+        None::<&str>,
+    )?));
+    items.push(Box::new(PredefinedMenuItem::separator(app_handle)?));
+
+    // Modes and agents with their running state, clicking one starts or stops it
+    let mode_items = status
+        .modes
+        .iter()
+        .map(|(mode_id, label, agents)| {
+            CheckMenuItem::with_id(
+                app_handle,
+                format!("{}{}", MODE_ITEM_PREFIX, mode_id),
+                if label.is_empty() {
+                    "Unnamed mode"
+                } else {
+                    label
+                },
+                !agents.is_empty(),
+                is_mode_running(agents, &status.running_agents),
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let agent_items = status
+        .agent_names
+        .iter()
+        .map(|agent_name| {
+            CheckMenuItem::with_id(
+                app_handle,
+                format!("{}{}", AGENT_ITEM_PREFIX, agent_name),
+                agent_name,
+                true,
+                status.running_agents.contains(agent_name),
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    for (title, submenu_items) in [("Modes", &mode_items), ("Agents", &agent_items)] {
+        items.push(Box::new(Submenu::with_items(
+            app_handle,
+            title,
+            !submenu_items.is_empty(),
+            &submenu_items
+                .iter()
+                .map(|item| item as &dyn IsMenuItem<Wry>)
+                .collect::<Vec<_>>(),
+        )?));
+    }
+    items.push(Box::new(PredefinedMenuItem::separator(app_handle)?));
+
+    for item in base_items(app_handle)? {
+        items.push(Box::new(item));
+    }
+
+    Menu::with_items(
+        app_handle,
+        &items.iter().map(|item| item.as_ref()).collect::<Vec<_>>(),
+    )
+}
+
+/// Items that are always there, handled in main.rs
+fn base_items(app_handle: &AppHandle) -> tauri::Result<Vec<MenuItem<Wry>>> {
+    Ok(vec![
+        MenuItem::with_id(app_handle, "main", "Open", true, None::<&str>)?,
+        MenuItem::with_id(app_handle, "exit", "Exit", true, None::<&str>)?,
+    ])
+}
+
+fn load_icon(app: &AppHandle, is_dark_mode: bool) -> Result<Image<'static>, String> {
+    let resource_path = resource_dir(app.package_info(), &app.env())
+        .map_err(|e| format!("Failed to locate resource directory: {}", e))?;
+    let icon_path = resource_path
+        .join("resources")
+        .join("icons")
+        .join(is_dark_mode.then_some(ICON_DARK).unwrap_or(ICON_LIGHT));
+    Image::from_path(icon_path).map_err(|e| format!("Failed to load icon: {}", e))
+}
+
+fn update_tray_icon(app: &AppHandle, is_dark_mode: bool) -> Result<(), String> {
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or_else(|| "Tray icon not found".to_string())?;
+
+    let icon = load_icon(app, is_dark_mode)?;
+
+    tray.set_icon(Some(icon))
+        .map_err(|e| format!("Failed to set tray icon: {}", e))?;
+
+    Ok(())
+}
@@ -1,9 +1,10 @@
 import InputDeviceSelect from "./InputDeviceSelect.tsx";
 import OutputDeviceSelect from "./OutputDeviceSelect.tsx";
 import TranscriptionModelSelect from "./TranscriptionModelSelect.tsx";
+import TranscriptionBackendSelect from "./TranscriptionBackendSelect.tsx";
 import LlmModelSelect from "./LlmModelSelect.tsx";
 import Menu, {Tab} from "./Menu.tsx";
-import {useState} from "react";
+import {useEffect, useState} from "react";
 import AgentList from "./AgentList.tsx";
 import {InstallDriverNotice} from "./InstallDriverNotice.tsx";
 import {useAppConfig} from "./util/useAppConfig.ts";
@@ -16,6 +17,11 @@ import Note from "./Note.tsx";
 import {Box, Link} from "@mui/material";
 import DebugButton from "./DebugButton.tsx";
 import {invoke} from "@tauri-apps/api/core";
+import {Events} from "./system/events.ts";
+import {ShortcutPressedEvent} from "./system/shortcuts.ts";
+import {Transcription} from "./system/transcription.ts";
+import {TrayActionRequestedEvent} from "./system/tray.ts";
+import useModes from "./useModes.ts";
 
 const LauncherTabName = 'Launch';
 
@@ -23,6 +29,41 @@ export default function App() {
     const {loading} = useAppConfig();
     const [activePage, setActivePage] = useState<string>(LauncherTabName);
 
+    // Transcription is started from the main window only, it owns the device and model selection
+    useEffect(() => {
+        return Events.get().subscribe('shortcut-pressed', (event: ShortcutPressedEvent) => {
+            if (event.action !== 'toggleTranscription') {
+                return;
+            }
+            if (Transcription.get().isRunning()) {
+                Transcription.get().stopTranscription();
+            } else {
+                Transcription.get().startTranscription();
+            }
+        });
+    }, []);
+
+    // Modes and agents toggled from the tray menu
+    const {startMode, stopMode, startAgent, stopAgent} = useModes();
+    useEffect(() => {
+        return Events.get().subscribe('tray-action-requested', (event: TrayActionRequestedEvent) => {
+            switch (event.action) {
+                case 'startMode':
+                    startMode(event.name);
+                    break;
+                case 'stopMode':
+                    stopMode(event.name);
+                    break;
+                case 'startAgent':
+                    startAgent(event.name);
+                    break;
+                case 'stopAgent':
+                    stopAgent(event.name);
+                    break;
+            }
+        });
+    }, [startMode, stopMode, startAgent, stopAgent]);
+
     if (loading) {
         return null;
     }
@@ -68,6 +109,9 @@ export default function App() {
                     <OutputDeviceSelect/>
                     <Note description='Choose a Transcription model to convert audio into text.'/>
                     <TranscriptionModelSelect/>
+                    <Note
+                        description='Choose the engine that transcribes, the model above is used by the built in Whisper. Vocabulary terms and what was said before are given to whisper.cpp and servers as a prompt, the built in Whisper cannot be prompted and only fixes the spelling of vocabulary terms.'/>
+                    <TranscriptionBackendSelect/>
                 </Tab>
                 <Tab label='Llm' icon={<EngineIcon/>}>
                     <InstallStartOllamaNotice/>
@@ -16,6 +16,7 @@ import PinIcon from "./icon/PinIcon.tsx";
 import {openAgentEdit} from "./agentEditWindow.ts";
 import {useForceRender} from "./util/useForceRender.ts";
 import {currentWindowCloseSafely} from "./util/windowUtil.ts";
+import {AgentsPauseRequestedEvent, ShortcutPressedEvent} from "./system/shortcuts.ts";
 
 Transcription.get(); // Required to subscribe to transcription events
 
@@ -99,6 +100,29 @@ export default function AppAgent() {
         Prompter.get().start(true);
     }, [agentConfig]);
 
+    useEffect(() => {
+        return Events.get().subscribe(['shortcut-pressed', 'agents-pause-requested'], (
+            event: ShortcutPressedEvent | AgentsPauseRequestedEvent
+        ) => {
+            switch (event.type) {
+                case 'shortcut-pressed':
+                    // Agents with a trigger are fired by Rust if the trigger answers the shortcut
+                    if (event.action === 'triggerAgents' && !Prompter.get().isTriggered()) {
+                        Prompter.get().triggerNow()
+                            .catch(e => Events.get().showError(`Failed to trigger agent: ${e}`));
+                    }
+                    break;
+                case 'agents-pause-requested':
+                    if (event.paused) {
+                        Prompter.get().pause();
+                    } else {
+                        Prompter.get().resume();
+                    }
+                    break;
+            }
+        });
+    }, []);
+
     if (loading) {
         return null;
     }
@@ -1,5 +1,5 @@
 import {Transcription} from "./system/transcription.ts";
-import {AgentConfig, AgentManager} from "./system/agentManager.ts";
+import {AgentConfig, AgentManager, PromptTokenEstimate} from "./system/agentManager.ts";
 import {useCallback, useEffect, useMemo, useRef, useState} from "react";
 import {
     Box,
@@ -58,6 +58,8 @@ export default function AppAgentEdit() {
     const currentAgentConfig: AgentConfig = useMemo(() => ({
         name,
         agent: {
+            // Keep settings that are only edited in the file, such as the vocabulary
+            ...initialAgentConfig.agent,
             prompt,
             intervalInSec,
             transcriptionHistoryMaxChars,
@@ -132,6 +134,17 @@ export default function AppAgentEdit() {
         }
     }, [transcriptionHistory, transcription]);
 
+    // Estimated once typing pauses, the context length is looked up once per model
+    const [promptTokenEstimate, setPromptTokenEstimate] = useState<PromptTokenEstimate | null>(null);
+    useEffect(() => {
+        const timeout = setTimeout(() => {
+            invoke<PromptTokenEstimate>('estimate_prompt_tokens', {agent: currentAgentConfig.agent})
+                .then(setPromptTokenEstimate)
+                .catch(e => console.error(`Failed to estimate prompt tokens: ${e}`));
+        }, 500);
+        return () => clearTimeout(timeout);
+    }, [currentAgentConfig]);
+
     // Configure prompter
     useEffect(() => {
         Prompter.get().configureAgent(currentAgentConfig);
@@ -235,13 +248,23 @@ export default function AppAgentEdit() {
                         <pre>{
                             'transcription: {\n' +
                             '  all: "...",\n' +
-                            '  latest: "..."\n' +
+                            '  latest: "...",\n' +
+                            '  sinceBookmark: "..."\n' +
+                            '},\n' +
+                            'meetings: {\n' +
+                            '  related: "..."\n' +
                             '},\n' +
                             'answer: {\n' +
                             '  previous: {\n' +
                             '    text: "answer"\n' +
                             '    json: { ... }\n' +
                             '  },\n' +
+                            '},\n' +
+                            'inputs: {\n' +
+                            '  <agent>: {\n' +
+                            '    text: "answer"\n' +
+                            '    json: { ... }\n' +
+                            '  },\n' +
                             '}'
                         }</pre>
                         <Typography variant='body1'>Previous answer JSON only available if Structured output is
@@ -257,6 +280,20 @@ export default function AppAgentEdit() {
                             <li><code>{'{{ json answer.previous.json }}'}</code> Previous answer as JSON string</li>
                             <li><code>{'{{ transcription.latest }}'}</code> Most recent transcription</li>
                             <li><code>{'{{ transcription.all }}'}</code> All stored transcription</li>
+                            <li><code>{'{{ transcription.sinceBookmark }}'}</code> Transcription since the last bookmark,
+                                e.g. to summarise the current topic
+                            </li>
+                            <li><code>{'{{ meetings.related }}'}</code> What was said in earlier meetings closest to the
+                                latest transcription, e.g. to answer "what did we decide last week?"
+                            </li>
+                            <li><code>{'{{ inputs.questions.text }}'}</code> Latest answer of the agent named questions,
+                                listed under <code>inputs.agents</code> in the agent file
+                            </li>
+                            <li><code>{'{{ transcription.latest }}'}</code> with <code>trigger</code> in the agent file
+                                is what was said since the agent last fired, on <code>keywords</code>,
+                                a <code>pattern</code>, <code>questionToHost</code>, <code>silenceSec</code> or
+                                the <code>hotkey</code>
+                            </li>
                         </ul>
                     </Collapse>
                     <Menu>
@@ -287,6 +324,13 @@ export default function AppAgentEdit() {
                             />
                         </Tab>
                     </Menu>
+                    {promptTokenEstimate && (
+                        <Typography variant='body2' color={promptTokenEstimate.warning ? 'warning.main' : 'text.secondary'}>
+                            {promptTokenEstimate.warning || (promptTokenEstimate.contextLength
+                                ? `~${promptTokenEstimate.templateTokens} of ${promptTokenEstimate.contextLength} tokens of ${promptTokenEstimate.modelName} before transcription`
+                                : `~${promptTokenEstimate.templateTokens} tokens before transcription`)}
+                        </Typography>
+                    )}
 
                 </Box>
                 <Box sx={SectionStyle} data-tauri-drag-region="">
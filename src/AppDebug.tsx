@@ -24,12 +24,20 @@ type LlmRecord = Omit<LlmRequestEvent, 'type'> & Omit<Partial<LlmResponseEvent>,
     responded?: Date,
 };
 export const AllEventTypes = [
+    'TranscriptionBookmarked',
     'TranscriptionData',
+    'TranscriptionDevicePaused',
+    'TranscriptionDeviceResumed',
     'TranscriptionDownloadProgress',
     'TranscriptionLoadingProgress',
+    'TranscriptionNote',
+    'TranscriptionReplayFinished',
+    'TranscriptionReplayStarted',
     'TranscriptionStarted',
     'TranscriptionStopped',
+    'agent-triggered',
     'agent-window-closed',
+    'agents-pause-requested',
     'app-config-changed',
     'device-input-option-selected',
     'device-input-options-updated',
@@ -41,13 +49,17 @@ export const AllEventTypes = [
     'llm-model-options-updated',
     'llm-request',
     'llm-response',
+    'meeting-summary-ready',
+    'noise-suppression-level',
     'ollama-is-stopped',
     'ollama-no-models',
     'ollama-not-installed',
     'prompter-status-changed',
+    'shortcut-pressed',
     'status-change',
     'transcription-model-option-selected',
     'transcription-model-options-updated',
+    'tray-action-requested',
 ];
 
 export default function AppDebug() {
@@ -137,7 +149,7 @@ export default function AppDebug() {
                                       key={`${event.received}`}>
                                 <div>
                                     <Typography variant="h6">
-                                        {Transcription.get().deviceIdToSource(event.deviceId) === DeviceSource.Host ? 'Host' : 'Guest'}
+                                        {Transcription.get().deviceUidToSource(event.deviceUid) === DeviceSource.Host ? 'Host' : 'Guest'}
                                         {' - '}
                                         (Accuracy {(event.confidence * 100) | 0}%)
                                     </Typography>
@@ -18,8 +18,8 @@ export default function InputDeviceSelect() {
     const refreshOptions = useCallback(() => Transcription.get()
         .fetchInputDevices()
         .catch(e => Events.get().showError(`Failed to refresh input devices: ${e}`)), [])
-    const [deviceId, setDeviceId] = useState<string | null>(() => mapDeviceIdToString(Transcription.get()
-        .getInputDeviceId()));
+    const [deviceUid, setDeviceUid] = useState<string | null>(() => Transcription.get()
+        .getInputDeviceUid());
 
     useEffect(() => {
         return Events.get().subscribe([
@@ -33,7 +33,7 @@ export default function InputDeviceSelect() {
                     setOptions(event.options.map(mapDeviceToOption));
                     break;
                 case 'device-input-option-selected':
-                    setDeviceId(mapDeviceIdToString(event.option));
+                    setDeviceUid(event.option);
                     break;
                 default:
                     console.error(`Unexpected event: ${event}`);
@@ -49,10 +49,10 @@ export default function InputDeviceSelect() {
                     margin: '1rem',
                 }}
                 label='Input device'
-                value={deviceId}
+                value={deviceUid}
                 options={options}
                 onSelect={useCallback((newValue: string) => {
-                    Transcription.get().selectInputDeviceId(parseInt(newValue));
+                    Transcription.get().selectInputDeviceUid(newValue);
                 }, [])}
             />
             <Box flex='0 1 auto' display='flex' alignItems='center' justifyContent='center' marginRight={2}>
@@ -64,11 +64,7 @@ export default function InputDeviceSelect() {
     );
 }
 
-const mapDeviceIdToString = (deviceId: number | null): string | null => (
-    deviceId == null ? null : `${deviceId}`
-);
-
 const mapDeviceToOption = (device: DeviceOption): Option => ({
     label: device.name,
-    value: `${device.id}`,
+    value: device.uid,
 });
@@ -0,0 +1,97 @@
+import {useCallback, useState} from "react";
+import Select, {Option} from "./Select.tsx";
+import {SpeechToTextBackend, Transcription} from "./system/transcription.ts";
+import {setAppConfig, setAppConfigDebounced, useAppConfig} from "./util/useAppConfig.ts";
+import {Events} from "./system/events.ts";
+import {Box, TextField} from "@mui/material";
+
+const BackendOptions: Option[] = [
+    {label: 'Whisper (built in)', value: 'Whisper'},
+    {label: 'whisper.cpp model file', value: 'WhisperCpp'},
+    {label: 'OpenAI compatible server', value: 'ExternalHttp'},
+];
+
+export default function TranscriptionBackendSelect() {
+    const {appConfig} = useAppConfig();
+    // Edited locally, the config is only written once typing settles
+    const [backend, setBackend] = useState<SpeechToTextBackend>(() => appConfig.transcription?.backend || {type: 'Whisper'});
+
+    const saveBackend = useCallback((newBackend: SpeechToTextBackend) => setAppConfig(c => c.transcription = {
+        ...c.transcription,
+        backend: newBackend,
+    }), []);
+
+    const onSelect = useCallback((newValue: string) => {
+        let newBackend: SpeechToTextBackend;
+        switch (newValue) {
+            case 'WhisperCpp':
+                newBackend = {type: 'WhisperCpp', modelPath: ''};
+                break;
+            case 'ExternalHttp':
+                newBackend = {type: 'ExternalHttp', baseUrl: 'http://localhost:8000', model: 'whisper-1', apiKey: null};
+                break;
+            default:
+                newBackend = {type: 'Whisper'};
+                break;
+        }
+        setBackend(newBackend);
+        saveBackend(newBackend)
+            .then(() => Transcription.get().restartTranscriptionIfRunning())
+            .catch(e => Events.get().showError(`Failed to save transcription engine: ${e}`));
+    }, [saveBackend]);
+
+    // Applied the next time transcription starts, restarting on every key press would reload the model
+    const onEdit = (newBackend: SpeechToTextBackend) => {
+        setBackend(newBackend);
+        setAppConfigDebounced(c => c.transcription = {
+            ...c.transcription,
+            backend: newBackend,
+        }).catch(e => Events.get().showError(`Failed to save transcription engine: ${e}`));
+    };
+
+    return (
+        <Box display="flex" flexDirection="column">
+            <Select
+                sx={{
+                    margin: '1rem',
+                }}
+                label='Transcription Engine'
+                value={backend.type}
+                options={BackendOptions}
+                onSelect={onSelect}
+            />
+            {backend.type === 'WhisperCpp' && (
+                <TextField
+                    sx={{marginX: '1rem', marginBottom: '1rem'}}
+                    label='Model file'
+                    helperText='GGML model, relative paths are in ~/.ollisten/models'
+                    value={backend.modelPath}
+                    onChange={e => onEdit({...backend, modelPath: e.target.value})}
+                />
+            )}
+            {backend.type === 'ExternalHttp' && (
+                <>
+                    <TextField
+                        sx={{marginX: '1rem', marginBottom: '1rem'}}
+                        label='Server URL'
+                        value={backend.baseUrl}
+                        onChange={e => onEdit({...backend, baseUrl: e.target.value})}
+                    />
+                    <TextField
+                        sx={{marginX: '1rem', marginBottom: '1rem'}}
+                        label='Model'
+                        value={backend.model}
+                        onChange={e => onEdit({...backend, model: e.target.value})}
+                    />
+                    <TextField
+                        sx={{marginX: '1rem', marginBottom: '1rem'}}
+                        label='API key'
+                        type='password'
+                        value={backend.apiKey || ''}
+                        onChange={e => onEdit({...backend, apiKey: e.target.value || null})}
+                    />
+                </>
+            )}
+        </Box>
+    );
+}
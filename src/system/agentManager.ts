@@ -4,10 +4,37 @@ import {Events} from "./events.ts";
 import {getAppConfig} from "../util/useAppConfig.ts";
 import {currentMonitor, Window} from "@tauri-apps/api/window";
 import {windowCloseSafely} from "../util/windowUtil.ts";
+import {Vocabulary} from "./transcription.ts";
 
 export interface Agent {
     intervalInSec?: number;
     transcriptionHistoryMaxChars: number | null;
+    // Keeps transcription.all within a budget, replaces transcriptionHistoryMaxChars when set
+    history?: null | {
+        strategy: HistoryStrategy;
+        // Most tokens of history in the prompt, half of it is kept for recent transcript
+        tokenBudget: number;
+    };
+    // Answers of other agents, available as {{inputs.<name>.text}} and {{inputs.<name>.json}}
+    inputs?: null | {
+        // False to only answer the other agents, without the transcript
+        transcription?: boolean;
+        agents: string[];
+    };
+    // Answers only when one of these fires instead of on every bit of transcription
+    trigger?: null | {
+        // Fires when any of them is said, ignoring case
+        keywords?: string[];
+        // Regex, ignoring case
+        pattern?: string | null;
+        // Fires when anyone but the Host asks a question
+        questionToHost?: boolean;
+        silenceSec?: number | null;
+        // Fires on the trigger agents shortcut
+        hotkey?: boolean;
+        // Least time between two invocations, 10 seconds by default
+        cooldownSec?: number | null;
+    };
     prompt: string;
     structuredOutput: null | {
         // JSON schema required for LLM output
@@ -15,6 +42,27 @@ export interface Agent {
         // Mustache template to map LLM output JSON to user-facing output
         mapper: string;
     };
+    // Added to the transcription vocabulary while this agent runs
+    vocabulary?: Vocabulary | null;
+    // Runs once the meeting ended instead of during it, the prompt answers with a MeetingSummary
+    finalizer?: null | {
+        // Condenses each part of meetings too long for one request, {{chunk.index}} of {{chunk.count}}
+        mapPrompt?: string | null;
+        chunkMaxChars?: number | null;
+    };
+}
+
+// Truncate drops the oldest transcript, summaries condense it with the LLM
+export type HistoryStrategy = 'truncate' | 'rollingSummary' | 'hierarchicalSummary';
+
+// How the prompt of an agent fits the context of the configured model
+export interface PromptTokenEstimate {
+    modelName: string | null;
+    // The prompt without any transcription or answers
+    templateTokens: number;
+    contextLength: number | null;
+    // Set when the template alone is close to the context length
+    warning: string | null;
 }
 
 export interface AgentConfig {
@@ -120,6 +168,9 @@ export class AgentManager {
         if (this.agentWindows[agentConfig.name]) {
             return;
         }
+        if (agentConfig.agent.finalizer) {
+            return; // Runs in the background once transcription stops
+        }
 
         const windowLabel = this.agentNameToWindowName(agentConfig.name);
         const windowProps = getAppConfig().windowProps?.[windowLabel];
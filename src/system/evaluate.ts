@@ -0,0 +1,34 @@
+import {invoke} from "@tauri-apps/api/core";
+
+// The model the app is set up with if not given
+export type LlmBackend = { type: 'configured' }
+    | { type: 'ollama', modelName: string }
+    | { type: 'mock', responses: string[] }; // In order, the last one is repeated
+
+export type Exchange = {
+    atMs: number; // Time into the fixture the agent was invoked at
+    transcriptionHistory: string;
+    transcriptionLatest: string;
+    prompt: string;
+    answer: string | null;
+    answerJson: object | null; // Provided if using structured output
+    error: string | null;
+};
+
+export type AssertionResult = {
+    assertion: { type: 'contains' | 'jsonPathEquals' | 'schemaValid' } & object;
+    passed: boolean;
+    message: string | null; // Expected and actual value of a failed assertion
+};
+
+export type EvaluationReport = {
+    agentName: string;
+    exchanges: Exchange[];
+    assertions: AssertionResult[];
+    passed: boolean;
+};
+
+// Runs an agent over the fixture of its test, <agent>.test.yaml next to it, and checks the answers
+export const evaluateAgent = async (agentName: string, llm?: LlmBackend): Promise<EvaluationReport> => {
+    return await invoke<EvaluationReport>('evaluate_agent', {agentName, llm: llm || null});
+};
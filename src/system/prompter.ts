@@ -1,9 +1,15 @@
 import Handlebars from "handlebars";
-import {AgentConfig, FileChangeEvent} from "./agentManager.ts";
+import {invoke} from "@tauri-apps/api/core";
+import {Agent, AgentConfig, FileChangeEvent} from "./agentManager.ts";
 import {Llm} from "./llm.ts";
 import debounce, {DebouncedFunction} from "../util/debounce.ts";
-import {DeviceSource, Transcription, TranscriptionDataEvent} from "./transcription.ts";
+import {
+    BookmarkedEvent,
+    NoteEvent,
+    TranscriptionDataEvent
+} from "./transcription.ts";
 import {Events, Unsubscribe} from "./events.ts";
+import {semanticSearch} from "./search.ts";
 import {currentWindowCloseSafely} from "../util/windowUtil.ts";
 
 export enum PrompterStatus {
@@ -35,7 +41,23 @@ export type LlmResponseEvent = {
     answer: string;
     answerJson: object | null; // Provided if using structured output
 }
+export type TriggerReason = 'keyword' | 'pattern' | 'questionToHost' | 'silence' | 'hotkey';
+export type AgentTriggeredEvent = {
+    type: 'agent-triggered';
+    agentName: string;
+    reason: TriggerReason;
+}
 type DebouncedInvoke = DebouncedFunction<[], void>;
+type AgentOutputInput = {
+    text: string;
+    json: object | null;
+}
+// Kept between invocations so only transcript that was not summarized yet is summarized
+type CondensedHistory = {
+    summaries: { level: number; text: string }[];
+    summarizedLines: number;
+    text: string;
+}
 
 // Add handlebar helper json that will JSON.stringify
 const HandlebarHelpers: { [name: string]: Function } = {
@@ -50,11 +72,26 @@ export class Prompter {
     private transcriptionUnsubscribe: Unsubscribe | null = null;
     private transcriptionHistory: string[] = [];
     private transcriptionLatest: string[] = [];
+    // Where in the history the last bookmark was added
+    private bookmarkIndex: number = 0;
     private previousAnswer: string | null = null;
     private previousAnswerJson: object | null = null;
     private isPaused: boolean = false;
     private agentName: string | null = null;
+    private agent: Agent | null = null;
+    private condensedHistory: CondensedHistory | null = null;
+    private condenseHistoryErrorShown: boolean = false;
+    // Latest answers of the agents this one takes as inputs
+    private inputs: { [agentName: string]: AgentOutputInput } = {};
+    // An upstream agent answered since the last invocation
+    private inputsChanged: boolean = false;
+    private listensToTranscription: boolean = true;
+    // Checked in Rust as transcription comes in, which asks this window to answer when it fires
+    private hasTrigger: boolean = false;
     private template: HandlebarsTemplateDelegate | null = null;
+    // Looking up earlier meetings costs an embedding per invocation, only done for prompts that use them
+    private usesRelatedMeetings: boolean = false;
+    private relatedMeetingsErrorShown: boolean = false;
     private structuredOutputSchema: string | null = null;
     private structuredOutputMapperTemplate: HandlebarsTemplateDelegate | null = null;
     private intervalInSec: number | null = null;
@@ -93,30 +130,53 @@ export class Prompter {
             };
         }
 
-        const eventsToListen: Array<'TranscriptionData' | 'file-agent-created' | 'file-agent-deleted' | 'file-agent-modified'> = ['TranscriptionData'];
+        const eventsToListen: Array<'TranscriptionData' | 'TranscriptionBookmarked' | 'TranscriptionNote' | 'llm-response' | 'agent-triggered' | 'file-agent-created' | 'file-agent-deleted' | 'file-agent-modified'> = ['TranscriptionData', 'TranscriptionBookmarked', 'TranscriptionNote', 'llm-response', 'agent-triggered'];
         if (!!watchFileChanges && !!this.agentName) {
             eventsToListen.push('file-agent-created', 'file-agent-deleted', 'file-agent-modified')
         }
         this.transcriptionUnsubscribe = Events.get().subscribe(
             eventsToListen, (
-                event: TranscriptionDataEvent | FileChangeEvent
+                event: TranscriptionDataEvent | BookmarkedEvent | NoteEvent | LlmResponseEvent | AgentTriggeredEvent | FileChangeEvent
             ) => {
                 switch (event.type) {
                     case 'TranscriptionData':
-                        if (this.isPaused || !this.debouncedInvoke) return;
-                        let transcriptionStr = event.text;
-                        switch (Transcription.get().deviceIdToSource(event.deviceId)) {
-                            case DeviceSource.Guest:
-                                transcriptionStr = `Guest: ${transcriptionStr}`;
-                                break;
-                            case DeviceSource.Host:
-                                transcriptionStr = `Host: ${transcriptionStr}`;
-                                break;
-                        }
+                        if (this.isPaused || !this.debouncedInvoke || !this.listensToTranscription) return;
+                        // Host or Guest live, whoever was named in an imported session on replay
+                        const transcriptionStr = event.speaker ? `${event.speaker}: ${event.text}` : event.text;
                         this.transcriptionHistory.push(transcriptionStr);
                         this.transcriptionLatest.push(transcriptionStr);
+                        // Kept until the trigger fires
+                        if (this.hasTrigger) return;
                         this.debouncedInvoke().catch(e => Events.get().showError(`Failed to invoke: ${e}`));
                         break;
+                    case 'TranscriptionBookmarked':
+                        if (!this.listensToTranscription) return;
+                        const bookmarkStr = event.label ? `--- Bookmark: ${event.label} ---` : '--- Bookmark ---';
+                        this.bookmarkIndex = this.transcriptionHistory.length;
+                        this.transcriptionHistory.push(bookmarkStr);
+                        this.transcriptionLatest.push(bookmarkStr);
+                        break;
+                    case 'TranscriptionNote':
+                        if (!this.listensToTranscription) return;
+                        const noteStr = `Note: ${event.text}`;
+                        this.transcriptionHistory.push(noteStr);
+                        this.transcriptionLatest.push(noteStr);
+                        break;
+                    case 'llm-response':
+                        // Answers of other agents invoke this one like transcription does
+                        if (this.isPaused || !this.debouncedInvoke || !(event.agentName in this.inputs)) return;
+                        this.inputs[event.agentName] = {
+                            text: event.answer,
+                            json: event.answerJson,
+                        };
+                        this.inputsChanged = true;
+                        if (this.hasTrigger) return;
+                        this.debouncedInvoke().catch(e => Events.get().showError(`Failed to invoke: ${e}`));
+                        break;
+                    case 'agent-triggered':
+                        if (this.isPaused || event.agentName !== this.agentName) return;
+                        this.invokeLatest().catch(e => Events.get().showError(`Failed to invoke: ${e}`));
+                        break;
                     case 'file-agent-deleted':
                         if (event.name === this.agentName) {
                             currentWindowCloseSafely();
@@ -134,12 +194,17 @@ export class Prompter {
                 }
             });
 
+        this.watchTrigger();
         this.sendStatusEvent(); // Started
 
         return () => {
             this.transcriptionUnsubscribe?.();
             this.transcriptionUnsubscribe = null;
             this.debouncedInvoke?.cancel();
+            if (this.hasTrigger && this.agentName) {
+                invoke('unwatch_agent_trigger', {agentName: this.agentName})
+                    .catch(e => console.error(`Failed to unwatch agent trigger: ${e}`));
+            }
             this.transcriptionHistory.push(this.transcriptionLatest.join("\n"));
             this.transcriptionLatest = [];
             this.sendStatusEvent(); // Stopped
@@ -158,7 +223,20 @@ export class Prompter {
 
     public configureAgent(agentConfig: AgentConfig) {
         this.agentName = agentConfig.name;
+        this.agent = agentConfig.agent;
+        // Upstream agents that didn't answer yet are blank, like a history before anything was said
+        this.inputs = Object.fromEntries((agentConfig.agent.inputs?.agents || []).map(agentName => [
+            agentName,
+            this.inputs[agentName] || {text: '', json: null},
+        ]));
+        this.listensToTranscription = agentConfig.agent.inputs?.transcription !== false;
+        const hadTrigger = this.hasTrigger;
+        this.hasTrigger = !!agentConfig.agent.trigger;
+        if (this.transcriptionUnsubscribe && (hadTrigger || this.hasTrigger)) {
+            this.watchTrigger();
+        }
         this.template = Handlebars.compile(agentConfig.agent.prompt);
+        this.usesRelatedMeetings = agentConfig.agent.prompt.includes('meetings.related');
         if (agentConfig.agent.structuredOutput) {
             this.structuredOutputSchema = agentConfig.agent.structuredOutput.schema;
             this.structuredOutputMapperTemplate = Handlebars.compile(agentConfig.agent.structuredOutput.mapper, {});
@@ -169,24 +247,105 @@ export class Prompter {
         const intervalInSec = Math.max(1, agentConfig.agent.intervalInSec || 3);
         if (intervalInSec !== this.intervalInSec || !this.debouncedInvoke) {
             this.intervalInSec = intervalInSec;
-            this.debouncedInvoke = debounce(async () => {
-                if (this.isPaused) {
-                    return
-                }
-                const transcriptionHistoryStr = this.transcriptionHistory.join("\n");
-                const transcriptionLatestStr = this.transcriptionLatest.join("\n");
-                this.transcriptionLatest = [];
-                const event = await this.invoke(
-                    transcriptionHistoryStr,
-                    transcriptionLatestStr,
-                    this.previousAnswer,
-                    this.previousAnswerJson,
-                );
-                if (event) {
-                    this.previousAnswer = event.answer;
-                    this.previousAnswerJson = event.answerJson || null;
-                }
-            }, intervalInSec * 1000, true);
+            this.debouncedInvoke = debounce(() => this.invokeLatest(), intervalInSec * 1000, true);
+        }
+    }
+
+    public isTriggered(): boolean {
+        return this.hasTrigger;
+    }
+
+    // Rust checks the trigger against transcription as it comes in and fires agent-triggered
+    private watchTrigger() {
+        if (!this.agentName) {
+            return;
+        }
+        const trigger = this.agent?.trigger;
+        const request = trigger
+            ? invoke('watch_agent_trigger', {agentName: this.agentName, trigger})
+            : invoke('unwatch_agent_trigger', {agentName: this.agentName});
+        request.catch(e => Events.get().showError(`Failed to watch trigger of agent ${this.agentName}: ${e}`));
+    }
+
+    // Answers what was said since the last answer right away instead of waiting for the interval
+    public async triggerNow() {
+        if (!this.transcriptionUnsubscribe) {
+            return;
+        }
+        await this.invokeLatest();
+    }
+
+    private async invokeLatest() {
+        if (this.isPaused) {
+            return
+        }
+        const transcriptionHistory = [...this.transcriptionHistory];
+        const transcriptionLatestStr = this.transcriptionLatest.join("\n");
+        const transcriptionSinceBookmarkStr = this.transcriptionHistory.slice(this.bookmarkIndex).join("\n");
+        this.transcriptionLatest = [];
+        const transcriptionHistoryStr = transcriptionLatestStr
+            ? await this.condenseHistory(transcriptionHistory, transcriptionLatestStr)
+            : transcriptionHistory.join("\n");
+        const relatedMeetingsStr = this.usesRelatedMeetings && transcriptionLatestStr
+            ? await this.findRelatedMeetings(transcriptionLatestStr)
+            : '';
+        const event = await this.invoke(
+            transcriptionHistoryStr,
+            transcriptionLatestStr,
+            this.previousAnswer,
+            this.previousAnswerJson,
+            transcriptionSinceBookmarkStr,
+            relatedMeetingsStr,
+        );
+        if (event) {
+            this.previousAnswer = event.answer;
+            this.previousAnswerJson = event.answerJson || null;
+            // Kept with the meeting record so answers can be searched later
+            invoke('record_agent_output', {
+                agentName: event.agentName,
+                text: event.answer,
+                json: event.answerJson,
+            }).catch(e => console.error(`Failed to record agent output: ${e}`));
+        }
+    }
+
+    // History within the budget of the agent and what the context of the model leaves next to the prompt
+    private async condenseHistory(lines: string[], latest: string): Promise<string> {
+        if (!this.agent) {
+            return lines.join("\n");
+        }
+        try {
+            this.condensedHistory = await invoke<CondensedHistory>('condense_transcription_history', {
+                agent: this.agent,
+                lines,
+                latest,
+                previous: this.condensedHistory,
+            });
+            return this.condensedHistory.text;
+        } catch (e) {
+            // Shown once, the agent keeps working with all of the history
+            if (!this.condenseHistoryErrorShown) {
+                this.condenseHistoryErrorShown = true;
+                await Events.get().showError(`Failed to condense transcription history: ${e}`);
+            }
+            return lines.join("\n");
+        }
+    }
+
+    // What was said in earlier meetings that is closest to what is being said now
+    private async findRelatedMeetings(query: string): Promise<string> {
+        try {
+            const hits = await semanticSearch(query, {excludeRunningSession: true});
+            return hits
+                .map(hit => `[${new Date(hit.sessionStartedAt + hit.atMs).toLocaleString()}] ${hit.speaker}: ${hit.text}`)
+                .join("\n");
+        } catch (e) {
+            // Shown once, e.g. when the embedding model is not pulled, the agent keeps working without
+            if (!this.relatedMeetingsErrorShown) {
+                this.relatedMeetingsErrorShown = true;
+                await Events.get().showError(`Failed to find related meetings: ${e}`);
+            }
+            return '';
         }
     }
 
@@ -195,15 +354,21 @@ export class Prompter {
         transcriptionLatestStr: string,
         previousAnswer: string | null,
         previousAnswerJson: object | null,
+        // All history if there is no bookmark
+        transcriptionSinceBookmarkStr: string = transcriptionHistoryStr,
+        relatedMeetingsStr: string = '',
     ): Promise<LlmResponseEvent | null> {
-        if (!transcriptionLatestStr || !this.agentName || !this.template) {
+        if ((!transcriptionLatestStr && !this.inputsChanged) || !this.agentName || !this.template) {
             return null
         }
+        this.inputsChanged = false;
         const prompt = this.template(this.getTemplateInput(
             transcriptionHistoryStr,
             transcriptionLatestStr,
             previousAnswer,
             previousAnswerJson,
+            transcriptionSinceBookmarkStr,
+            relatedMeetingsStr,
         ), {
             helpers: HandlebarHelpers,
         });
@@ -249,12 +414,18 @@ export class Prompter {
         transcriptionLatest: string,
         previousAnswer: string | null,
         previousAnswerJson: object | null,
+        transcriptionSinceBookmark: string,
+        relatedMeetings: string,
     ): object {
         // If you modify this, document it in AppAgentEdit.tsx
         return {
             transcription: {
                 all: transcriptionHistory,
-                latest: transcriptionLatest
+                latest: transcriptionLatest,
+                sinceBookmark: transcriptionSinceBookmark,
+            },
+            meetings: {
+                related: relatedMeetings,
             },
             answer: {
                 previous: {
@@ -262,6 +433,7 @@ export class Prompter {
                     json: previousAnswerJson || null
                 },
             },
+            inputs: this.inputs,
         }
     }
 }
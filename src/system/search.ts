@@ -0,0 +1,37 @@
+import {invoke} from "@tauri-apps/api/core";
+
+export type SearchFilters = {
+    // Unix time in milliseconds
+    from?: number;
+    to?: number;
+    // Host, Guest or agent names
+    speakers?: string[];
+    // Only look at earlier meetings
+    excludeRunningSession?: boolean;
+    limit?: number;
+};
+
+export type SearchHit = {
+    sessionId: string;
+    sessionStartedAt: number; // Unix time in milliseconds
+    entryIndex: number; // Position in the session timeline
+    atMs: number; // Since the session started
+    speaker: string;
+    kind: 'transcript' | 'note' | 'bookmark' | 'agentOutput';
+    snippet: { text: string, highlighted: boolean }[];
+};
+
+// Words must all be present, words in double quotes as a phrase, e.g. "pricing change" Q3
+export const searchTranscripts = async (query: string, filters?: SearchFilters): Promise<SearchHit[]> => {
+    return await invoke<SearchHit[]>('search_transcripts', {query, filters: filters || null});
+};
+
+export type SemanticHit = Omit<SearchHit, 'snippet'> & {
+    text: string;
+    score: number; // Cosine similarity, higher is closer
+};
+
+// Closest in meaning rather than wording, limited to search.relatedTopK in ollisten.yaml by default
+export const semanticSearch = async (query: string, filters?: SearchFilters): Promise<SemanticHit[]> => {
+    return await invoke<SemanticHit[]>('semantic_search', {query, filters: filters || null});
+};
@@ -0,0 +1,68 @@
+import {invoke} from "@tauri-apps/api/core";
+
+export type ActionItem = {
+    description: string;
+    owner: string | null;
+    dueDate: string | null; // As said in the meeting
+};
+
+export type MeetingSummary = {
+    summary: string;
+    decisions: string[];
+    actionItems: ActionItem[];
+};
+
+// Also saved with the session as its summaries
+export type MeetingSummaryReadyEvent = {
+    type: 'meeting-summary-ready';
+    sessionId: string;
+    summaries: Array<{
+        agentName: string;
+        summary: MeetingSummary | null;
+        error: string | null;
+    }>;
+};
+
+export type ExportFormat = 'srt' | 'webVtt' | 'markdown' | 'text' | 'json';
+
+export type ExportedSession = {
+    fileName: string; // Suggested name to save the content under
+    content: string;
+};
+
+// JSON can be imported back as is, the other formats are for reading or subtitles
+export const exportSession = async (sessionId: string, format: ExportFormat): Promise<ExportedSession> => {
+    return await invoke<ExportedSession>('export_session', {sessionId, format});
+};
+
+export type ImportFormat = 'srt' | 'webVtt' | 'json';
+
+// Saves a transcript of another tool as a session, the format is guessed from the content if not given
+export const importSession = async (path: string, format?: ImportFormat): Promise<{ id: string }> => {
+    return await invoke<{ id: string }>('import_session', {path, format: format || null});
+};
+
+// Instant sends the whole session at once, a factor of 10 plays it ten times as fast
+export type ReplaySpeed = { factor: number } | 'instant';
+
+export type ReplayRecord = {
+    id: string;
+    sessionId: string;
+    speed: ReplaySpeed;
+    startedAt: number; // Unix time in milliseconds
+    original: Array<object>; // Agent outputs of the session as it happened
+    replayed: Array<object>; // Agent outputs of the replay
+};
+
+// Sends a saved session to the running agents again, resolves with the replay id
+export const replaySession = async (sessionId: string, speed: ReplaySpeed): Promise<string> => {
+    return await invoke<string>('replay_session', {sessionId, speed});
+};
+
+export const stopReplay = async (): Promise<void> => {
+    await invoke('stop_replay');
+};
+
+export const getReplay = async (replayId: string): Promise<ReplayRecord> => {
+    return await invoke<ReplayRecord>('get_replay', {replayId});
+};
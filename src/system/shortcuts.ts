@@ -0,0 +1,12 @@
+/*
+ * Rust events for global shortcuts the client acts on
+ */
+export type ShortcutAction = 'toggleTranscription' | 'pauseAgents' | 'triggerAgents' | 'bookmark' | 'toggleHostMute';
+export type ShortcutPressedEvent = {
+    type: 'shortcut-pressed';
+    action: ShortcutAction;
+};
+export type AgentsPauseRequestedEvent = {
+    type: 'agents-pause-requested';
+    paused: boolean;
+};
@@ -6,8 +6,43 @@ import {randomUuid} from "../util/idUtil.ts";
 export type DeviceOption = {
     name: string;
     id: number;
+    uid: string;
+    host: string;
+    channels: number;
+    defaultSampleRate: number;
+    isDefault: boolean;
 }
 
+export type Vocabulary = {
+    // Terms given to Whisper as its initial prompt, the built in Whisper takes no prompt and only fixes their spelling
+    terms?: string[];
+    // Correct spelling mapped to the ways it tends to be misheard
+    replacements?: { [replacement: string]: string[] };
+    // From 0 (exact matches only) to 1
+    fuzziness?: number | null;
+};
+
+// Engine turning speech into text, transcription.backend in ollisten.yaml
+export type SpeechToTextBackend = {
+    type: 'Whisper';
+} | {
+    type: 'WhisperCpp';
+    // GGML model file, relative paths are resolved against ~/.ollisten/models
+    modelPath: string;
+} | {
+    type: 'ExternalHttp';
+    // Base of an OpenAI compatible server, e.g. http://localhost:8000
+    baseUrl: string;
+    model: string;
+    apiKey?: string | null;
+};
+
+// Which mode and agents the vocabulary is loaded for, all agents if agentNames is not given
+export type VocabularyScope = {
+    modeId?: string;
+    agentNames?: string[];
+};
+
 export enum DeviceSource {
     Host,
     Guest,
@@ -41,14 +76,44 @@ export type LoadingProgressEvent = {
 };
 export type TranscriptionStartedEvent = {
     type: 'TranscriptionStarted';
-    deviceName: string;
+    deviceId: number;
+    deviceUid: string;
 };
 export type TranscriptionDataEvent = {
     type: 'TranscriptionData';
     deviceId: number,
+    deviceUid: string,
+    speaker: string, // Host or Guest, or as named in a replayed session
     text: string,
     confidence: number,
 };
+export type DevicePausedEvent = {
+    type: 'TranscriptionDevicePaused';
+    deviceId: number,
+};
+export type DeviceResumedEvent = {
+    type: 'TranscriptionDeviceResumed';
+    deviceId: number,
+};
+export type BookmarkedEvent = {
+    type: 'TranscriptionBookmarked';
+    atMs: number, // Since the session started
+    label: string | null,
+};
+export type NoteEvent = {
+    type: 'TranscriptionNote';
+    atMs: number, // Since the session started
+    text: string,
+};
+export type ReplayStartedEvent = {
+    type: 'TranscriptionReplayStarted';
+    replayId: string,
+    sessionId: string,
+};
+export type ReplayFinishedEvent = {
+    type: 'TranscriptionReplayFinished';
+    replayId: string,
+};
 export type ErrorEvent = {
     type: 'TranscriptionError';
     message: string,
@@ -74,7 +139,7 @@ export type TranscriptionModelOptionsUpdatedEvent = {
 };
 export type DeviceInputOptionSelectedEvent = {
     type: 'device-input-option-selected';
-    option: number;
+    option: string; // Device UID
 };
 export type DeviceInputOptionsUpdatedEvent = {
     type: 'device-input-options-updated';
@@ -91,6 +156,7 @@ export class Transcription {
     private static instance: Transcription | null = null
     private unsubscribe: Unsubscribe | null = null;
     private readonly subscriberName = randomUuid();
+    private vocabularyScope: VocabularyScope = {};
 
     static get = () => {
         if (!Transcription.instance) {
@@ -147,9 +213,10 @@ export class Transcription {
 
         // Setup event handler
         const unsubscribeEventHandler = Events.get().subscribe([
-            'TranscriptionDownloadProgress', 'TranscriptionLoadingProgress', 'TranscriptionStarted', 'TranscriptionData', 'TranscriptionStopped'
+            'TranscriptionDownloadProgress', 'TranscriptionLoadingProgress', 'TranscriptionStarted', 'TranscriptionData', 'TranscriptionStopped',
+            'TranscriptionReplayStarted', 'TranscriptionReplayFinished'
         ], (
-            event: DownloadProgressEvent | LoadingProgressEvent | TranscriptionDataEvent | TranscriptionStartedEvent | ErrorEvent | StoppedEvent
+            event: DownloadProgressEvent | LoadingProgressEvent | TranscriptionDataEvent | TranscriptionStartedEvent | ErrorEvent | StoppedEvent | ReplayStartedEvent | ReplayFinishedEvent
         ) => {
             switch (event.type) {
                 case "TranscriptionDownloadProgress":
@@ -163,10 +230,12 @@ export class Transcription {
                     }
                     break;
                 case "TranscriptionStarted":
+                case "TranscriptionReplayStarted":
                 case "TranscriptionData":
                     this.setStatus(Status.TranscriptionStarted);
                     break;
                 case "TranscriptionStopped":
+                case "TranscriptionReplayFinished":
                     this.setStatus(Status.Stopped);
                     break;
                 default:
@@ -207,8 +276,8 @@ export class Transcription {
 
     public canStart(): {
         valid: true,
-        deviceIdHost: number | null,
-        deviceIdGuest: number | null,
+        deviceUidHost: string | null,
+        deviceUidGuest: string | null,
     } | {
         valid: false,
         error: string,
@@ -219,7 +288,7 @@ export class Transcription {
                 error: 'No transcription model selected',
             };
         }
-        if (this.deviceInputId === null
+        if (this.deviceInputUid === null
             && this.deviceOutput === null) {
             return {
                 valid: false,
@@ -228,8 +297,8 @@ export class Transcription {
         }
         return {
             valid: true,
-            deviceIdGuest: this.deviceOutput?.id || null,
-            deviceIdHost: this.deviceInputId,
+            deviceUidGuest: this.deviceOutput?.uid || null,
+            deviceUidHost: this.deviceInputUid,
         }
     }
 
@@ -244,7 +313,10 @@ export class Transcription {
         }
     }
 
-    public async startTranscription() {
+    public async startTranscription(vocabularyScope?: VocabularyScope) {
+        if (vocabularyScope) {
+            this.vocabularyScope = vocabularyScope;
+        }
         let startData = this.canStart();
         if (!startData.valid) {
             this.onError(startData.error);
@@ -254,10 +326,13 @@ export class Transcription {
         try {
             await invoke('start_transcription', {
                 modelType: this.transcriptionModelName,
+                // UIDs stay the same when devices reconnect or get renumbered
                 deviceIds: [
-                    ...(startData.deviceIdHost ? [startData.deviceIdHost] : []),
-                    ...(startData.deviceIdGuest ? [startData.deviceIdGuest] : []),
+                    ...(startData.deviceUidHost ? [startData.deviceUidHost] : []),
+                    ...(startData.deviceUidGuest ? [startData.deviceUidGuest] : []),
                 ],
+                modeId: this.vocabularyScope.modeId,
+                agentNames: this.vocabularyScope.agentNames,
             });
         } catch (e) {
             this.onError(`Failed to start transcription: ${e}`);
@@ -273,8 +348,42 @@ export class Transcription {
         }
     }
 
-    public deviceIdToSource(deviceId: number): DeviceSource {
-        if (this.deviceOutput?.id === deviceId) {
+    // Mutes a single device without stopping the others, e.g. the Host mic during a side conversation
+    public async pauseDevice(deviceUid: string) {
+        try {
+            await invoke('pause_device', {device: deviceUid});
+        } catch (e) {
+            this.onError(`Failed to pause device: ${e}`);
+        }
+    }
+
+    public async resumeDevice(deviceUid: string) {
+        try {
+            await invoke('resume_device', {device: deviceUid});
+        } catch (e) {
+            this.onError(`Failed to resume device: ${e}`);
+        }
+    }
+
+    // Marks the current moment of the meeting, agents can look at what was said since
+    public async addBookmark(label?: string) {
+        try {
+            await invoke('add_bookmark', {label: label || null});
+        } catch (e) {
+            this.onError(`Failed to add bookmark: ${e}`);
+        }
+    }
+
+    public async addNote(text: string) {
+        try {
+            await invoke('add_note', {text});
+        } catch (e) {
+            this.onError(`Failed to add note: ${e}`);
+        }
+    }
+
+    public deviceUidToSource(deviceUid: string): DeviceSource {
+        if (this.deviceOutput?.uid === deviceUid) {
             return DeviceSource.Guest;
         }
         return DeviceSource.Host;
@@ -338,7 +447,7 @@ export class Transcription {
      */
 
     private deviceInputOptions: DeviceOption[] = [];
-    private deviceInputId: number | null = null;
+    private deviceInputUid: string | null = null;
 
     public async fetchInputDevices() {
         try {
@@ -350,21 +459,24 @@ export class Transcription {
             }
             this.deviceInputOptions = response;
             this.onEvent({type: 'device-input-options-updated', options: response});
-            if (this.deviceInputId == null || !this.deviceInputOptions.some(o => o.id === this.deviceInputId)) {
-                var chosenNewId: number | undefined = undefined;
+            if (this.deviceInputUid == null || !this.deviceInputOptions.some(o => o.uid === this.deviceInputUid)) {
+                var chosenNewUid: string | undefined = undefined;
 
-                // Chose one from config if available
+                // Chose one from config if available, by name for configs saved before UIDs were
+                const deviceUidFromConfig = getAppConfig().selectedInputDeviceUid;
                 const deviceNameFromConfig = getAppConfig().selectedInputDeviceName;
-                if (deviceNameFromConfig) {
-                    chosenNewId = response.find(o => o.name === deviceNameFromConfig)?.id;
+                if (deviceUidFromConfig) {
+                    chosenNewUid = response.find(o => o.uid === deviceUidFromConfig)?.uid;
+                } else if (deviceNameFromConfig) {
+                    chosenNewUid = response.find(o => o.name === deviceNameFromConfig)?.uid;
                 }
 
                 // Otherwise choose first on from the options
-                if (!chosenNewId) {
-                    chosenNewId = response[0].id
+                if (!chosenNewUid) {
+                    chosenNewUid = response[0].uid
                 }
 
-                this.selectInputDeviceId(chosenNewId);
+                this.selectInputDeviceUid(chosenNewUid);
             }
         } catch (e) {
             this.onError(`Failed to get microphone/input devices: ${e}`);
@@ -375,17 +487,19 @@ export class Transcription {
         return this.deviceInputOptions;
     }
 
-    public getInputDeviceId(): number | null {
-        return this.deviceInputId;
+    public getInputDeviceUid(): string | null {
+        return this.deviceInputUid;
     }
 
-    public selectInputDeviceId(newId: number): void {
-        this.deviceInputId = newId;
+    public selectInputDeviceUid(newUid: string): void {
+        this.deviceInputUid = newUid;
         this.restartTranscriptionIfRunning();
-        this.onEvent({type: 'device-input-option-selected', option: newId});
+        this.onEvent({type: 'device-input-option-selected', option: newUid});
 
-        const deviceName = this.getInputDeviceOptions().find(o => o.id === newId)?.name;
-        setAppConfig(c => c.selectedInputDeviceName = deviceName);
+        setAppConfig(c => {
+            c.selectedInputDeviceUid = newUid;
+            c.selectedInputDeviceName = undefined;
+        });
     }
 
     /*
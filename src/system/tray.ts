@@ -0,0 +1,9 @@
+/*
+ * Rust events for tray menu items the main window acts on
+ */
+export type TrayActionRequestedEvent = {
+    type: 'tray-action-requested';
+    action: 'startMode' | 'stopMode' | 'startAgent' | 'stopAgent';
+    // Mode id or agent name
+    name: string;
+};
@@ -81,18 +81,22 @@ export default function useModes(): {
 
     const agentNames = useMemo(() => Object.keys(agentByName), [agentByName]);
 
-    const startAgents = useCallback((agentNames?: string[]) => {
+    const startAgents = useCallback((agentNames?: string[], modeId?: string) => {
         if (agentNames != undefined && !agentNames.length) {
             return;
         }
-        Transcription.get().startTranscription()
+        Transcription.get().startTranscription({
+            modeId,
+            // Agents already running keep their vocabulary
+            agentNames: agentNames && [...new Set([...AgentManager.get().getRunningAgentNames(), ...agentNames])],
+        })
             .catch(e => Events.get().showError(`Failed to start transcription: ${e}`));
         AgentManager.get().managerStart(agentNames)
             .then(() => forceRender())
             .catch(e => Events.get().showError(`Failed to start agents ${agentNames}: ${e}`));
     }, []);
     const startAll = useCallback(() => startAgents(), [startAgents]);
-    const startMode = useCallback((modeId: string) => startAgents(appConfig.modes?.[modeId].agents || []), [appConfig, startAgents]);
+    const startMode = useCallback((modeId: string) => startAgents(appConfig.modes?.[modeId].agents || [], modeId), [appConfig, startAgents]);
     const startAgent = useCallback((agentName: string) => startAgents([agentName]), [startAgents]);
 
     const stopAgents = useCallback((agentNames?: string[]) => {
@@ -3,6 +3,8 @@ import {invoke} from "@tauri-apps/api/core";
 import {Events} from "../system/events.ts";
 import {useForceRender} from "./useForceRender.ts";
 import debounce from "./debounce.ts";
+import {SpeechToTextBackend, Vocabulary} from "../system/transcription.ts";
+import {ShortcutAction} from "../system/shortcuts.ts";
 
 export type AppConfig = Partial<{
     windowProps: {
@@ -17,11 +19,36 @@ export type AppConfig = Partial<{
         [modeId: string]: {
             label: string;
             agents: string[];
+            vocabulary?: Vocabulary;
         }
     };
     selectedLlmModelName: string;
+    selectedInputDeviceUid: string;
+    // Replaced by selectedInputDeviceUid, only read to keep the selection of older configs
     selectedInputDeviceName: string;
     selectedTranscriptionModelName: string;
+    transcription: Partial<{
+        // Built in Whisper unless set
+        backend: SpeechToTextBackend;
+        // Pass the end of the previous chunk's text to the next decode, on by default, the built in Whisper takes no prompt and ignores it
+        carryOverContext: boolean;
+        // Audio repeated at the start of the next chunk when long speech is cut, off by default
+        chunkOverlapMs: number;
+        // Noise suppression strength from 0 (off) to 1 by device UID
+        noiseSuppression: { [deviceUid: string]: number };
+    }>;
+    // System-wide shortcuts such as CmdOrCtrl+Alt+T, null disables one, changes apply on restart
+    shortcuts: Partial<{ [action in ShortcutAction]: string | null }>;
+    search: Partial<{
+        // Ollama model embedding meetings for semantic search, nomic-embed-text by default
+        embeddingModel: string;
+        // Snippets from earlier meetings given to agents via {{ meetings.related }}, 5 by default
+        relatedTopK: number;
+    }>;
+    llm: Partial<{
+        // Tokens asked of Ollama for models whose Modelfile sets no num_ctx, 4096 by default
+        contextLength: number;
+    }>;
 }>;
 
 export type AppConfigChangedEvent = {